@@ -0,0 +1,234 @@
+use crate::gb::handler::cmd::CmdControl;
+use crate::gb::handler::manscdp::AlarmNotify;
+use crate::general::model::{AlarmInfo, TimeRange};
+use crate::general::{AlarmConf, AlarmLinkage};
+use crate::service::{callback, handler};
+use crate::store::entity::GbsDeviceAlarm;
+use crate::store::snap::Snap;
+use crate::utils::se_token;
+use common::chrono::Local;
+use common::dashmap::DashMap;
+use common::exception::GlobalResult;
+use common::log::{debug, error, warn};
+use common::once_cell::sync::Lazy;
+use common::tokio::time::{sleep, Instant};
+use std::time::Duration;
+
+//告警去重：(device_id,channel_id,method,alarm_type) -> 最近一次受理时间
+static ALARM_RECENT: Lazy<DashMap<(String, String, u8, u8), Instant>> = Lazy::new(DashMap::new);
+//去重记录超过该数量时清理过期项
+const RECENT_PRUNE: usize = 4096;
+//推送重试间隔基数(秒),按2的幂退避
+const PUSH_BACKOFF: u64 = 2;
+//告警后片段录完后再等待的秒数,留给设备写入录像
+const CLIP_DELAY: u64 = 10;
+
+pub struct AlarmPipeline;
+
+impl AlarmPipeline {
+    //告警受理：等级过滤、去重、入库,推送异步进行不阻塞SIP处理
+    pub async fn feed(device_id: &str, alarm: AlarmNotify) {
+        let conf = AlarmConf::get_alarm_conf();
+        if !conf.enable {
+            return;
+        }
+        let mut info = AlarmInfo::from(alarm);
+        info.deviceId = device_id.to_string();
+        if !Self::accept(conf.priority, info.priority) {
+            debug!(
+                "告警等级过滤: device_id = {},channel_id = {},priority = {}",
+                device_id, &info.channelId, info.priority
+            );
+            return;
+        }
+        let window = Duration::from_secs(conf.dedup_window);
+        if Self::duplicate(&info, window, Instant::now()) {
+            debug!(
+                "告警重复: device_id = {},channel_id = {},method = {},alarm_type = {}",
+                device_id, &info.channelId, info.method, info.alarmType
+            );
+            return;
+        }
+        match GbsDeviceAlarm::build(&info).insert_gbs_device_alarm().await {
+            Ok(id) => {
+                info.alarmId = Some(id);
+                if let Some(rule) = conf.linkage(info.method, info.alarmType) {
+                    let channel_id = Some(info.channelId.clone())
+                        .filter(|id| !id.is_empty())
+                        .unwrap_or_else(|| device_id.to_string());
+                    let alarm_ts = TimeRange::parse_gb_time(&info.timeStr)
+                        .unwrap_or(Local::now().timestamp() as u32);
+                    common::tokio::spawn(Self::linkage(
+                        id,
+                        device_id.to_string(),
+                        channel_id,
+                        alarm_ts,
+                        rule,
+                    ));
+                }
+            }
+            Err(err) => error!("告警入库失败: device_id = {},{err}", device_id),
+        }
+        if conf.push_url.as_ref().is_some_and(|url| !url.is_empty()) {
+            common::tokio::spawn(Self::push(info, conf.push_retry));
+        }
+    }
+
+    //1为最高级,未携带等级按最低级4处理
+    fn accept(conf_priority: u8, priority: u8) -> bool {
+        let priority = if priority == 0 { 4 } else { priority };
+        priority <= conf_priority
+    }
+
+    fn duplicate(info: &AlarmInfo, window: Duration, now: Instant) -> bool {
+        if window.is_zero() {
+            return false;
+        }
+        if ALARM_RECENT.len() > RECENT_PRUNE {
+            ALARM_RECENT.retain(|_, last| now.saturating_duration_since(*last) < window);
+        }
+        let key = (
+            info.deviceId.clone(),
+            info.channelId.clone(),
+            info.method,
+            info.alarmType,
+        );
+        let mut duplicate = false;
+        ALARM_RECENT
+            .entry(key)
+            .and_modify(|last| {
+                if now.saturating_duration_since(*last) < window {
+                    duplicate = true;
+                } else {
+                    *last = now;
+                }
+            })
+            .or_insert(now);
+        duplicate
+    }
+
+    //告警联动：抓拍并下载告警前后录像,文件关联告警ID
+    async fn linkage(
+        alarm_id: i64,
+        device_id: String,
+        channel_id: String,
+        alarm_ts: u32,
+        rule: &'static AlarmLinkage,
+    ) {
+        if *rule.get_snap_num() > 0 {
+            if let Err(err) = Self::snapshot(alarm_id, &device_id, &channel_id, rule).await {
+                warn!("告警联动抓拍失败: alarm_id = {alarm_id},{err}");
+            }
+        }
+        if *rule.get_clip_pre() == 0 && *rule.get_clip_post() == 0 {
+            return;
+        }
+        let st = alarm_ts.saturating_sub(*rule.get_clip_pre());
+        let et = alarm_ts.saturating_add(*rule.get_clip_post());
+        //等待设备录完告警后片段
+        let wait = (et as i64 + CLIP_DELAY as i64 - Local::now().timestamp()).max(0);
+        sleep(Duration::from_secs(wait as u64)).await;
+        if let Err(err) = handler::alarm_clip(
+            alarm_id,
+            &device_id,
+            &channel_id,
+            st,
+            et,
+            *rule.get_clip_mode(),
+        )
+        .await
+        {
+            error!("告警联动录像下载失败: alarm_id = {alarm_id},{err}");
+        }
+    }
+
+    //上传地址携带告警ID,由令牌签名
+    async fn snapshot(
+        alarm_id: i64,
+        device_id: &String,
+        channel_id: &String,
+        rule: &AlarmLinkage,
+    ) -> GlobalResult<()> {
+        let Some(push_url) = Snap::get_snap_by_conf().get_push_url() else {
+            warn!("告警联动抓拍: 未配置server.snap.push_url");
+            return Ok(());
+        };
+        let (_, session_id) = se_token::build_token_session_id(device_id, channel_id)?;
+        let token = se_token::build_alarm_token(&session_id, alarm_id);
+        let url = format!("{push_url}?token={token}&alarm_id={alarm_id}");
+        CmdControl::snapshot_image(
+            device_id,
+            channel_id,
+            *rule.get_snap_num(),
+            *rule.get_snap_interval(),
+            &url,
+            &session_id,
+        )
+        .await
+    }
+
+    async fn push(info: AlarmInfo, retry: u8) {
+        for attempt in 0..=retry {
+            match callback::call_alarm_info(&info).await {
+                Ok(true) => return,
+                //推送地址已接收但未确认成功,不再重试
+                Ok(false) => {
+                    warn!("告警推送未被接受: alarm_id = {:?}", info.alarmId);
+                    return;
+                }
+                Err(_) if attempt < retry => {
+                    sleep(Duration::from_secs(PUSH_BACKOFF << attempt)).await;
+                }
+                Err(_) => {}
+            }
+        }
+        error!(
+            "告警推送失败,已重试{}次: alarm_id = {:?},device_id = {},channel_id = {}",
+            retry, info.alarmId, &info.deviceId, &info.channelId
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept() {
+        assert!(AlarmPipeline::accept(4, 4));
+        assert!(AlarmPipeline::accept(4, 0));
+        assert!(AlarmPipeline::accept(2, 1));
+        assert!(!AlarmPipeline::accept(2, 3));
+        assert!(!AlarmPipeline::accept(2, 0));
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let info = AlarmInfo {
+            method: 5,
+            alarmType: 2,
+            deviceId: "34020000001110000009".to_string(),
+            channelId: "34020000001340000001".to_string(),
+            ..Default::default()
+        };
+        let window = Duration::from_secs(30);
+        let now = Instant::now();
+        assert!(!AlarmPipeline::duplicate(&info, window, now));
+        assert!(AlarmPipeline::duplicate(
+            &info,
+            window,
+            now + Duration::from_secs(10)
+        ));
+        let other = AlarmInfo {
+            alarmType: 3,
+            ..info
+        };
+        assert!(!AlarmPipeline::duplicate(&other, window, now));
+        assert!(!AlarmPipeline::duplicate(
+            &other,
+            window,
+            now + Duration::from_secs(31)
+        ));
+        assert!(!AlarmPipeline::duplicate(&other, Duration::ZERO, now));
+    }
+}
@@ -1,9 +1,14 @@
+use crate::gb::handler::cascade::{NodeKind, Platform, ShareItem};
+use crate::gb::handler::dialog::Dialog;
 use crate::gb::handler::events::event::Ident;
 use crate::gb::handler::parser;
 use crate::gb::shared::rw::RWSession;
 use crate::gb::SessionConf;
-use crate::general::model::{MediaAddress, PtzControlModel, StreamMode, TimeRange};
-use crate::store::entity::GbsOauth;
+use crate::general::model::{
+    AlarmSubscribeModel, BroadcastInfo, DeviceConfigModel, DeviceControlCmd, DragZoom,
+    MediaAddress, PtzCmd, PtzControlModel, StreamMode, SubscribeDialog, TimeRange,
+};
+use crate::store::entity::{GbsCascadeNode, GbsDeviceChannel, GbsOauth};
 use crate::store::mapper;
 use common::anyhow::anyhow;
 use common::chrono::Local;
@@ -27,6 +32,11 @@ use std::net::SocketAddr;
 use uuid::Uuid;
 
 const GB_VERSION: &str = "3.0";
+pub const MANSCDP_CONTENT_TYPE: &str = "Application/MANSCDP+xml";
+//OPTIONS应答声明支持的方法与消息体类型
+pub const ALLOW_METHODS: &str =
+    "INVITE, ACK, BYE, CANCEL, INFO, MESSAGE, NOTIFY, SUBSCRIBE, OPTIONS, REGISTER";
+pub const ACCEPT_TYPES: &str = "Application/MANSCDP+xml, Application/SDP";
 
 pub struct ResponseBuilder;
 
@@ -126,6 +136,96 @@ impl ResponseBuilder {
         .into())
     }
 
+    pub fn build_options_response(
+        req: &Request,
+        socket_addr: &SocketAddr,
+    ) -> GlobalResult<SipMessage> {
+        let mut response_header = Self::build_response_header(req, socket_addr)?;
+        response_header.push(rsip::headers::Allow::new(ALLOW_METHODS).into());
+        response_header.push(rsip::headers::Accept::new(ACCEPT_TYPES).into());
+        Ok(rsip::Response {
+            status_code: 200.into(),
+            headers: response_header,
+            version: rsip::Version::V2,
+            body: Default::default(),
+        }
+        .into())
+    }
+
+    pub fn build_status_response(
+        req: &Request,
+        socket_addr: &SocketAddr,
+        status_code: u16,
+    ) -> GlobalResult<SipMessage> {
+        let response_header = Self::build_response_header(req, socket_addr)?;
+        Ok(rsip::Response {
+            status_code: status_code.into(),
+            headers: response_header,
+            version: rsip::Version::V2,
+            body: Default::default(),
+        }
+        .into())
+    }
+
+    //MANSCDP消息体拒绝：415需携带Accept声明支持的消息体类型
+    pub fn build_manscdp_reject_response(
+        req: &Request,
+        socket_addr: &SocketAddr,
+        status_code: u16,
+    ) -> GlobalResult<SipMessage> {
+        let mut response_header = Self::build_response_header(req, socket_addr)?;
+        if status_code == 415 {
+            response_header.push(rsip::headers::Accept::new(MANSCDP_CONTENT_TYPE).into());
+        }
+        Ok(rsip::Response {
+            status_code: status_code.into(),
+            headers: response_header,
+            version: rsip::Version::V2,
+            body: Default::default(),
+        }
+        .into())
+    }
+
+    //语音广播：应答设备音频INVITE,以流媒体节点地址发送音频
+    pub fn build_broadcast_ok_response(
+        req: &Request,
+        socket_addr: &SocketAddr,
+        info: &BroadcastInfo,
+    ) -> GlobalResult<Response> {
+        let sdp = SdpBuilder::broadcast(info);
+        Self::build_sdp_ok_response(req, socket_addr, &info.source_id, sdp.into_bytes())
+    }
+
+    //INVITE应答200：Contact为本端编码,携带应答SDP
+    pub fn build_sdp_ok_response(
+        req: &Request,
+        socket_addr: &SocketAddr,
+        contact_id: &str,
+        sdp: Vec<u8>,
+    ) -> GlobalResult<Response> {
+        let mut response_header = Self::build_response_header(req, socket_addr)?;
+        response_header
+            .retain(|header| !matches!(header, Header::ContentLength(_) | Header::Contact(_)));
+        let conf = SessionConf::get_session_by_conf();
+        response_header.push(
+            rsip::headers::Contact::new(format!(
+                "<sip:{}@{}:{}>",
+                contact_id,
+                conf.get_wan_ip(),
+                conf.get_wan_port()
+            ))
+            .into(),
+        );
+        response_header.push(rsip::headers::ContentType::new("Application/SDP").into());
+        response_header.push(rsip::headers::ContentLength::from(sdp.len() as u32).into());
+        Ok(rsip::Response {
+            status_code: 200.into(),
+            headers: response_header,
+            version: rsip::Version::V2,
+            body: sdp,
+        })
+    }
+
     fn build_response_header(
         req: &Request,
         socket_addr: &SocketAddr,
@@ -205,15 +305,127 @@ impl RequestBuilder {
 
         Self::build_message_request(None, device_id, xml).await
     }
+    pub async fn query_device_status(
+        device_id: &String,
+        sn: u32,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::query_device_status(device_id, sn);
+        Self::build_message_request(None, device_id, xml).await
+    }
+    pub async fn query_config_download(
+        device_id: &String,
+        sn: u32,
+        config_types: &[String],
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::query_config_download(device_id, sn, config_types);
+        Self::build_message_request(None, device_id, xml).await
+    }
+    pub async fn control_device_config(
+        model: &DeviceConfigModel,
+        sn: u32,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::control_device_config(model, sn);
+        Self::build_message_request(model.get_channel_id().as_ref(), model.get_device_id(), xml)
+            .await
+    }
+    pub async fn control_device(
+        device_id: &String,
+        channel_id_opt: Option<&String>,
+        sn: u32,
+        cmd: &DeviceControlCmd,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::control_device(channel_id_opt.unwrap_or(device_id), sn, cmd);
+        Self::build_message_request(channel_id_opt, device_id, xml).await
+    }
+    pub async fn query_device_catalog_by_sn(
+        device_id: &String,
+        sn: u32,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::query_device_catalog_by_sn(device_id, sn);
+        Self::build_message_request(None, device_id, xml).await
+    }
     pub async fn query_device_catalog(device_id: &String) -> GlobalResult<(Ident, SipMessage)> {
         let xml = XmlBuilder::query_device_catalog(device_id);
 
         Self::build_message_request(None, device_id, xml).await
     }
-    pub async fn subscribe_device_catalog(device_id: &String) -> GlobalResult<(Ident, SipMessage)> {
-        let xml = XmlBuilder::query_device_catalog(device_id);
-
-        Self::build_subscribe_request(device_id, xml).await
+    pub async fn query_record_info(
+        device_id: &String,
+        channel_id_opt: Option<&String>,
+        sn: u32,
+        range: &TimeRange,
+        record_type: &str,
+        secrecy: u8,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::query_record_info(
+            channel_id_opt.unwrap_or(device_id),
+            sn,
+            range,
+            record_type,
+            secrecy,
+        );
+        Self::build_message_request(channel_id_opt, device_id, xml).await
+    }
+    pub async fn notify_broadcast(
+        device_id: &String,
+        channel_id: &String,
+        source_id: &String,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::notify_broadcast(source_id, channel_id);
+        let channel_id_opt = (channel_id != device_id).then_some(channel_id);
+        Self::build_message_request(channel_id_opt, device_id, xml).await
+    }
+    //应用层应答：设备的Query/Control无法处理时回复Result
+    pub async fn response_result(
+        device_id: &String,
+        cmd_type: &str,
+        sn: &str,
+        target_id: &str,
+        result: &str,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::response_result(cmd_type, sn, target_id, result);
+        Self::build_message_request(None, device_id, xml).await
+    }
+    pub fn keepalive_body(device_id: &str, sn: u32) -> String {
+        XmlBuilder::notify_keepalive(device_id, sn)
+    }
+    pub fn catalog_response_body(
+        sn: &str,
+        device_id: &str,
+        sum_num: usize,
+        items: &[ShareItem],
+    ) -> String {
+        XmlBuilder::response_catalog(sn, device_id, sum_num, items)
+    }
+    pub fn device_info_response_body(
+        sn: &str,
+        device_id: &str,
+        name: &str,
+        channel: usize,
+    ) -> String {
+        XmlBuilder::response_device_info(sn, device_id, name, channel)
+    }
+    pub fn device_status_response_body(sn: &str, device_id: &str, online: bool) -> String {
+        XmlBuilder::response_device_status(sn, device_id, online)
+    }
+    pub fn result_response_body(cmd_type: &str, sn: &str, device_id: &str, result: &str) -> String {
+        XmlBuilder::response_result(cmd_type, sn, device_id, result)
+    }
+    pub fn catalog_subscribe_body(device_id: &String) -> String {
+        XmlBuilder::query_device_catalog(device_id)
+    }
+    pub fn alarm_subscribe_body(model: &AlarmSubscribeModel) -> String {
+        XmlBuilder::subscribe_alarm(model)
+    }
+    pub fn mobile_position_subscribe_body(device_id: &String, interval: u32) -> String {
+        XmlBuilder::subscribe_mobile_position(device_id, interval)
+    }
+    //订阅消息体随对话保存,刷新与取消时原样下发
+    pub async fn subscribe(
+        device_id: &String,
+        dialog: &SubscribeDialog,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        Self::build_subscribe_request(device_id, dialog.body.clone(), dialog).await
     }
 
     pub async fn control_snapshot_image(
@@ -228,6 +440,23 @@ impl RequestBuilder {
 
         Self::build_message_request(Some(channel_id), device_id, xml).await
     }
+    pub async fn control_ptz_cmd(
+        device_id: &String,
+        channel_id: &String,
+        cmd: &PtzCmd,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::control_ptz_cmd(channel_id, cmd);
+        Self::build_message_request(Some(channel_id), device_id, xml).await
+    }
+    //PTZCmd指令码原样下发,用于转发上级平台的云台控制
+    pub async fn control_ptz_line(
+        device_id: &String,
+        channel_id: &String,
+        cmd_line: &str,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let xml = XmlBuilder::control_ptz_line(channel_id, cmd_line);
+        Self::build_message_request(Some(channel_id), device_id, xml).await
+    }
     pub async fn control_ptz(
         ptz_control_model: &PtzControlModel,
     ) -> GlobalResult<(Ident, SipMessage)> {
@@ -240,6 +469,184 @@ impl RequestBuilder {
         .await
     }
 
+    //OPTIONS探测设备是否可达
+    pub async fn options(device_id: &String) -> GlobalResult<(Ident, SipMessage)> {
+        let (mut headers, uri) =
+            Self::build_request_header(None, device_id, false, true, None, None).await?;
+        let call_id_str = Uuid::new_v4().as_simple().to_string();
+        headers.push(rsip::headers::CallId::new(&call_id_str).into());
+        let mut rng = rng();
+        let cs_eq_str = format!("{} OPTIONS", rng.random_range(12u8..255u8));
+        headers.push(rsip::headers::CSeq::new(&cs_eq_str).into());
+        headers.push(rsip::headers::Accept::new(ACCEPT_TYPES).into());
+        headers.push(rsip::headers::ContentLength::default().into());
+        let request_msg: SipMessage = Request {
+            method: Method::Options,
+            uri,
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: Default::default(),
+        }
+        .into();
+        let ident = Ident::new(device_id.to_string(), call_id_str, cs_eq_str);
+        Ok((ident, request_msg))
+    }
+
+    //级联注册：同一注册会话复用call_id与from_tag,CSeq递增;expires为0时注销
+    pub fn cascade_register(
+        platform: &Platform,
+        call_id: &str,
+        seq: u32,
+        from_tag: &str,
+        expires: u32,
+        authorization: Option<typed::Authorization>,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let (mut headers, uri) = Self::build_cascade_header(platform, from_tag, None, true)?;
+        headers.retain(|header| !matches!(header, Header::To(_)));
+        headers.push(
+            rsip::headers::To::new(format!(
+                "<sip:{}@{}>",
+                platform.get_local_id(),
+                platform.local_domain()
+            ))
+            .into(),
+        );
+        headers.push(rsip::headers::CallId::new(call_id).into());
+        let cs_eq_str = format!("{} REGISTER", seq);
+        headers.push(rsip::headers::CSeq::new(&cs_eq_str).into());
+        headers.push(rsip::headers::Expires::new(expires.to_string()).into());
+        if let Some(authorization) = authorization {
+            headers.push(authorization.into());
+        }
+        headers.push(Header::Other(
+            String::from("X-GB-Ver"),
+            GB_VERSION.to_string(),
+        ));
+        headers.push(rsip::headers::ContentLength::default().into());
+        let request_msg: SipMessage = Request {
+            method: Method::Register,
+            uri,
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: Default::default(),
+        }
+        .into();
+        let ident = Ident::new(platform.get_id().clone(), call_id.to_string(), cs_eq_str);
+        Ok((ident, request_msg))
+    }
+
+    //级联：向上级平台发送MANSCDP消息(心跳、查询应答)
+    pub fn cascade_message(platform: &Platform, body: String) -> GlobalResult<(Ident, SipMessage)> {
+        let mut rng = rng();
+        let from_tag = rng.random_range(123456789u32..987654321u32).to_string();
+        let (mut headers, uri) = Self::build_cascade_header(platform, &from_tag, None, false)?;
+        let call_id_str = Uuid::new_v4().as_simple().to_string();
+        headers.push(rsip::headers::CallId::new(&call_id_str).into());
+        let cs_eq_str = format!("{} MESSAGE", rng.random_range(12u8..255u8));
+        headers.push(rsip::headers::CSeq::new(&cs_eq_str).into());
+        headers.push(rsip::headers::ContentType::new(MANSCDP_CONTENT_TYPE).into());
+        headers.push(rsip::headers::ContentLength::from(body.len() as u32).into());
+        let request_msg: SipMessage = Request {
+            method: Method::Message,
+            uri,
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: body.into_bytes(),
+        }
+        .into();
+        let ident = Ident::new(platform.get_id().clone(), call_id_str, cs_eq_str);
+        Ok((ident, request_msg))
+    }
+
+    //级联：上级点播对话内的BYE,Request-URI取上级Contact
+    pub fn cascade_bye(
+        platform: &Platform,
+        call_id: &str,
+        seq: u32,
+        from_tag: &str,
+        to_tag: &str,
+    ) -> GlobalResult<(Ident, SipMessage)> {
+        let (mut headers, mut uri) =
+            Self::build_cascade_header(platform, from_tag, Some(to_tag), false)?;
+        headers.push(rsip::headers::CallId::new(call_id).into());
+        Dialog::route(call_id, &mut uri, &mut headers);
+        let cs_eq_str = format!("{} BYE", seq);
+        headers.push(rsip::headers::CSeq::new(&cs_eq_str).into());
+        headers.push(rsip::headers::ContentLength::default().into());
+        let request_msg: SipMessage = Request {
+            method: Method::Bye,
+            uri,
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: Default::default(),
+        }
+        .into();
+        let ident = Ident::new(platform.get_id().clone(), call_id.to_string(), cs_eq_str);
+        Ok((ident, request_msg))
+    }
+
+    /// 构建发往上级平台的请求头：From为本平台在上级的编码
+    fn build_cascade_header(
+        platform: &Platform,
+        from_tag: &str,
+        to_tag: Option<&str>,
+        contact: bool,
+    ) -> GlobalResult<(rsip::Headers, Uri)> {
+        let conf = SessionConf::get_session_by_conf();
+        let server_ip = conf.get_wan_ip().to_string();
+        let server_port = conf.get_wan_port();
+        let uri = uri::Uri::try_from(format!(
+            "sip:{}@{}",
+            platform.get_id(),
+            platform.get_domain()
+        ))
+        .hand_log(|msg| warn!("{msg}"))?;
+        let mut rng = rng();
+        let mut headers: rsip::Headers = Default::default();
+        headers.push(
+            rsip::headers::Via::new(format!(
+                "SIP/2.0/UDP {}:{};rport;branch=z9hG4bK{}",
+                server_ip,
+                server_port,
+                rng.random_range(123456789u32..987654321u32)
+            ))
+            .into(),
+        );
+        headers.push(
+            rsip::headers::From::new(format!(
+                "<sip:{}@{}>;tag={}",
+                platform.get_local_id(),
+                platform.local_domain(),
+                from_tag
+            ))
+            .into(),
+        );
+        let to = match to_tag {
+            None => format!("<sip:{}@{}>", platform.get_id(), platform.get_domain()),
+            Some(tag) => format!(
+                "<sip:{}@{}>;tag={}",
+                platform.get_id(),
+                platform.get_domain(),
+                tag
+            ),
+        };
+        headers.push(rsip::headers::To::new(to).into());
+        if contact {
+            headers.push(
+                rsip::headers::Contact::new(format!(
+                    "<sip:{}@{}:{}>",
+                    platform.get_local_id(),
+                    server_ip,
+                    server_port
+                ))
+                .into(),
+            );
+        }
+        headers.push(rsip::headers::MaxForwards::new("70").into());
+        headers.push(rsip::headers::UserAgent::new("gbserver 0.1").into());
+        Ok((headers, uri))
+    }
+
     async fn build_message_request(
         channel_id_opt: Option<&String>,
         device_id: &String,
@@ -253,7 +660,7 @@ impl RequestBuilder {
         let cs_eq_str = format!("{} MESSAGE", rng.random_range(12u8..255u8));
         let cs_eq = rsip::headers::CSeq::new(&cs_eq_str).into();
         headers.push(cs_eq);
-        headers.push(rsip::headers::ContentType::new("Application/MANSCDP+xml").into());
+        headers.push(rsip::headers::ContentType::new(MANSCDP_CONTENT_TYPE).into());
         headers.push(rsip::headers::ContentLength::from(body.len() as u32).into());
         let request_msg: SipMessage = Request {
             method: Method::Message,
@@ -270,23 +677,26 @@ impl RequestBuilder {
     async fn build_subscribe_request(
         device_id: &String,
         body: String,
+        dialog: &SubscribeDialog,
     ) -> GlobalResult<(Ident, SipMessage)> {
-        let (mut headers, uri) =
-            Self::build_request_header(None, device_id, true, true, None, None).await?;
-        let call_id_str = Uuid::new_v4().as_simple().to_string();
-        headers.push(rsip::headers::CallId::new(&call_id_str).into());
-        let mut rng = rng();
-        let cs_eq_str = format!("{} SUBSCRIBE", rng.random_range(12u8..255u8));
+        let (mut headers, uri) = Self::build_request_header(
+            None,
+            device_id,
+            false,
+            true,
+            Some(&dialog.from_tag),
+            dialog.to_tag.as_deref(),
+        )
+        .await?;
+        headers.push(rsip::headers::CallId::new(&dialog.call_id).into());
+        let cs_eq_str = format!("{} SUBSCRIBE", dialog.seq);
         let cs_eq = rsip::headers::CSeq::new(&cs_eq_str).into();
         headers.push(cs_eq);
         headers.push(
-            rsip::headers::Event::new(format!(
-                "Catalog;id={}",
-                rng.random_range(123456789u32..987654321u32)
-            ))
-            .into(),
+            rsip::headers::Event::new(format!("{};id={}", dialog.event, dialog.event_id)).into(),
         );
-        headers.push(rsip::headers::ContentType::new("Application/MANSCDP+xml").into());
+        headers.push(rsip::headers::Expires::new(dialog.expires.to_string()).into());
+        headers.push(rsip::headers::ContentType::new(MANSCDP_CONTENT_TYPE).into());
         headers.push(rsip::headers::ContentLength::from(body.len() as u32).into());
         let request_msg: SipMessage = Request {
             method: Method::Subscribe,
@@ -296,7 +706,7 @@ impl RequestBuilder {
             body: body.as_bytes().to_vec(),
         }
         .into();
-        let ident = Ident::new(device_id.to_string(), call_id_str, cs_eq_str);
+        let ident = Ident::new(device_id.to_string(), dialog.call_id.clone(), cs_eq_str);
         Ok((ident, request_msg))
     }
 
@@ -320,6 +730,8 @@ impl RequestBuilder {
         .await?;
         let call_id = call_id.unwrap_or_else(|| Uuid::new_v4().as_simple().to_string());
         headers.push(rsip::headers::CallId::new(&call_id).into());
+        let mut uri = uri;
+        Dialog::route(&call_id, &mut uri, &mut headers);
         let seq = seq.unwrap_or_else(|| {
             let mut rng = rng();
             rng.random_range(12u32..255u32)
@@ -493,6 +905,8 @@ impl RequestBuilder {
         )
         .await?;
         headers.push(rsip::headers::CallId::new(&call_id).into());
+        let mut uri = uri;
+        Dialog::route(&call_id, &mut uri, &mut headers);
         let cs_eq_str = format!("{} BYE", seq);
         let cs_eq = rsip::headers::CSeq::new(&cs_eq_str).into();
         headers.push(cs_eq);
@@ -557,42 +971,20 @@ impl RequestBuilder {
         .await
     }
 
-    // 暂停回放
-    pub async fn pause(
-        device_id: &String,
-        channel_id: &String,
-        from_tag: &str,
-        to_tag: &str,
-        seq: u32,
-        call_id: String,
-    ) -> GlobalResult<(Ident, SipMessage)> {
-        let sdp = SdpBuilder::info_pause();
-        Self::common_info_request(
-            device_id,
-            channel_id,
-            &sdp,
-            from_tag,
-            to_tag,
-            Some(seq),
-            Some(call_id),
-        )
-        .await
-    }
-
-    // 恢复回放
-    pub async fn replay(
+    // 回放控制：暂停、恢复等MANSRTSP INFO
+    pub async fn info(
         device_id: &String,
         channel_id: &String,
+        body: &str,
         from_tag: &str,
         to_tag: &str,
         seq: u32,
         call_id: String,
     ) -> GlobalResult<(Ident, SipMessage)> {
-        let sdp = SdpBuilder::info_replay();
         Self::common_info_request(
             device_id,
             channel_id,
-            &sdp,
+            body,
             from_tag,
             to_tag,
             Some(seq),
@@ -643,7 +1035,11 @@ impl RequestBuilder {
         Ok((ident, msg))
     }
 
-    pub fn build_ack_request_by_response(res: &Response) -> GlobalResult<SipMessage> {
+    //2xx的ACK：Request-URI为对话远端目标,携带路由集
+    pub fn build_ack_request_by_response(
+        res: &Response,
+        dialog: &Dialog,
+    ) -> GlobalResult<SipMessage> {
         let mut headers: rsip::Headers = Default::default();
         headers.push(
             res.to_header()
@@ -680,9 +1076,14 @@ impl RequestBuilder {
             .seq()
             .hand_log(|msg| warn!("{msg}"))?;
         headers.push(rsip::headers::CSeq::new(format!("{seq} ACK")).into());
-        let uri = from.uri().hand_log(|msg| warn!("{msg}"))?;
-        let uri_str = uri.to_string();
-        headers.push(rsip::headers::Contact::new(format!("<{uri_str}>")).into());
+        let from_uri = from.uri().hand_log(|msg| warn!("{msg}"))?;
+        headers.push(rsip::headers::Contact::new(format!("<{from_uri}>")).into());
+        let mut uri = res
+            .to_header()
+            .hand_log(|msg| warn!("{msg}"))?
+            .uri()
+            .hand_log(|msg| warn!("{msg}"))?;
+        dialog.apply_route(&mut uri, &mut headers);
         headers.push(rsip::headers::MaxForwards::new("70").into());
         headers.push(rsip::headers::UserAgent::new("gbserver 0.1").into());
         headers.push(rsip::headers::ContentLength::default().into());
@@ -695,6 +1096,60 @@ impl RequestBuilder {
         }
         .into())
     }
+    //CANCEL：与INVITE相同的Request-URI、Via(branch)、Route、From、To、Call-ID及CSeq序号
+    pub fn build_cancel_request(invite: &Request) -> GlobalResult<SipMessage> {
+        let mut headers: rsip::Headers = Default::default();
+        headers.push(
+            invite
+                .via_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .clone()
+                .into(),
+        );
+        for header in invite.headers.iter() {
+            if let Header::Route(route) = header {
+                headers.push(route.clone().into());
+            }
+        }
+        headers.push(
+            invite
+                .from_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .clone()
+                .into(),
+        );
+        headers.push(
+            invite
+                .to_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .clone()
+                .into(),
+        );
+        headers.push(
+            invite
+                .call_id_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .clone()
+                .into(),
+        );
+        let seq = invite
+            .cseq_header()
+            .hand_log(|msg| warn!("{msg}"))?
+            .seq()
+            .hand_log(|msg| warn!("{msg}"))?;
+        headers.push(rsip::headers::CSeq::new(format!("{seq} CANCEL")).into());
+        headers.push(rsip::headers::MaxForwards::new("70").into());
+        headers.push(rsip::headers::UserAgent::new("gbserver 0.1").into());
+        headers.push(rsip::headers::ContentLength::default().into());
+        Ok(rsip::Request {
+            method: Method::Cancel,
+            uri: invite.uri.clone(),
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: Default::default(),
+        }
+        .into())
+    }
     async fn build_bye() {}
 }
 
@@ -704,19 +1159,76 @@ struct XmlBuilder;
 /// 2016 GB2312
 impl XmlBuilder {
     pub fn control_ptz(ptz_control_model: &PtzControlModel) -> String {
+        Self::control_ptz_line(
+            ptz_control_model.get_channel_id(),
+            &Self::build_cmd_ptz_line(ptz_control_model),
+        )
+    }
+    pub fn control_ptz_cmd(channel_id: &String, cmd: &PtzCmd) -> String {
+        Self::control_ptz_line(channel_id, &Self::build_ptz_cmd_line(cmd))
+    }
+    pub fn control_device(target_id: &String, sn: u32, cmd: &DeviceControlCmd) -> String {
+        let mut xml = String::with_capacity(300);
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB18030\"?>\r\n");
+        xml.push_str("<Control>\r\n");
+        xml.push_str("<CmdType>DeviceControl</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", target_id));
+        match cmd {
+            DeviceControlCmd::TeleBoot => xml.push_str("<TeleBoot>Boot</TeleBoot>\r\n"),
+            DeviceControlCmd::Record(true) => xml.push_str("<RecordCmd>Record</RecordCmd>\r\n"),
+            DeviceControlCmd::Record(false) => {
+                xml.push_str("<RecordCmd>StopRecord</RecordCmd>\r\n")
+            }
+            DeviceControlCmd::Guard(true) => xml.push_str("<GuardCmd>SetGuard</GuardCmd>\r\n"),
+            DeviceControlCmd::Guard(false) => xml.push_str("<GuardCmd>ResetGuard</GuardCmd>\r\n"),
+            DeviceControlCmd::ResetAlarm {
+                alarm_method,
+                alarm_type,
+            } => {
+                xml.push_str("<AlarmCmd>ResetAlarm</AlarmCmd>\r\n");
+                if alarm_method.is_some() || alarm_type.is_some() {
+                    xml.push_str("<Info>\r\n");
+                    if let Some(method) = alarm_method {
+                        xml.push_str(&format!("<AlarmMethod>{}</AlarmMethod>\r\n", method));
+                    }
+                    if let Some(tp) = alarm_type {
+                        xml.push_str(&format!("<AlarmType>{}</AlarmType>\r\n", tp));
+                    }
+                    xml.push_str("</Info>\r\n");
+                }
+            }
+            DeviceControlCmd::IFame => xml.push_str("<IFameCmd>Send</IFameCmd>\r\n"),
+            DeviceControlCmd::DragZoomIn(zoom) => {
+                Self::drag_zoom_line(&mut xml, "DragZoomIn", zoom)
+            }
+            DeviceControlCmd::DragZoomOut(zoom) => {
+                Self::drag_zoom_line(&mut xml, "DragZoomOut", zoom)
+            }
+        }
+        xml.push_str("</Control>\r\n");
+        xml
+    }
+
+    fn drag_zoom_line(xml: &mut String, tag: &str, zoom: &DragZoom) {
+        xml.push_str(&format!("<{}>\r\n", tag));
+        xml.push_str(&format!("<Length>{}</Length>\r\n", zoom.length));
+        xml.push_str(&format!("<Width>{}</Width>\r\n", zoom.width));
+        xml.push_str(&format!("<MidPointX>{}</MidPointX>\r\n", zoom.mid_point_x));
+        xml.push_str(&format!("<MidPointY>{}</MidPointY>\r\n", zoom.mid_point_y));
+        xml.push_str(&format!("<LengthX>{}</LengthX>\r\n", zoom.length_x));
+        xml.push_str(&format!("<LengthY>{}</LengthY>\r\n", zoom.length_y));
+        xml.push_str(&format!("</{}>\r\n", tag));
+    }
+
+    pub fn control_ptz_line(channel_id: &String, cmd_line: &str) -> String {
         let mut xml = String::with_capacity(200);
         xml.push_str("<?xml version=\"1.0\" encoding=\"GB18030\"?>\r\n");
         xml.push_str("<Control>\r\n");
         xml.push_str("<CmdType>DeviceControl</CmdType>\r\n");
         xml.push_str(&format!("<SN>{}</SN>\r\n", Local::now().timestamp()));
-        xml.push_str(&format!(
-            "<DeviceID>{}</DeviceID>\r\n",
-            ptz_control_model.get_channel_id()
-        ));
-        xml.push_str(&format!(
-            "<PTZCmd>{}</PTZCmd>\r\n",
-            Self::build_cmd_ptz_line(ptz_control_model)
-        ));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", channel_id));
+        xml.push_str(&format!("<PTZCmd>{}</PTZCmd>\r\n", cmd_line));
         xml.push_str("<Info>\r\n");
         xml.push_str("<ControlPriority>5</ControlPriority>\r\n");
         xml.push_str("</Info>\r\n");
@@ -750,7 +1262,63 @@ impl XmlBuilder {
         bytes[4] = *ptz_control_model.get_horizon_speed();
         bytes[5] = *ptz_control_model.get_vertical_speed();
         bytes[6] = ptz_control_model.get_zoom_speed() << 4;
-        bytes[7] = (bytes.iter().copied().map(|x| x as u16).sum::<u16>() % 256) as u8;
+        Self::encode_ptz_bytes(bytes)
+    }
+    //字节4为指令码,字节5、6为数据,字节7高4位为组合码2(12位数据的高4位)
+    fn build_ptz_cmd_line(cmd: &PtzCmd) -> String {
+        let (code, data1, data2, data3) = match *cmd {
+            PtzCmd::Fi {
+                iris,
+                focus,
+                iris_speed,
+                focus_speed,
+            } => {
+                let mut code = 0x40;
+                match iris {
+                    1 => code |= 0x08,
+                    2 => code |= 0x04,
+                    _ => {}
+                }
+                match focus {
+                    1 => code |= 0x02,
+                    2 => code |= 0x01,
+                    _ => {}
+                }
+                (code, focus_speed, iris_speed, 0)
+            }
+            PtzCmd::PresetSet(preset) => (0x81, 0, preset, 0),
+            PtzCmd::PresetCall(preset) => (0x82, 0, preset, 0),
+            PtzCmd::PresetDel(preset) => (0x83, 0, preset, 0),
+            PtzCmd::CruiseAdd(group, preset) => (0x84, group, preset, 0),
+            PtzCmd::CruiseDel(group, preset) => (0x85, group, preset, 0),
+            PtzCmd::CruiseSpeed(group, speed) => (
+                0x86,
+                group,
+                (speed & 0xFF) as u8,
+                ((speed >> 8) & 0x0F) as u8,
+            ),
+            PtzCmd::CruiseDwell(group, sec) => {
+                (0x87, group, (sec & 0xFF) as u8, ((sec >> 8) & 0x0F) as u8)
+            }
+            PtzCmd::CruiseStart(group) => (0x88, group, 0, 0),
+            PtzCmd::ScanStart(group) => (0x89, group, 0x00, 0),
+            PtzCmd::ScanLeft(group) => (0x89, group, 0x01, 0),
+            PtzCmd::ScanRight(group) => (0x89, group, 0x02, 0),
+            PtzCmd::ScanSpeed(group, speed) => (
+                0x8A,
+                group,
+                (speed & 0xFF) as u8,
+                ((speed >> 8) & 0x0F) as u8,
+            ),
+            PtzCmd::AuxOn(aux) => (0x8C, aux, 0, 0),
+            PtzCmd::AuxOff(aux) => (0x8D, aux, 0, 0),
+            PtzCmd::Stop => (0, 0, 0, 0),
+        };
+        Self::encode_ptz_bytes([0xA5, 0x0F, 0x01, code, data1, data2, data3 << 4, 0x00])
+    }
+    //字节8为前7字节和对256取模的校验码
+    fn encode_ptz_bytes(mut bytes: [u8; 8]) -> String {
+        bytes[7] = (bytes.iter().take(7).copied().map(|x| x as u16).sum::<u16>() % 256) as u8;
         let mut cmd_line = String::new();
         for byte in &bytes {
             write!(&mut cmd_line, "{:02X}", byte).unwrap();
@@ -811,25 +1379,439 @@ impl XmlBuilder {
         xml
     }
 
+    pub fn query_device_status(device_id: &String, sn: u32) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Query>\r\n");
+        xml.push_str("<CmdType>DeviceStatus</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str("</Query>\r\n");
+        xml
+    }
+
+    //多个配置类型以"/"分隔
+    //用户输入的配置值转义XML特殊字符
+    fn escape(val: &str) -> String {
+        let mut out = String::with_capacity(val.len());
+        for c in val.chars() {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&apos;"),
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    pub fn query_config_download(device_id: &String, sn: u32, config_types: &[String]) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Query>\r\n");
+        xml.push_str("<CmdType>ConfigDownload</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str(&format!(
+            "<ConfigType>{}</ConfigType>\r\n",
+            Self::escape(&config_types.join("/"))
+        ));
+        xml.push_str("</Query>\r\n");
+        xml
+    }
+
+    pub fn control_device_config(model: &DeviceConfigModel, sn: u32) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Control>\r\n");
+        xml.push_str("<CmdType>DeviceConfig</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!(
+            "<DeviceID>{}</DeviceID>\r\n",
+            Self::escape(
+                model
+                    .get_channel_id()
+                    .as_ref()
+                    .unwrap_or(model.get_device_id())
+            )
+        ));
+        if model.get_name().is_some()
+            || model.get_expiration().is_some()
+            || model.get_heart_beat_interval().is_some()
+            || model.get_heart_beat_count().is_some()
+        {
+            xml.push_str("<BasicParam>\r\n");
+            if let Some(name) = model.get_name() {
+                xml.push_str(&format!("<Name>{}</Name>\r\n", Self::escape(name)));
+            }
+            if let Some(expiration) = model.get_expiration() {
+                xml.push_str(&format!("<Expiration>{}</Expiration>\r\n", expiration));
+            }
+            if let Some(interval) = model.get_heart_beat_interval() {
+                xml.push_str(&format!(
+                    "<HeartBeatInterval>{}</HeartBeatInterval>\r\n",
+                    interval
+                ));
+            }
+            if let Some(count) = model.get_heart_beat_count() {
+                xml.push_str(&format!("<HeartBeatCount>{}</HeartBeatCount>\r\n", count));
+            }
+            xml.push_str("</BasicParam>\r\n");
+        }
+        if let Some(report) = model.get_alarm_report() {
+            xml.push_str("<AlarmReport>\r\n");
+            if let Some(motion) = report.motion_detection {
+                xml.push_str(&format!(
+                    "<MotionDetection>{}</MotionDetection>\r\n",
+                    motion
+                ));
+            }
+            if let Some(field) = report.field_detection {
+                xml.push_str(&format!("<FieldDetection>{}</FieldDetection>\r\n", field));
+            }
+            xml.push_str("</AlarmReport>\r\n");
+        }
+        xml.push_str("</Control>\r\n");
+        xml
+    }
+
     pub fn query_device_catalog(device_id: &String) -> String {
+        Self::query_device_catalog_by_sn(device_id, Local::now().timestamp_subsec_millis())
+    }
+
+    pub fn query_device_catalog_by_sn(device_id: &String, sn: u32) -> String {
         let mut xml = String::new();
         xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
         xml.push_str("<Query>\r\n");
         xml.push_str("<CmdType>Catalog</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str("</Query>\r\n");
+        xml
+    }
+
+    pub fn subscribe_alarm(model: &AlarmSubscribeModel) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Query>\r\n");
+        xml.push_str("<CmdType>Alarm</CmdType>\r\n");
+        xml.push_str(&format!(
+            "<SN>{}</SN>\r\n",
+            Local::now().timestamp_subsec_millis()
+        ));
+        xml.push_str(&format!(
+            "<DeviceID>{}</DeviceID>\r\n",
+            model.get_device_id()
+        ));
+        xml.push_str(&format!(
+            "<StartAlarmPriority>{}</StartAlarmPriority>\r\n",
+            model.get_start_priority().unwrap_or(0)
+        ));
+        xml.push_str(&format!(
+            "<EndAlarmPriority>{}</EndAlarmPriority>\r\n",
+            model.get_end_priority().unwrap_or(0)
+        ));
+        xml.push_str(&format!(
+            "<AlarmMethod>{}</AlarmMethod>\r\n",
+            model.get_alarm_method().unwrap_or(0)
+        ));
+        if let Some(st) = model.get_start_time() {
+            xml.push_str(&format!(
+                "<StartAlarmTime>{}</StartAlarmTime>\r\n",
+                TimeRange::to_gb_time(*st)
+            ));
+        }
+        if let Some(et) = model.get_end_time() {
+            xml.push_str(&format!(
+                "<EndAlarmTime>{}</EndAlarmTime>\r\n",
+                TimeRange::to_gb_time(*et)
+            ));
+        }
+        xml.push_str("</Query>\r\n");
+        xml
+    }
+
+    pub fn subscribe_mobile_position(device_id: &String, interval: u32) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Query>\r\n");
+        xml.push_str("<CmdType>MobilePosition</CmdType>\r\n");
         xml.push_str(&format!(
             "<SN>{}</SN>\r\n",
             Local::now().timestamp_subsec_millis()
         ));
         xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str(&format!("<Interval>{}</Interval>\r\n", interval));
+        xml.push_str("</Query>\r\n");
+        xml
+    }
+
+    pub fn response_result(cmd_type: &str, sn: &str, device_id: &str, result: &str) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Response>\r\n");
+        xml.push_str(&format!("<CmdType>{}</CmdType>\r\n", cmd_type));
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str(&format!("<Result>{}</Result>\r\n", result));
+        xml.push_str("</Response>\r\n");
+        xml
+    }
+
+    pub fn notify_keepalive(device_id: &str, sn: u32) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Notify>\r\n");
+        xml.push_str("<CmdType>Keepalive</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str("<Status>OK</Status>\r\n");
+        xml.push_str("</Notify>\r\n");
+        xml
+    }
+
+    //目录应答分包：SumNum为总数,每包携带部分节点与通道
+    pub fn response_catalog(
+        sn: &str,
+        device_id: &str,
+        sum_num: usize,
+        items: &[ShareItem],
+    ) -> String {
+        let mut xml = String::with_capacity(200 + items.len() * 500);
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Response>\r\n");
+        xml.push_str("<CmdType>Catalog</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str(&format!("<SumNum>{}</SumNum>\r\n", sum_num));
+        xml.push_str(&format!("<DeviceList Num=\"{}\">\r\n", items.len()));
+        for item in items {
+            xml.push_str("<Item>\r\n");
+            match item {
+                ShareItem::Node { node, kind } => Self::catalog_node_line(&mut xml, node, kind),
+                ShareItem::Channel {
+                    channel,
+                    id,
+                    parent_id,
+                    civil_code,
+                } => Self::catalog_channel_line(
+                    &mut xml,
+                    channel,
+                    id,
+                    parent_id,
+                    civil_code.as_deref(),
+                ),
+            }
+            xml.push_str("</Item>\r\n");
+        }
+        xml.push_str("</DeviceList>\r\n");
+        xml.push_str("</Response>\r\n");
+        xml
+    }
+
+    fn catalog_node_line(xml: &mut String, node: &GbsCascadeNode, kind: &NodeKind) {
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", node.get_node_id()));
+        xml.push_str(&format!("<Name>{}</Name>\r\n", node.get_name()));
+        if kind == &NodeKind::CivilCode {
+            return;
+        }
+        if let Some(parent_id) = node.get_parent_id() {
+            xml.push_str(&format!("<ParentID>{}</ParentID>\r\n", parent_id));
+        }
+        if kind == &NodeKind::VirtualOrganization {
+            if let Some(business_group_id) = node.get_business_group_id() {
+                xml.push_str(&format!(
+                    "<BusinessGroupID>{}</BusinessGroupID>\r\n",
+                    business_group_id
+                ));
+            }
+        }
+    }
+
+    fn catalog_channel_line(
+        xml: &mut String,
+        channel: &GbsDeviceChannel,
+        id: &str,
+        parent_id: &str,
+        civil_code: Option<&str>,
+    ) {
+        let name = channel
+            .get_alias_name()
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .or(channel.get_name().as_deref())
+            .unwrap_or_default();
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", id));
+        xml.push_str(&format!("<Name>{}</Name>\r\n", name));
+        xml.push_str(&format!(
+            "<Manufacturer>{}</Manufacturer>\r\n",
+            channel.get_manufacturer().as_deref().unwrap_or_default()
+        ));
+        xml.push_str(&format!(
+            "<Model>{}</Model>\r\n",
+            channel.get_model().as_deref().unwrap_or_default()
+        ));
+        xml.push_str(&format!(
+            "<Owner>{}</Owner>\r\n",
+            channel.get_owner().as_deref().unwrap_or_default()
+        ));
+        xml.push_str(&format!(
+            "<CivilCode>{}</CivilCode>\r\n",
+            civil_code.unwrap_or_default()
+        ));
+        xml.push_str(&format!(
+            "<Address>{}</Address>\r\n",
+            channel.get_address().as_deref().unwrap_or_default()
+        ));
+        xml.push_str("<Parental>0</Parental>\r\n");
+        xml.push_str(&format!("<ParentID>{}</ParentID>\r\n", parent_id));
+        xml.push_str("<SafetyWay>0</SafetyWay>\r\n");
+        xml.push_str("<RegisterWay>1</RegisterWay>\r\n");
+        xml.push_str("<Secrecy>0</Secrecy>\r\n");
+        xml.push_str(&format!("<Status>{}</Status>\r\n", channel.get_status()));
+        if let (Some(longitude), Some(latitude)) = (channel.get_longitude(), channel.get_latitude())
+        {
+            xml.push_str(&format!("<Longitude>{}</Longitude>\r\n", longitude));
+            xml.push_str(&format!("<Latitude>{}</Latitude>\r\n", latitude));
+        }
+        if let Some(ptz_type) = channel.get_ptz_type() {
+            xml.push_str("<Info>\r\n");
+            xml.push_str(&format!("<PTZType>{}</PTZType>\r\n", ptz_type));
+            xml.push_str("</Info>\r\n");
+        }
+    }
+
+    pub fn response_device_info(sn: &str, device_id: &str, name: &str, channel: usize) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Response>\r\n");
+        xml.push_str("<CmdType>DeviceInfo</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str(&format!("<DeviceName>{}</DeviceName>\r\n", name));
+        xml.push_str("<Result>OK</Result>\r\n");
+        xml.push_str("<Manufacturer>gbserver</Manufacturer>\r\n");
+        xml.push_str("<Model>gbserver</Model>\r\n");
+        xml.push_str("<Firmware>0.1</Firmware>\r\n");
+        xml.push_str(&format!("<Channel>{}</Channel>\r\n", channel));
+        xml.push_str("</Response>\r\n");
+        xml
+    }
+
+    pub fn response_device_status(sn: &str, device_id: &str, online: bool) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Response>\r\n");
+        xml.push_str("<CmdType>DeviceStatus</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", device_id));
+        xml.push_str("<Result>OK</Result>\r\n");
+        xml.push_str(&format!(
+            "<Online>{}</Online>\r\n",
+            if online { "ONLINE" } else { "OFFLINE" }
+        ));
+        xml.push_str(&format!(
+            "<Status>{}</Status>\r\n",
+            if online { "OK" } else { "ERROR" }
+        ));
+        xml.push_str(&format!(
+            "<DeviceTime>{}</DeviceTime>\r\n",
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        ));
+        xml.push_str("</Response>\r\n");
+        xml
+    }
+
+    pub fn notify_broadcast(source_id: &String, target_id: &String) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Notify>\r\n");
+        xml.push_str("<CmdType>Broadcast</CmdType>\r\n");
+        xml.push_str(&format!(
+            "<SN>{}</SN>\r\n",
+            Local::now().timestamp_subsec_millis()
+        ));
+        xml.push_str(&format!("<SourceID>{}</SourceID>\r\n", source_id));
+        xml.push_str(&format!("<TargetID>{}</TargetID>\r\n", target_id));
+        xml.push_str("</Notify>\r\n");
+        xml
+    }
+
+    //SN由调用方指定,用于关联设备分包应答
+    pub fn query_record_info(
+        channel_id: &String,
+        sn: u32,
+        range: &TimeRange,
+        record_type: &str,
+        secrecy: u8,
+    ) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"GB2312\"?>\r\n");
+        xml.push_str("<Query>\r\n");
+        xml.push_str("<CmdType>RecordInfo</CmdType>\r\n");
+        xml.push_str(&format!("<SN>{}</SN>\r\n", sn));
+        xml.push_str(&format!("<DeviceID>{}</DeviceID>\r\n", channel_id));
+        xml.push_str(&format!(
+            "<StartTime>{}</StartTime>\r\n",
+            TimeRange::to_gb_time(*range.get_start_time())
+        ));
+        xml.push_str(&format!(
+            "<EndTime>{}</EndTime>\r\n",
+            TimeRange::to_gb_time(*range.get_end_time())
+        ));
+        xml.push_str(&format!("<Secrecy>{}</Secrecy>\r\n", secrecy));
+        xml.push_str(&format!("<Type>{}</Type>\r\n", record_type));
         xml.push_str("</Query>\r\n");
         xml
     }
 }
 
-struct SdpBuilder;
+pub struct SdpBuilder;
 
 #[allow(unused)]
 impl SdpBuilder {
+    //平台音频sendonly,TCP时setup与设备offer相反
+    pub fn broadcast(info: &BroadcastInfo) -> String {
+        let mut sdp = String::with_capacity(300);
+        sdp.push_str("v=0\r\n");
+        sdp.push_str(&format!(
+            "o={} 0 0 IN IP4 {}\r\n",
+            info.source_id, info.node_ip
+        ));
+        sdp.push_str("s=Play\r\n");
+        sdp.push_str(&format!("c=IN IP4 {}\r\n", info.node_ip));
+        sdp.push_str("t=0 0\r\n");
+        match info.trans_mode {
+            0 => sdp.push_str(&format!(
+                "m=audio {} RTP/AVP {}\r\n",
+                info.node_port, info.payload
+            )),
+            mode => {
+                sdp.push_str(&format!(
+                    "m=audio {} TCP/RTP/AVP {}\r\n",
+                    info.node_port, info.payload
+                ));
+                if mode == 2 {
+                    sdp.push_str("a=setup:active\r\n");
+                } else {
+                    sdp.push_str("a=setup:passive\r\n");
+                }
+                sdp.push_str("a=connection:new\r\n");
+            }
+        }
+        sdp.push_str("a=sendonly\r\n");
+        if info.payload == 0 {
+            sdp.push_str("a=rtpmap:0 PCMU/8000\r\n");
+        } else {
+            sdp.push_str("a=rtpmap:8 PCMA/8000\r\n");
+        }
+        sdp.push_str(&format!("y={}\r\n", info.ssrc));
+        sdp.push_str("f=v/////a/1/8/1\r\n");
+        sdp
+    }
+
     pub fn info_pause() -> String {
         let mut sdp = String::with_capacity(100);
         sdp.push_str("PAUSE RTSP/1.0\r\n");
@@ -984,8 +1966,24 @@ impl SdpBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::general::model::PtzControlModel;
+    use crate::general::model::{DeviceConfigModel, PtzControlModel};
     use common::chrono::Local;
+    use common::serde_json;
+
+    #[test]
+    fn test_config_escape() {
+        let model: DeviceConfigModel =
+            serde_json::from_str(r#"{"device_id":"34020000001110000001","name":"A&B <\"cam\">"}"#)
+                .unwrap();
+        let xml = super::XmlBuilder::control_device_config(&model, 1);
+        assert!(xml.contains("<Name>A&amp;B &lt;&quot;cam&quot;&gt;</Name>"));
+        let xml = super::XmlBuilder::query_config_download(
+            &"34020000001110000001".to_string(),
+            1,
+            &["BasicParam".to_string(), "</ConfigType>".to_string()],
+        );
+        assert!(xml.contains("<ConfigType>BasicParam/&lt;/ConfigType&gt;</ConfigType>"));
+    }
 
     #[test]
     fn test_date_format() {
@@ -1027,6 +2025,131 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ptz_ext_cmd() {
+        use crate::general::model::PtzCmd;
+        let line = super::XmlBuilder::build_ptz_cmd_line;
+        assert_eq!(line(&PtzCmd::Stop), "A50F0100000000B5");
+        assert_eq!(line(&PtzCmd::PresetCall(3)), "A50F01820003003A");
+        assert_eq!(line(&PtzCmd::CruiseAdd(1, 2)), "A50F01840102003C");
+        assert_eq!(line(&PtzCmd::CruiseSpeed(1, 0x123)), "A50F01860123106F");
+        assert_eq!(line(&PtzCmd::ScanRight(0)), "A50F018900020040");
+        assert_eq!(line(&PtzCmd::AuxOn(1)), "A50F018C01000042");
+        let fi = PtzCmd::Fi {
+            iris: 2,
+            focus: 1,
+            iris_speed: 0x10,
+            focus_speed: 0x20,
+        };
+        assert_eq!(line(&fi), "A50F01462010002B");
+    }
+
+    #[test]
+    fn test_control_device_xml() {
+        use crate::gb::handler::manscdp::{Body, Control, Manscdp};
+        use crate::general::model::{DeviceControlCmd, DragZoom};
+        let parse = |xml: &str| match Manscdp::parse(xml.as_bytes()).unwrap().body {
+            Body::Control(Control::DeviceControl(ctl)) => ctl,
+            body => panic!("unexpected {body:?}"),
+        };
+        let id = "34020000001320000001".to_string();
+        let xml = super::XmlBuilder::control_device(&id, 17, &DeviceControlCmd::Guard(false));
+        assert_eq!(parse(&xml).guard_cmd.as_deref(), Some("ResetGuard"));
+        let cmd = DeviceControlCmd::ResetAlarm {
+            alarm_method: Some(2),
+            alarm_type: None,
+        };
+        let xml = super::XmlBuilder::control_device(&id, 18, &cmd);
+        let info = parse(&xml).info.unwrap();
+        assert_eq!(info.alarm_method.as_deref(), Some("2"));
+        assert!(!xml.contains("AlarmType"));
+        let zoom = DragZoom {
+            length: 1920,
+            width: 1080,
+            mid_point_x: 960,
+            mid_point_y: 540,
+            length_x: 200,
+            length_y: 100,
+        };
+        let xml = super::XmlBuilder::control_device(&id, 19, &DeviceControlCmd::DragZoomOut(zoom));
+        let zoom = parse(&xml).drag_zoom_out.unwrap();
+        assert_eq!(zoom.mid_point_y, Some(540));
+        assert!(DeviceControlCmd::Record(true).has_response());
+        assert!(!DeviceControlCmd::IFame.has_response());
+    }
+
+    #[test]
+    fn test_response_result_xml() {
+        use crate::gb::handler::manscdp::{Body, Manscdp, Response};
+        let xml = super::XmlBuilder::response_result(
+            "DeviceControl",
+            "27",
+            "34020000001320000001",
+            "ERROR",
+        );
+        let msg = Manscdp::parse(xml.as_bytes()).unwrap();
+        assert_eq!(msg.head.sn, "27");
+        let Body::Response(Response::DeviceControl(res)) = msg.body else {
+            panic!("unexpected response");
+        };
+        assert!(!res.is_ok());
+        assert_eq!(res.result, "ERROR");
+    }
+
+    #[test]
+    fn test_cancel_request() {
+        use super::RequestBuilder;
+        use rsip::prelude::{HeadersExt, UntypedHeader};
+        use rsip::{Method, Request, SipMessage};
+        let raw = "INVITE sip:34020000001320000001@3402000000 SIP/2.0\r\n\
+                   Via: SIP/2.0/UDP 10.0.0.2:5060;rport;branch=z9hG4bKinvite\r\n\
+                   From: <sip:34020000002000000001@3402000000>;tag=local\r\n\
+                   To: <sip:34020000001320000001@3402000000>\r\n\
+                   Call-ID: cancel@10.0.0.2\r\n\
+                   CSeq: 9 INVITE\r\n\
+                   Content-Length: 0\r\n\r\n";
+        let invite = Request::try_from(raw.as_bytes()).unwrap();
+        let SipMessage::Request(cancel) = RequestBuilder::build_cancel_request(&invite).unwrap()
+        else {
+            panic!("not a request");
+        };
+        assert_eq!(cancel.method, Method::Cancel);
+        assert_eq!(cancel.uri, invite.uri);
+        assert_eq!(
+            cancel.via_header().unwrap().value(),
+            invite.via_header().unwrap().value()
+        );
+        assert_eq!(cancel.cseq_header().unwrap().value(), "9 CANCEL");
+        assert_eq!(cancel.call_id_header().unwrap().value(), "cancel@10.0.0.2");
+    }
+
+    #[test]
+    fn test_options_response() {
+        use super::{ResponseBuilder, ACCEPT_TYPES, ALLOW_METHODS};
+        use rsip::{Header, Request, SipMessage};
+        let raw = "OPTIONS sip:34020000002000000001@3402000000 SIP/2.0\r\n\
+                   Via: SIP/2.0/UDP 10.0.0.1:5060;rport;branch=z9hG4bKoptions\r\n\
+                   From: <sip:34020000001320000001@3402000000>;tag=dev\r\n\
+                   To: <sip:34020000002000000001@3402000000>\r\n\
+                   Call-ID: options@10.0.0.1\r\n\
+                   CSeq: 1 OPTIONS\r\n\
+                   Content-Length: 0\r\n\r\n";
+        let req = Request::try_from(raw.as_bytes()).unwrap();
+        let addr = "10.0.0.1:5060".parse().unwrap();
+        let SipMessage::Response(res) =
+            ResponseBuilder::build_options_response(&req, &addr).unwrap()
+        else {
+            panic!("not a response");
+        };
+        assert_eq!(res.status_code.code(), 200);
+        assert!(res.headers.iter().any(
+            |h| matches!(h, Header::Allow(allow) if allow.to_string().contains(ALLOW_METHODS))
+        ));
+        assert!(res.headers.iter().any(
+            |h| matches!(h, Header::Accept(accept) if accept.to_string().contains(ACCEPT_TYPES))
+        ));
+    }
+
     #[test]
     fn test_left_mv() {
         let sec = Local::now().timestamp();
@@ -0,0 +1,1114 @@
+use crate::gb::handler::builder::{RequestBuilder, ResponseBuilder};
+use crate::gb::handler::cmd::{CmdControl, CmdStream};
+use crate::gb::handler::dialog::Dialog;
+use crate::gb::handler::events::event::{Container, EventSession, Ident};
+use crate::gb::handler::manscdp::{Body, Control, DeviceControl, Head, Manscdp, Query};
+use crate::gb::shared::transaction::{Transaction, TIMER_F};
+use crate::gb::SessionConf;
+use crate::general::model::{MediaAddress, StreamMode, TimeRange};
+use crate::store::entity::{GbsCascadeChannel, GbsCascadeNode, GbsDeviceChannel};
+use common::bytes::Bytes;
+use common::confgen::conf;
+use common::constructor::Get;
+use common::dashmap::DashMap;
+use common::exception::{GlobalError, GlobalResult, TransError};
+use common::log::{error, info, warn};
+use common::net::state::{Association, Package, Protocol, Zip};
+use common::once_cell::sync::Lazy;
+use common::rand::{rng, Rng};
+use common::serde::Deserialize;
+use common::serde_default;
+use common::tokio::sync::mpsc;
+use common::tokio::sync::mpsc::{Receiver, Sender};
+use common::tokio::time::{self, Instant};
+use rsip::headers::auth::{Algorithm, AuthQop, Scheme};
+use rsip::headers::{typed, ToTypedHeader};
+use rsip::message::HeadersExt;
+use rsip::prelude::UntypedHeader;
+use rsip::services::DigestGenerator;
+use rsip::{Method, Request, Response, SipMessage, Uri};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::OnceLock;
+use std::time::Duration;
+use uuid::Uuid;
+
+//连续心跳无应答次数达到后重新注册
+const KEEPALIVE_LOSS: u8 = 3;
+//注册失败后重试间隔(秒)
+const RETRY_INTERVAL: u64 = 30;
+//目录应答每包携带的通道数
+const CATALOG_PAGE: usize = 5;
+
+static CASCADE: OnceLock<Cascade> = OnceLock::new();
+//上级点播：上级call_id -> 转发至设备的点播
+static RELAYS: Lazy<DashMap<String, Relay>> = Lazy::new(DashMap::new);
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "common::serde")]
+#[conf(prefix = "server")]
+struct CascadeConf {
+    #[serde(default = "default_cascade")]
+    cascade: Vec<Platform>,
+}
+serde_default!(default_cascade, Vec<Platform>, Vec::new());
+
+///上级平台：仅支持UDP
+#[derive(Debug, Clone, Get, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct Platform {
+    //上级平台编码
+    id: String,
+    //上级平台域
+    domain: String,
+    ip: Ipv4Addr,
+    #[serde(default = "default_port")]
+    port: u16,
+    password: Option<String>,
+    //本平台在上级的编码
+    local_id: String,
+    #[serde(default = "default_expires")]
+    expires: u32,
+    #[serde(default = "default_heartbeat")]
+    heartbeat: u16,
+}
+serde_default!(default_port, u16, 5060);
+serde_default!(default_expires, u32, 3600);
+serde_default!(default_heartbeat, u16, 60);
+
+impl Platform {
+    //本平台域取编码前十位
+    pub fn local_domain(&self) -> &str {
+        self.local_id.get(0..10).unwrap_or(&self.local_id)
+    }
+
+    fn association(&self) -> Association {
+        let conf = SessionConf::get_session_by_conf();
+        Association::new(
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, *conf.get_wan_port())),
+            SocketAddr::from((self.ip, self.port)),
+            Protocol::UDP,
+        )
+    }
+}
+
+struct Relay {
+    platform_id: String,
+    device_id: String,
+    channel_id: String,
+    device_call_id: String,
+}
+
+//注册会话：刷新注册沿用call_id与from_tag
+struct RegisterSession {
+    call_id: String,
+    from_tag: String,
+    seq: u32,
+}
+
+impl RegisterSession {
+    fn new() -> Self {
+        Self {
+            call_id: Uuid::new_v4().as_simple().to_string(),
+            from_tag: rng().random_range(123456789u32..987654321u32).to_string(),
+            seq: 0,
+        }
+    }
+}
+
+pub struct Cascade {
+    platforms: HashMap<String, Platform>,
+    output: Sender<Zip>,
+}
+
+impl Cascade {
+    //注册至配置的上级平台,并保持心跳
+    pub fn start(output: Sender<Zip>) {
+        let conf = CascadeConf::conf();
+        if conf.cascade.is_empty() {
+            return;
+        }
+        let platforms: HashMap<String, Platform> = conf
+            .cascade
+            .into_iter()
+            .map(|platform| (platform.id.clone(), platform))
+            .collect();
+        let cascade = CASCADE.get_or_init(|| Cascade { platforms, output });
+        for platform in cascade.platforms.values() {
+            common::tokio::spawn(Self::keep_registered(platform));
+        }
+    }
+
+    pub fn platform(id: &str) -> Option<&'static Platform> {
+        CASCADE.get().and_then(|cascade| cascade.platforms.get(id))
+    }
+
+    pub async fn hand_request(
+        platform: &'static Platform,
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<()> {
+        match req.method {
+            Method::Message => Self::message(platform, req, tx, bill).await,
+            Method::Invite => Self::invite(platform, req, tx, bill).await,
+            Method::Bye => Self::bye(req, tx, bill).await,
+            Method::Ack => {
+                if let Ok(call_id) = req.call_id_header() {
+                    Dialog::confirm(call_id.value());
+                }
+                Ok(())
+            }
+            _ => Self::reply(&req, &tx, bill, 405).await,
+        }
+    }
+
+    //设备挂断：同时挂断对应的上级点播
+    pub fn device_bye(device_call_id: &str) {
+        let Some(call_id) = RELAYS
+            .iter()
+            .find(|relay| relay.device_call_id == device_call_id)
+            .map(|relay| relay.key().clone())
+        else {
+            return;
+        };
+        let Some((call_id, relay)) = RELAYS.remove(&call_id) else {
+            return;
+        };
+        let Some(platform) = Self::platform(&relay.platform_id) else {
+            return;
+        };
+        common::tokio::spawn(async move {
+            let Some((seq, from_tag, to_tag)) = Dialog::next_request(&call_id) else {
+                return;
+            };
+            let bye = RequestBuilder::cascade_bye(platform, &call_id, seq, &from_tag, &to_tag);
+            Dialog::terminate(&call_id);
+            if let Ok((ident, msg)) = bye {
+                let _ = Self::request(platform, ident, msg)
+                    .await
+                    .hand_log(|msg| warn!("{msg}"));
+            }
+        });
+    }
+
+    async fn keep_registered(platform: &'static Platform) {
+        let mut session = RegisterSession::new();
+        loop {
+            match Self::register(platform, &mut session).await {
+                Ok(expires) => {
+                    info!(
+                        "级联平台 = [{}],注册成功,有效期{}s",
+                        platform.get_id(),
+                        expires
+                    );
+                    Self::keep_alive(platform, expires).await;
+                }
+                Err(err) => {
+                    warn!("级联平台 = [{}],注册失败:{}", platform.get_id(), err);
+                    session = RegisterSession::new();
+                    time::sleep(Duration::from_secs(RETRY_INTERVAL)).await;
+                }
+            }
+        }
+    }
+
+    //注册,401时按摘要认证重发;返回上级确认的有效期
+    async fn register(platform: &Platform, session: &mut RegisterSession) -> GlobalResult<u32> {
+        let expires = *platform.get_expires();
+        session.seq += 1;
+        let (ident, msg) = RequestBuilder::cascade_register(
+            platform,
+            &session.call_id,
+            session.seq,
+            &session.from_tag,
+            expires,
+            None,
+        )?;
+        let mut res = Self::request(platform, ident, msg).await?;
+        if res.status_code.code() == 401 {
+            let www = res
+                .www_authenticate_header()
+                .ok_or_else(|| {
+                    GlobalError::new_biz_error(1100, "401应答缺少WWW-Authenticate", |msg| {
+                        warn!("{msg}")
+                    })
+                })?
+                .typed()
+                .hand_log(|msg| warn!("{msg}"))?;
+            session.seq += 1;
+            let (ident, msg) = RequestBuilder::cascade_register(
+                platform,
+                &session.call_id,
+                session.seq,
+                &session.from_tag,
+                expires,
+                Some(Self::authorization(platform, &www)?),
+            )?;
+            res = Self::request(platform, ident, msg).await?;
+        }
+        if res.status_code.code() != 200 {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                &format!("注册被拒绝:{}", res.status_code),
+                |msg| warn!("{msg}"),
+            ));
+        }
+        Ok(res
+            .expires_header()
+            .and_then(|header| header.value().trim().parse::<u32>().ok())
+            .filter(|expires| *expires > 0)
+            .unwrap_or(expires))
+    }
+
+    fn authorization(
+        platform: &Platform,
+        www: &typed::WwwAuthenticate,
+    ) -> GlobalResult<typed::Authorization> {
+        let uri = Uri::try_from(format!("sip:{}@{}", platform.id, platform.domain))
+            .hand_log(|msg| warn!("{msg}"))?;
+        let algorithm = www.algorithm.unwrap_or(Algorithm::Md5);
+        let qop = www.qop.as_ref().map(|_| AuthQop::Auth {
+            cnonce: Uuid::new_v4().as_simple().to_string(),
+            nc: 1,
+        });
+        let response = DigestGenerator {
+            username: &platform.local_id,
+            password: platform.password.as_deref().unwrap_or_default(),
+            nonce: &www.nonce,
+            uri: &uri,
+            realm: &www.realm,
+            method: &Method::Register,
+            qop: qop.as_ref(),
+            algorithm,
+        }
+        .compute();
+        Ok(typed::Authorization {
+            scheme: Scheme::Digest,
+            username: platform.local_id.clone(),
+            realm: www.realm.clone(),
+            nonce: www.nonce.clone(),
+            uri,
+            response,
+            algorithm: Some(algorithm),
+            opaque: www.opaque.clone(),
+            qop,
+        })
+    }
+
+    //按心跳周期发送Keepalive,有效期过半或连续无应答时返回以重新注册
+    async fn keep_alive(platform: &Platform, expires: u32) {
+        let refresh = Instant::now() + Duration::from_secs(expires as u64 / 2);
+        let heartbeat = Duration::from_secs(*platform.get_heartbeat() as u64);
+        let mut loss = 0u8;
+        let mut sn = 0u32;
+        loop {
+            time::sleep(heartbeat.min(refresh.saturating_duration_since(Instant::now()))).await;
+            if Instant::now() >= refresh {
+                return;
+            }
+            sn += 1;
+            let body = RequestBuilder::keepalive_body(platform.get_local_id(), sn);
+            let ok = match RequestBuilder::cascade_message(platform, body) {
+                Ok((ident, msg)) => Self::request(platform, ident, msg)
+                    .await
+                    .is_ok_and(|res| res.status_code.code() == 200),
+                Err(_) => false,
+            };
+            if ok {
+                loss = 0;
+                continue;
+            }
+            loss += 1;
+            warn!("级联平台 = [{}],心跳无应答:{}次", platform.get_id(), loss);
+            if loss >= KEEPALIVE_LOSS {
+                return;
+            }
+        }
+    }
+
+    //发送请求并等待最终应答
+    async fn request(platform: &Platform, ident: Ident, msg: SipMessage) -> GlobalResult<Response> {
+        let cascade = CASCADE
+            .get()
+            .ok_or_else(|| GlobalError::new_sys_error("级联未启动", |msg| error!("{msg}")))?;
+        let bill = platform.association();
+        let (tx, mut rx) = mpsc::channel(10);
+        EventSession::listen_event(
+            &ident,
+            Instant::now() + TIMER_F,
+            Container::build_res(Some(tx)),
+        )?;
+        Transaction::client_send(&msg, &cascade.output, &bill);
+        let _ = cascade
+            .output
+            .try_send(Zip::build_data(Package::new(bill, Bytes::from(msg))))
+            .hand_log(|msg| error!("{msg}"));
+        Self::final_response(&ident, &mut rx, TIMER_F).await
+    }
+
+    //等待最终应答,超时或会话结束均清理监听
+    async fn final_response(
+        ident: &Ident,
+        rx: &mut Receiver<(Option<Response>, Instant)>,
+        limit: Duration,
+    ) -> GlobalResult<Response> {
+        let response = time::timeout(limit, async {
+            while let Some((Some(res), _)) = rx.recv().await {
+                if res.status_code.code() >= 200 {
+                    return Some(res);
+                }
+            }
+            None
+        })
+        .await
+        .ok()
+        .flatten();
+        EventSession::remove_event(ident);
+        response.ok_or_else(|| {
+            GlobalError::new_biz_error(1000, "上级平台未响应", |msg| warn!("{msg}"))
+        })
+    }
+
+    async fn reply(
+        req: &Request,
+        tx: &Sender<Zip>,
+        bill: &Association,
+        code: u16,
+    ) -> GlobalResult<()> {
+        let response = ResponseBuilder::build_status_response(req, bill.get_remote_addr(), code)?;
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        Ok(())
+    }
+
+    //上级查询与控制：先应答200,再异步发送应用层应答
+    async fn message(
+        platform: &'static Platform,
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<()> {
+        let msg = match Manscdp::parse(&req.body) {
+            Ok(msg) => msg,
+            Err(err) => {
+                warn!("级联平台 = [{}],{err}", platform.get_id());
+                let response = ResponseBuilder::build_manscdp_reject_response(
+                    &req,
+                    bill.get_remote_addr(),
+                    err.status_code(),
+                )?;
+                let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+                let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+                return Ok(());
+            }
+        };
+        match msg.body {
+            Body::Query(Query::Catalog(head)) => {
+                Self::reply(&req, &tx, bill, 200).await?;
+                common::tokio::spawn(Self::catalog(platform, head));
+            }
+            Body::Query(Query::DeviceInfo(head)) => {
+                Self::reply(&req, &tx, bill, 200).await?;
+                common::tokio::spawn(Self::device_info(platform, head));
+            }
+            Body::Query(Query::DeviceStatus(head)) => {
+                Self::reply(&req, &tx, bill, 200).await?;
+                common::tokio::spawn(Self::device_status(platform, head));
+            }
+            Body::Control(Control::DeviceControl(control)) => {
+                Self::reply(&req, &tx, bill, 200).await?;
+                common::tokio::spawn(Self::device_control(platform, control));
+            }
+            Body::Response(_) | Body::Notify(_) => Self::reply(&req, &tx, bill, 200).await?,
+            body => {
+                warn!(
+                    "级联平台 = [{}];message -- > {:?} 不支持。",
+                    platform.get_id(),
+                    body
+                );
+                Self::reply(&req, &tx, bill, 501).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn respond(platform: &Platform, body: String) {
+        if let Ok((ident, msg)) = RequestBuilder::cascade_message(platform, body) {
+            let _ = Self::request(platform, ident, msg)
+                .await
+                .hand_log(|msg| warn!("{msg}"));
+        }
+    }
+
+    //按共享规则查找上级可见的通道：上报编码 -> 设备通道
+    async fn shared_channel(
+        platform: &Platform,
+        shared_id: &String,
+    ) -> Option<(GbsCascadeChannel, GbsDeviceChannel)> {
+        let share =
+            GbsCascadeChannel::query_gbs_cascade_channel_by_shared_id(platform.get_id(), shared_id)
+                .await
+                .ok()??;
+        let channel = GbsDeviceChannel::query_gbs_device_channel(
+            share.get_device_id(),
+            share.get_channel_id(),
+        )
+        .await
+        .ok()??;
+        Some((share, channel))
+    }
+
+    //分包上报共享节点与通道,逐包等待上级应答
+    async fn catalog(platform: &'static Platform, head: Head) {
+        let platform_id = platform.get_id();
+        let (Ok(nodes), Ok(shares), Ok(channels)) = (
+            GbsCascadeNode::query_gbs_cascade_nodes(platform_id).await,
+            GbsCascadeChannel::query_gbs_cascade_channels(platform_id).await,
+            GbsDeviceChannel::query_shared_gbs_device_channels(platform_id).await,
+        ) else {
+            return;
+        };
+        let local_id = platform.get_local_id();
+        let items = share_items(local_id, nodes, shares, channels);
+        if items.is_empty() {
+            let body = RequestBuilder::catalog_response_body(&head.sn, local_id, 0, &[]);
+            Self::respond(platform, body).await;
+            return;
+        }
+        for page in items.chunks(CATALOG_PAGE) {
+            let body = RequestBuilder::catalog_response_body(&head.sn, local_id, items.len(), page);
+            Self::respond(platform, body).await;
+        }
+    }
+
+    async fn device_info(platform: &'static Platform, head: Head) {
+        let body = if head.device_id == *platform.get_local_id() {
+            let Ok(shares) = GbsCascadeChannel::query_gbs_cascade_channels(platform.get_id()).await
+            else {
+                return;
+            };
+            RequestBuilder::device_info_response_body(
+                &head.sn,
+                &head.device_id,
+                "gbserver",
+                shares.len(),
+            )
+        } else {
+            match Self::shared_channel(platform, &head.device_id).await {
+                Some((_, channel)) => RequestBuilder::device_info_response_body(
+                    &head.sn,
+                    &head.device_id,
+                    channel.get_name().as_deref().unwrap_or_default(),
+                    1,
+                ),
+                None => RequestBuilder::result_response_body(
+                    "DeviceInfo",
+                    &head.sn,
+                    &head.device_id,
+                    "ERROR",
+                ),
+            }
+        };
+        Self::respond(platform, body).await;
+    }
+
+    async fn device_status(platform: &'static Platform, head: Head) {
+        let body = if head.device_id == *platform.get_local_id() {
+            RequestBuilder::device_status_response_body(&head.sn, &head.device_id, true)
+        } else {
+            match Self::shared_channel(platform, &head.device_id).await {
+                Some((_, channel)) => RequestBuilder::device_status_response_body(
+                    &head.sn,
+                    &head.device_id,
+                    Self::online(channel.get_status()),
+                ),
+                None => RequestBuilder::result_response_body(
+                    "DeviceStatus",
+                    &head.sn,
+                    &head.device_id,
+                    "ERROR",
+                ),
+            }
+        };
+        Self::respond(platform, body).await;
+    }
+
+    //云台控制转发至通道所属设备,云台控制无应用层应答;其他控制暂不支持
+    async fn device_control(platform: &'static Platform, control: DeviceControl) {
+        let shared_id = control.device_id;
+        let Some(ptz_cmd) = control.ptz_cmd else {
+            warn!(
+                "级联平台 = [{}],通道 = [{}],仅支持转发云台控制",
+                platform.get_id(),
+                shared_id
+            );
+            let body = RequestBuilder::result_response_body(
+                "DeviceControl",
+                &control.sn,
+                &shared_id,
+                "ERROR",
+            );
+            Self::respond(platform, body).await;
+            return;
+        };
+        match Self::shared_channel(platform, &shared_id).await {
+            Some((share, _)) => {
+                let _ = CmdControl::control_ptz_line(
+                    share.get_device_id(),
+                    share.get_channel_id(),
+                    ptz_cmd.trim(),
+                )
+                .await
+                .hand_log(|msg| warn!("{msg}"));
+            }
+            None => warn!(
+                "级联平台 = [{}],未共享通道 = [{}]",
+                platform.get_id(),
+                shared_id
+            ),
+        }
+    }
+
+    fn online(status: &str) -> bool {
+        matches!(
+            &status.to_ascii_uppercase()[..],
+            "ON" | "ONLINE" | "ONLY" | ""
+        )
+    }
+
+    //上级点播：先应答100,向设备点播后以设备应答SDP回复上级
+    async fn invite(
+        platform: &'static Platform,
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<()> {
+        Self::reply(&req, &tx, bill, 100).await?;
+        let bill = bill.clone();
+        common::tokio::spawn(async move {
+            let response: SipMessage = match Self::relay_invite(platform, &req, &bill).await {
+                Ok(response) => response.into(),
+                Err(code) => {
+                    match ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), code)
+                    {
+                        Ok(response) => response,
+                        Err(_) => return,
+                    }
+                }
+            };
+            let zip = Zip::build_data(Package::new(bill, Bytes::from(response)));
+            let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        });
+        Ok(())
+    }
+
+    async fn relay_invite(
+        platform: &Platform,
+        req: &Request,
+        bill: &Association,
+    ) -> Result<Response, u16> {
+        let call_id = req
+            .call_id_header()
+            .map_err(|_| 400u16)?
+            .value()
+            .to_string();
+        let shared_id = req
+            .uri
+            .auth
+            .as_ref()
+            .map(|auth| auth.user.clone())
+            .ok_or(400u16)?;
+        //仅接受共享给该平台的通道
+        let (share, _) = Self::shared_channel(platform, &shared_id)
+            .await
+            .ok_or(403u16)?;
+        let device_id = share.get_device_id();
+        let channel_id = share.get_channel_id().clone();
+        let offer = Offer::parse(&req.body).map_err(|_| 488u16)?;
+        let media_address = MediaAddress::build(offer.ip, offer.port);
+        let range = TimeRange::build(offer.start_time, offer.end_time);
+        let invite = match &offer.name[..] {
+            "Playback" => {
+                CmdStream::play_back_invite(
+                    device_id,
+                    &channel_id,
+                    media_address,
+                    offer.stream_mode,
+                    &offer.ssrc,
+                    range,
+                )
+                .await
+            }
+            "Download" => {
+                CmdStream::download_invite(
+                    device_id,
+                    &channel_id,
+                    media_address,
+                    offer.stream_mode,
+                    &offer.ssrc,
+                    range,
+                    offer.download_speed.unwrap_or(1),
+                )
+                .await
+            }
+            _ => {
+                CmdStream::play_live_invite(
+                    device_id,
+                    &channel_id,
+                    media_address,
+                    offer.stream_mode,
+                    &offer.ssrc,
+                )
+                .await
+            }
+        };
+        let (res, _) = invite.map_err(|_| 480u16)?;
+        let device_call_id = CmdStream::invite_ack(device_id, &res).map_err(|_| 500u16)?;
+        //上级已取消或INVITE事务已超时,挂断设备
+        let answered = Transaction::server_invite_pending(req) == Some(true);
+        let response = if answered {
+            //应答SDP中的通道编码替换为上报编码
+            let sdp = String::from_utf8_lossy(&res.body).replace(&channel_id, &shared_id);
+            ResponseBuilder::build_sdp_ok_response(
+                req,
+                bill.get_remote_addr(),
+                &shared_id,
+                sdp.into_bytes(),
+            )
+            .ok()
+        } else {
+            None
+        };
+        let dialog = response.as_ref().and_then(|response| {
+            let local_tag = ResponseBuilder::get_tag_by_header_to(response).ok()?;
+            Dialog::uas(platform.get_id(), req, &local_tag).ok()
+        });
+        let (Some(response), Some(dialog)) = (response, dialog) else {
+            let _ = CmdStream::play_bye(device_call_id, device_id, &channel_id).await;
+            return Err(if answered { 500 } else { 487 });
+        };
+        Dialog::track(dialog);
+        RELAYS.insert(
+            call_id,
+            Relay {
+                platform_id: platform.get_id().clone(),
+                device_id: device_id.clone(),
+                channel_id,
+                device_call_id,
+            },
+        );
+        Ok(response)
+    }
+
+    //上级挂断：应答后挂断设备点播
+    async fn bye(req: Request, tx: Sender<Zip>, bill: &Association) -> GlobalResult<()> {
+        let dialog = Dialog::receive(&req);
+        let code = dialog.as_ref().map_or_else(|code| *code, |_| 200);
+        Self::reply(&req, &tx, bill, code).await?;
+        let Ok(dialog) = dialog else {
+            return Ok(());
+        };
+        Dialog::terminate(dialog.get_call_id());
+        if let Some((_, relay)) = RELAYS.remove(dialog.get_call_id()) {
+            common::tokio::spawn(async move {
+                let _ =
+                    CmdStream::play_bye(relay.device_call_id, &relay.device_id, &relay.channel_id)
+                        .await;
+            });
+        }
+        Ok(())
+    }
+}
+
+//共享节点类型：行政区划为2/4/6/8位编码,业务分组与虚拟组织为20位编码的类型码215/216
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    CivilCode,
+    BusinessGroup,
+    VirtualOrganization,
+}
+
+impl NodeKind {
+    pub fn of(node_id: &str) -> Option<NodeKind> {
+        if !node_id.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        match node_id.len() {
+            2 | 4 | 6 | 8 => Some(NodeKind::CivilCode),
+            20 => match &node_id[10..13] {
+                "215" => Some(NodeKind::BusinessGroup),
+                "216" => Some(NodeKind::VirtualOrganization),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+//上报上级的目录项
+pub enum ShareItem {
+    Node {
+        node: GbsCascadeNode,
+        kind: NodeKind,
+    },
+    //id与parent_id为上报编码,civil_code取所属行政区划节点,无则沿用通道自身
+    Channel {
+        channel: GbsDeviceChannel,
+        id: String,
+        parent_id: String,
+        civil_code: Option<String>,
+    },
+}
+
+//共享规则生成目录：先节点后通道;未挂节点或节点已删除的通道挂在平台下
+fn share_items(
+    local_id: &String,
+    nodes: Vec<GbsCascadeNode>,
+    shares: Vec<GbsCascadeChannel>,
+    channels: Vec<GbsDeviceChannel>,
+) -> Vec<ShareItem> {
+    let node_map: HashMap<&String, &GbsCascadeNode> = nodes
+        .iter()
+        .map(|node| (node.get_node_id(), node))
+        .collect();
+    let mut channel_map: HashMap<(String, String), GbsDeviceChannel> = channels
+        .into_iter()
+        .map(|channel| {
+            (
+                (
+                    channel.get_device_id().clone(),
+                    channel.get_channel_id().clone(),
+                ),
+                channel,
+            )
+        })
+        .collect();
+    let mut channel_items = Vec::with_capacity(shares.len());
+    for share in &shares {
+        let key = (
+            share.get_device_id().clone(),
+            share.get_channel_id().clone(),
+        );
+        let Some(channel) = channel_map.remove(&key) else {
+            continue;
+        };
+        let parent = share
+            .get_parent_id()
+            .as_ref()
+            .filter(|id| node_map.contains_key(id));
+        if parent.is_none() {
+            if let Some(parent_id) = share.get_parent_id() {
+                warn!(
+                    "共享通道 = [{}],所属节点 = [{}]不存在,改挂平台下",
+                    share.shared_id(),
+                    parent_id
+                );
+            }
+        }
+        channel_items.push(ShareItem::Channel {
+            id: share.shared_id().clone(),
+            parent_id: parent.unwrap_or(local_id).clone(),
+            civil_code: civil_code(&node_map, parent).or_else(|| channel.get_civil_code().clone()),
+            channel,
+        });
+    }
+    let mut items: Vec<ShareItem> = nodes
+        .into_iter()
+        .filter_map(|node| {
+            NodeKind::of(node.get_node_id()).map(|kind| ShareItem::Node { node, kind })
+        })
+        .collect();
+    items.extend(channel_items);
+    items
+}
+
+//沿上级节点查找所属行政区划,限制深度避免节点成环
+fn civil_code<'a>(
+    node_map: &HashMap<&'a String, &'a GbsCascadeNode>,
+    mut parent: Option<&'a String>,
+) -> Option<String> {
+    for _ in 0..=node_map.len() {
+        let id = parent?;
+        if NodeKind::of(id) == Some(NodeKind::CivilCode) {
+            return Some(id.clone());
+        }
+        parent = node_map
+            .get(id)
+            .and_then(|node| node.get_parent_id().as_ref());
+    }
+    None
+}
+
+//上级点播SDP：s=Play/Playback/Download,t=回放起止时间,y=SSRC
+#[derive(Debug)]
+struct Offer {
+    name: String,
+    ip: String,
+    port: u16,
+    stream_mode: StreamMode,
+    ssrc: String,
+    start_time: u32,
+    end_time: u32,
+    download_speed: Option<u8>,
+}
+
+impl Offer {
+    fn parse(sdp: &[u8]) -> GlobalResult<Offer> {
+        let session = sdp_types::Session::parse(sdp).map_err(|err| {
+            GlobalError::new_sys_error(&format!("上级SDP解析失败:{err}"), |msg| {
+                warn!("{msg}")
+            })
+        })?;
+        let media = session
+            .medias
+            .iter()
+            .find(|media| media.media.eq_ignore_ascii_case("video"))
+            .ok_or_else(|| {
+                GlobalError::new_sys_error("上级SDP缺少视频描述", |msg| warn!("{msg}"))
+            })?;
+        let connection = media
+            .connections
+            .first()
+            .or(session.connection.as_ref())
+            .ok_or_else(|| {
+                GlobalError::new_sys_error("上级SDP缺少连接地址", |msg| warn!("{msg}"))
+            })?;
+        let attribute = |name: &str| {
+            media
+                .attributes
+                .iter()
+                .find(|attr| attr.attribute.eq(name))
+                .and_then(|attr| attr.value.as_deref())
+                .map(|val| val.trim().to_lowercase())
+        };
+        let stream_mode = if !media.proto.to_uppercase().starts_with("TCP") {
+            StreamMode::Udp
+        } else if attribute("setup").as_deref() == Some("passive") {
+            StreamMode::TcpPassive
+        } else {
+            StreamMode::TcpActive
+        };
+        //y字段sdp_types不解析,需单独读取
+        let ssrc = String::from_utf8_lossy(sdp)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("y=").map(|v| v.trim().to_string()))
+            .filter(|ssrc| !ssrc.is_empty())
+            .ok_or_else(|| {
+                GlobalError::new_sys_error("上级SDP缺少SSRC", |msg| warn!("{msg}"))
+            })?;
+        let (start_time, end_time) = session
+            .times
+            .first()
+            .map(|time| (time.start_time as u32, time.stop_time as u32))
+            .unwrap_or_default();
+        Ok(Offer {
+            name: session.session_name.trim().to_string(),
+            ip: connection.connection_address.clone(),
+            port: media.port,
+            stream_mode,
+            ssrc,
+            start_time,
+            end_time,
+            download_speed: attribute("downloadspeed").and_then(|speed| speed.parse().ok()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::tokio;
+
+    #[tokio::test]
+    async fn test_silent_superior() {
+        let ident = Ident::new(
+            "34020000002000000002".to_string(),
+            "silent@10.0.0.1".to_string(),
+            "1 REGISTER".to_string(),
+        );
+        let (tx, mut rx) = mpsc::channel(10);
+        EventSession::listen_event(
+            &ident,
+            Instant::now() + TIMER_F,
+            Container::build_res(Some(tx.clone())),
+        )
+        .unwrap();
+        let res = Cascade::final_response(&ident, &mut rx, Duration::from_millis(50)).await;
+        assert!(res.is_err());
+        assert!(!EventSession::contains_call_id("silent@10.0.0.1"));
+        //超时后已清理,可再次监听
+        EventSession::listen_event(
+            &ident,
+            Instant::now() + TIMER_F,
+            Container::build_res(Some(tx)),
+        )
+        .unwrap();
+        EventSession::remove_event(&ident);
+    }
+
+    #[test]
+    fn test_authorization() {
+        let platform = Platform {
+            id: "34020000002000000002".to_string(),
+            domain: "3402000000".to_string(),
+            ip: Ipv4Addr::LOCALHOST,
+            port: 5060,
+            password: Some("12345678".to_string()),
+            local_id: "34020000002000000001".to_string(),
+            expires: 3600,
+            heartbeat: 60,
+        };
+        assert_eq!(platform.local_domain(), "3402000000");
+        let www = typed::WwwAuthenticate {
+            realm: "3402000000".to_string(),
+            nonce: "abcdef".to_string(),
+            algorithm: Some(Algorithm::Md5),
+            qop: Some(rsip::headers::auth::Qop::Auth),
+            ..Default::default()
+        };
+        let auth = Cascade::authorization(&platform, &www).unwrap();
+        assert_eq!(auth.username, "34020000002000000001");
+        assert!(DigestGenerator::from(&auth, "12345678", &Method::Register).verify(&auth.response));
+        assert!(!DigestGenerator::from(&auth, "87654321", &Method::Register).verify(&auth.response));
+    }
+
+    #[test]
+    fn test_offer_parse() {
+        let sdp = "v=0\r\n\
+                   o=34020000001320000001 0 0 IN IP4 10.0.0.9\r\n\
+                   s=Playback\r\n\
+                   u=34020000001320000001:0\r\n\
+                   c=IN IP4 10.0.0.9\r\n\
+                   t=1700000000 1700003600\r\n\
+                   m=video 30000 TCP/RTP/AVP 96 98\r\n\
+                   a=recvonly\r\n\
+                   a=setup:passive\r\n\
+                   a=rtpmap:96 PS/90000\r\n\
+                   y=1100000001\r\n";
+        let offer = Offer::parse(sdp.as_bytes()).unwrap();
+        assert_eq!(offer.name, "Playback");
+        assert_eq!(offer.ip, "10.0.0.9");
+        assert_eq!(offer.port, 30000);
+        assert_eq!(offer.stream_mode, StreamMode::TcpPassive);
+        assert_eq!(offer.ssrc, "1100000001");
+        assert_eq!((offer.start_time, offer.end_time), (1700000000, 1700003600));
+        assert!(Offer::parse(sdp.replace("y=1100000001\r\n", "").as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_node_kind() {
+        assert_eq!(NodeKind::of("3402"), Some(NodeKind::CivilCode));
+        assert_eq!(NodeKind::of("34020000"), Some(NodeKind::CivilCode));
+        assert_eq!(
+            NodeKind::of("34020000002150000001"),
+            Some(NodeKind::BusinessGroup)
+        );
+        assert_eq!(
+            NodeKind::of("34020000002160000001"),
+            Some(NodeKind::VirtualOrganization)
+        );
+        assert_eq!(NodeKind::of("34020000001320000001"), None);
+        assert_eq!(NodeKind::of("340"), None);
+        assert_eq!(NodeKind::of("34a2"), None);
+    }
+
+    #[test]
+    fn test_share_items() {
+        use crate::gb::handler::manscdp::Response as ManscdpResponse;
+        use crate::general::model::{CascadeChannelModel, CascadeNodeModel};
+
+        let platform_id = "34020000002000000002".to_string();
+        let local_id = "34020000002000000001".to_string();
+        let node = |id: &str, parent: Option<&str>| {
+            GbsCascadeNode::build(&CascadeNodeModel::new(
+                platform_id.clone(),
+                id.to_string(),
+                id.to_string(),
+                parent.map(str::to_string),
+                None,
+            ))
+        };
+        let share = |channel_id: &str, share_id: Option<&str>, parent: Option<&str>| {
+            GbsCascadeChannel::build(&CascadeChannelModel::new(
+                platform_id.clone(),
+                "34020000001110000001".to_string(),
+                channel_id.to_string(),
+                share_id.map(str::to_string),
+                parent.map(str::to_string),
+            ))
+        };
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>Catalog</CmdType>
+<SN>11</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<SumNum>2</SumNum>
+<DeviceList Num="2">
+<Item>
+<DeviceID>34020000001320000001</DeviceID>
+<CivilCode>3401</CivilCode>
+</Item>
+<Item>
+<DeviceID>34020000001320000002</DeviceID>
+<CivilCode>3401</CivilCode>
+</Item>
+</DeviceList>
+</Response>"#;
+        let Body::Response(ManscdpResponse::Catalog(list)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected Catalog");
+        };
+        let channels = GbsDeviceChannel::build(&"34020000001110000001".to_string(), &list);
+        let nodes = vec![
+            node("3402", None),
+            node("34020000002160000001", Some("3402")),
+            node("34020000001320000009", None),
+        ];
+        let shares = vec![
+            share(
+                "34020000001320000001",
+                Some("34020000001320000101"),
+                Some("34020000002160000001"),
+            ),
+            share("34020000001320000002", None, Some("34020000002160000009")),
+            share("34020000001320000003", None, None),
+        ];
+        let items = share_items(&local_id, nodes, shares, channels);
+        //非法节点编码与不存在的通道不上报
+        assert_eq!(items.len(), 4);
+        assert!(matches!(
+            &items[1],
+            ShareItem::Node {
+                kind: NodeKind::VirtualOrganization,
+                ..
+            }
+        ));
+        let ShareItem::Channel {
+            id,
+            parent_id,
+            civil_code,
+            ..
+        } = &items[2]
+        else {
+            panic!("expected channel");
+        };
+        assert_eq!(id, "34020000001320000101");
+        assert_eq!(parent_id, "34020000002160000001");
+        assert_eq!(civil_code.as_deref(), Some("3402"));
+        //所属节点不存在时挂在平台下,沿用通道自身行政区划
+        let ShareItem::Channel {
+            id,
+            parent_id,
+            civil_code,
+            ..
+        } = &items[3]
+        else {
+            panic!("expected channel");
+        };
+        assert_eq!(id, "34020000001320000002");
+        assert_eq!(parent_id, &local_id);
+        assert_eq!(civil_code.as_deref(), Some("3401"));
+    }
+}
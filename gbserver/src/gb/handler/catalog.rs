@@ -0,0 +1,211 @@
+use crate::gb::handler::cmd::{CmdQuery, CmdResponse};
+use crate::gb::handler::manscdp::CatalogList;
+use crate::general::model::{CatalogSyncEvent, CatalogSyncResult};
+use crate::service::callback;
+use crate::store::entity::GbsDeviceChannel;
+use common::bytes::Bytes;
+use common::chrono::Local;
+use common::dashmap::DashMap;
+use common::exception::{GlobalResult, TransError};
+use common::log::{error, info, warn};
+use common::once_cell::sync::Lazy;
+use common::serde_json;
+use common::tokio::time::{sleep_until, Instant};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+//目录分包汇总：(device_id,SN) -> 已收到的分包
+static CATALOG_FRAGMENTS: Lazy<DashMap<(String, String), Fragments>> = Lazy::new(DashMap::new);
+//两个分包之间的最长间隔,超时则按未收全结束
+const FRAGMENT_IDLE: u64 = 15;
+
+struct Fragments {
+    //未携带SumNum时只能等待超时结束
+    sum_num: Option<u32>,
+    //累计收到的Item数,用于判断收全；部分设备会重复上报同一通道
+    received: u32,
+    //按通道去重后入库
+    channels: HashMap<String, GbsDeviceChannel>,
+    deadline: Instant,
+}
+
+pub struct CatalogSync;
+
+impl CatalogSync {
+    //目录查询应答：按SN汇总分包,收全或超时后统一入库
+    pub async fn feed(device_id: &String, list: CatalogList) {
+        let channels = GbsDeviceChannel::build(device_id, &list);
+        let sum_num = list.sum_num;
+        let received = list.device_list.items.len() as u32;
+        let key = (device_id.to_string(), list.sn);
+        let deadline = Instant::now() + Duration::from_secs(FRAGMENT_IDLE);
+        let (first, complete) = {
+            let mut first = false;
+            let mut entry = CATALOG_FRAGMENTS.entry(key.clone()).or_insert_with(|| {
+                first = true;
+                Fragments {
+                    sum_num,
+                    received: 0,
+                    channels: HashMap::new(),
+                    deadline,
+                }
+            });
+            entry.sum_num = entry.sum_num.max(sum_num);
+            entry.deadline = deadline;
+            entry.received += received;
+            for dc in channels {
+                if !dc.get_channel_id().is_empty() {
+                    entry.channels.insert(dc.get_channel_id().clone(), dc);
+                }
+            }
+            let complete = entry
+                .sum_num
+                .is_some_and(|sum_num| entry.received >= sum_num);
+            (first, complete)
+        };
+        if complete {
+            if let Some((_, fragments)) = CATALOG_FRAGMENTS.remove(&key) {
+                Self::finish(key, fragments, true).await;
+            }
+        } else if first {
+            common::tokio::spawn(Self::watch(key));
+        }
+    }
+
+    //分包空闲超时后按未收全结束
+    async fn watch(key: (String, String)) {
+        loop {
+            let deadline = match CATALOG_FRAGMENTS.get(&key) {
+                None => return,
+                Some(fragments) => fragments.deadline,
+            };
+            sleep_until(deadline).await;
+            if let Some((_, fragments)) = CATALOG_FRAGMENTS
+                .remove_if(&key, |_, fragments| fragments.deadline <= Instant::now())
+            {
+                Self::finish(key, fragments, false).await;
+                return;
+            }
+        }
+    }
+
+    async fn finish(key: (String, String), fragments: Fragments, complete: bool) {
+        let (device_id, sn) = key;
+        let channels: Vec<GbsDeviceChannel> = fragments.channels.into_values().collect();
+        let sum_num = fragments.sum_num.unwrap_or_default();
+        let received = fragments.received;
+        let result = match Self::save(&device_id, &sn, sum_num, received, &channels, complete).await
+        {
+            Ok(result) => result,
+            Err(_) => return,
+        };
+        if !complete {
+            warn!(
+                "目录分包未收全: device_id = {},sn = {},sum_num = {},received = {}",
+                device_id, sn, result.sum_num, result.received
+            );
+        }
+        info!("目录同步: {:?}", &result);
+        if let Ok(bytes) = serde_json::to_vec(&result).hand_log(|msg| error!("{msg}")) {
+            CmdResponse::reply(&device_id, &sn, Bytes::from(bytes));
+        }
+        //通过预置位探测是否有云台可用,仅查询摄像机类通道(类型码131/132)
+        common::tokio::spawn(async move {
+            for dc in channels {
+                let channel_id = dc.get_channel_id();
+                if matches!(channel_id.get(10..13), Some("131") | Some("132")) {
+                    let _ = CmdQuery::query_preset(dc.get_device_id(), Some(channel_id))
+                        .await
+                        .hand_log(|msg| error!("{msg}"));
+                }
+            }
+        });
+        let event = CatalogSyncEvent {
+            event: "catalog_sync".to_string(),
+            result,
+            ts: Local::now().timestamp(),
+        };
+        let _ = callback::call_catalog_sync(&event).await;
+    }
+
+    async fn save(
+        device_id: &String,
+        sn: &str,
+        sum_num: u32,
+        received: u32,
+        channels: &[GbsDeviceChannel],
+        complete: bool,
+    ) -> GlobalResult<CatalogSyncResult> {
+        let existing: HashSet<String> = GbsDeviceChannel::query_gbs_device_channel_ids(device_id)
+            .await?
+            .into_iter()
+            .collect();
+        GbsDeviceChannel::upsert_gbs_device_channel(channels).await?;
+        let (added, updated) = Self::diff(&existing, channels);
+        //仅在收全时下线,避免分包丢失导致误判；保留通道记录及其关联
+        let removed: Vec<String> = if complete {
+            let current: HashSet<&String> = channels.iter().map(|dc| dc.get_channel_id()).collect();
+            existing
+                .iter()
+                .filter(|channel_id| !current.contains(channel_id))
+                .cloned()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        GbsDeviceChannel::offline_gbs_device_channels(device_id, &removed).await?;
+        Ok(CatalogSyncResult {
+            device_id: device_id.to_string(),
+            sn: sn.to_string(),
+            sum_num,
+            received,
+            added,
+            updated,
+            removed: removed.len() as u32,
+            complete,
+        })
+    }
+
+    fn diff(existing: &HashSet<String>, channels: &[GbsDeviceChannel]) -> (u32, u32) {
+        let updated = channels
+            .iter()
+            .filter(|dc| existing.contains(dc.get_channel_id()))
+            .count() as u32;
+        (channels.len() as u32 - updated, updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::handler::manscdp::{Body, Manscdp, Response};
+
+    #[test]
+    fn test_catalog_diff() {
+        let existing: HashSet<String> = ["34020000001320000001", "34020000001320000002"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>Catalog</CmdType>
+<SN>11</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<SumNum>2</SumNum>
+<DeviceList Num="2">
+<Item>
+<DeviceID>34020000001320000002</DeviceID>
+</Item>
+<Item>
+<DeviceID>34020000001320000003</DeviceID>
+</Item>
+</DeviceList>
+</Response>"#;
+        let Body::Response(Response::Catalog(list)) = Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected Catalog");
+        };
+        let channels = GbsDeviceChannel::build(&"34020000001110000001".to_string(), &list);
+        assert_eq!(CatalogSync::diff(&existing, &channels), (1, 1));
+    }
+}
@@ -1,19 +1,154 @@
-use crate::gb::handler::builder::{RequestBuilder, ResponseBuilder};
-use crate::gb::handler::events::event::{Container, EventSession, Ident};
-use crate::gb::shared::rw::RequestOutput;
-use crate::general::model::{MediaAddress, PtzControlModel, StreamMode, TimeRange};
+use crate::gb::handler::builder::{RequestBuilder, ResponseBuilder, SdpBuilder};
+use crate::gb::handler::dialog::Dialog;
+use crate::gb::handler::events::event::{Container, EventSession, Ident, EXPIRES};
+use crate::gb::handler::manscdp::{self, Body, Manscdp};
+use crate::gb::handler::parser;
+use crate::gb::shared::rw::{RWSession, RequestOutput};
+use crate::gb::shared::transaction::TIMER_B;
+use crate::gb::SessionConf;
+use crate::general::cache::Cache;
+use crate::general::model::{
+    AlarmSubscribeModel, CatalogSyncResult, ConfigDownloadInfo, DeviceConfigModel,
+    DeviceControlCmd, DeviceStatusInfo, MediaAddress, PtzCmd, PtzControlModel, RecordInfo,
+    RecordItem, StreamMode, SubscribeDialog, TimeRange,
+};
+use common::bytes::Bytes;
 use common::exception::{GlobalError, GlobalResult, TransError};
 use common::log::{debug, error, warn};
+use common::rand::{rng, Rng};
+use common::serde_json;
 use common::tokio::sync::mpsc;
-use common::tokio::time::Instant;
+use common::tokio::sync::mpsc::Receiver;
+use common::tokio::time::{self, Instant};
 use regex::Regex;
 use rsip::prelude::{HeadersExt, UntypedHeader};
-use rsip::{Response, SipMessage};
+use rsip::{Method, Response, SipMessage};
 use std::collections::HashMap;
 use std::time::Duration;
 
+const KEY_CMD_RESPONSE: &str = "KEY_CMD_RESPONSE:";
+//语音广播会话：KEY_BROADCAST:{音频输出通道}
+pub const KEY_BROADCAST: &str = "KEY_BROADCAST:";
+//等待设备MANSCDP应答消息的超时时间,分包应答需预留足够时间
+const RESPONSE_EXPIRES: u64 = 15;
+//订阅对话：KEY_SUBSCRIBE:{事件类型}:{device_id}
+const KEY_SUBSCRIBE: &str = "KEY_SUBSCRIBE:";
+pub const SUBSCRIBE_CATALOG: &str = "Catalog";
+pub const SUBSCRIBE_ALARM: &str = "Alarm";
+pub const SUBSCRIBE_MOBILE_POSITION: &str = "MobilePosition";
+pub const SUBSCRIBE_KINDS: [&str; 3] = [
+    SUBSCRIBE_CATALOG,
+    SUBSCRIBE_ALARM,
+    SUBSCRIBE_MOBILE_POSITION,
+];
+//默认订阅有效期(秒)
+const SUBSCRIBE_EXPIRES: u32 = 3600;
+
+//设备应答以独立MESSAGE上报,通过device_id + SN关联下发的查询
 pub struct CmdResponse;
 
+impl CmdResponse {
+    pub fn listen(device_id: &str, sn: u32, expires: Duration) -> Receiver<Option<Bytes>> {
+        let (tx, rx) = mpsc::channel(64);
+        let key = format!("{KEY_CMD_RESPONSE}{device_id}:{sn}");
+        Cache::state_insert(key, Bytes::new(), Some(Instant::now() + expires), Some(tx));
+        rx
+    }
+
+    pub fn unlisten(device_id: &str, sn: u32) {
+        let key = format!("{KEY_CMD_RESPONSE}{device_id}:{sn}");
+        Cache::state_remove(&key);
+    }
+
+    //下发请求并监听应答：SIP层应答失败立即返回,调用方读取完毕后需unlisten
+    pub async fn send(
+        device_id: &str,
+        sn: u32,
+        ident: Ident,
+        msg: SipMessage,
+        expires: Duration,
+    ) -> GlobalResult<Receiver<Option<Bytes>>> {
+        let rx = Self::listen(device_id, sn, expires);
+        let (tx, mut sip_rx) = mpsc::channel(10);
+        if let Err(err) = RequestOutput::new(ident.clone(), msg, Some(tx)).do_send() {
+            Self::unlisten(device_id, sn);
+            return Err(err);
+        }
+        let sip_res = sip_rx.recv().await;
+        EventSession::remove_event(&ident);
+        if let Some((Some(res), _)) = sip_res {
+            if res.status_code.code() >= 300 {
+                Self::unlisten(device_id, sn);
+                return Err(GlobalError::new_biz_error(
+                    3000,
+                    &res.status_code.to_string(),
+                    |msg| error!("{msg}"),
+                ));
+            }
+        }
+        Ok(rx)
+    }
+
+    //等待控制类应答并校验Result,超过expires视为未响应
+    pub async fn recv_result(
+        mut rx: Receiver<Option<Bytes>>,
+        device_id: &str,
+        sn: u32,
+        desc: &str,
+        expires: Duration,
+    ) -> GlobalResult<()> {
+        let res = time::timeout(expires, rx.recv()).await.ok().flatten();
+        Self::unlisten(device_id, sn);
+        let bytes = match res {
+            Some(Some(bytes)) => bytes,
+            _ => {
+                return Err(GlobalError::new_biz_error(
+                    1000,
+                    &format!("{desc}未响应或超时"),
+                    |msg| error!("{msg}"),
+                ))
+            }
+        };
+        let res = match Manscdp::parse(&bytes).hand_log(|msg| error!("{msg}"))?.body {
+            Body::Response(
+                manscdp::Response::DeviceControl(res)
+                | manscdp::Response::DeviceConfig(res)
+                | manscdp::Response::Alarm(res)
+                | manscdp::Response::Broadcast(res),
+            ) => res,
+            body => {
+                return Err(GlobalError::new_biz_error(
+                    1100,
+                    &format!("{desc}应答类型不匹配:{body:?}"),
+                    |msg| error!("{msg}"),
+                ))
+            }
+        };
+        if res.is_ok() {
+            return Ok(());
+        }
+        Err(GlobalError::new_biz_error(
+            1100,
+            &format!("{desc}失败:{}", res.result),
+            |msg| error!("{msg}"),
+        ))
+    }
+
+    //将设备应答原文转发给监听方,无监听则忽略
+    pub fn dispatch(device_id: &str, sn: &str, body: &[u8]) {
+        if !sn.is_empty() {
+            Self::reply(device_id, sn, Bytes::copy_from_slice(body));
+        }
+    }
+
+    pub fn reply(device_id: &str, sn: &str, bytes: Bytes) {
+        let key = format!("{KEY_CMD_RESPONSE}{device_id}:{sn}");
+        if let Some((_, Some(tx))) = Cache::state_get(&key) {
+            let _ = tx.try_send(Some(bytes)).hand_log(|msg| warn!("{msg}"));
+        }
+    }
+}
+
 pub struct CmdQuery;
 
 impl CmdQuery {
@@ -32,9 +167,172 @@ impl CmdQuery {
         let (ident, msg) = RequestBuilder::query_device_catalog(device_id).await?;
         RequestOutput::new(ident, msg, None).do_send()
     }
-    pub async fn subscribe_device_catalog(device_id: &String) -> GlobalResult<()> {
-        let (ident, msg) = RequestBuilder::subscribe_device_catalog(device_id).await?;
-        RequestOutput::new(ident, msg, None).do_send()
+    //OPTIONS探测：limit内收到任意应答即视为设备可达
+    pub async fn options(device_id: &String, limit: Duration) -> GlobalResult<u16> {
+        let (ident, msg) = RequestBuilder::options(device_id).await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        RequestOutput::new(ident.clone(), msg, Some(tx)).do_send()?;
+        let res = time::timeout(limit, rx.recv()).await.ok().flatten();
+        EventSession::remove_event(&ident);
+        match res {
+            Some((Some(res), _)) => Ok(res.status_code.code()),
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                "设备OPTIONS探测无应答",
+                |msg| warn!("{msg}"),
+            )),
+        }
+    }
+    //收集设备按SumNum分包上报的录像列表,超时则返回已收到的部分
+    pub async fn query_record_info(
+        device_id: &String,
+        channel_id_opt: Option<&String>,
+        range: TimeRange,
+        record_type: &str,
+        secrecy: u8,
+    ) -> GlobalResult<Vec<RecordItem>> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) = RequestBuilder::query_record_info(
+            device_id,
+            channel_id_opt,
+            sn,
+            &range,
+            record_type,
+            secrecy,
+        )
+        .await?;
+        let expires = Duration::from_secs(RESPONSE_EXPIRES);
+        let mut rx = CmdResponse::listen(device_id, sn, expires);
+        if let Err(err) = RequestOutput::new(ident, msg, None).do_send() {
+            CmdResponse::unlisten(device_id, sn);
+            return Err(err);
+        }
+        let deadline = Instant::now() + expires;
+        let mut sum_num = None;
+        let mut items = Vec::new();
+        while let Ok(Some(Some(bytes))) = time::timeout_at(deadline, rx.recv()).await {
+            if let Ok(Body::Response(manscdp::Response::RecordInfo(res))) = Manscdp::parse(&bytes)
+                .hand_log(|msg| error!("{msg}"))
+                .map(|msg| msg.body)
+            {
+                let info = RecordInfo::from(res);
+                sum_num = Some(info.sum_num);
+                items.extend(info.items);
+                if items.len() >= info.sum_num as usize {
+                    break;
+                }
+            }
+        }
+        CmdResponse::unlisten(device_id, sn);
+        match sum_num {
+            None => Err(GlobalError::new_biz_error(
+                1000,
+                "录像查询未响应或超时",
+                |msg| error!("{msg}"),
+            )),
+            Some(sum_num) => {
+                if items.len() < sum_num as usize {
+                    warn!(
+                        "录像查询分包未收全: device_id = {},sn = {},sum_num = {},received = {}",
+                        device_id,
+                        sn,
+                        sum_num,
+                        items.len()
+                    );
+                }
+                items.sort_by_key(|item| item.st);
+                Ok(items)
+            }
+        }
+    }
+    //下发目录查询并等待分包汇总完成后的同步结果
+    pub async fn sync_device_catalog(
+        device_id: &String,
+        expires: Duration,
+    ) -> GlobalResult<CatalogSyncResult> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) = RequestBuilder::query_device_catalog_by_sn(device_id, sn).await?;
+        let mut rx = CmdResponse::send(device_id, sn, ident, msg, expires).await?;
+        let res = time::timeout(expires, rx.recv()).await.ok().flatten();
+        CmdResponse::unlisten(device_id, sn);
+        match res {
+            Some(Some(bytes)) => {
+                Ok(serde_json::from_slice(&bytes).hand_log(|msg| error!("{msg}"))?)
+            }
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                "目录同步未响应或超时",
+                |msg| error!("{msg}"),
+            )),
+        }
+    }
+    pub async fn query_device_status(
+        device_id: &String,
+        expires: Duration,
+    ) -> GlobalResult<DeviceStatusInfo> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) = RequestBuilder::query_device_status(device_id, sn).await?;
+        let mut rx = CmdResponse::send(device_id, sn, ident, msg, expires).await?;
+        let res = time::timeout(expires, rx.recv()).await.ok().flatten();
+        CmdResponse::unlisten(device_id, sn);
+        match res {
+            Some(Some(bytes)) => match Manscdp::parse(&bytes).hand_log(|msg| error!("{msg}"))?.body
+            {
+                Body::Response(manscdp::Response::DeviceStatus(res)) => {
+                    Ok(DeviceStatusInfo::from(res))
+                }
+                body => Err(GlobalError::new_biz_error(
+                    1100,
+                    &format!("设备状态应答类型不匹配:{body:?}"),
+                    |msg| error!("{msg}"),
+                )),
+            },
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                "设备状态查询未响应或超时",
+                |msg| error!("{msg}"),
+            )),
+        }
+    }
+    //设备可能按配置类型分别应答,合并至所有类型到齐或超时
+    pub async fn query_config_download(
+        device_id: &String,
+        config_types: &[String],
+        expires: Duration,
+    ) -> GlobalResult<ConfigDownloadInfo> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) =
+            RequestBuilder::query_config_download(device_id, sn, config_types).await?;
+        let mut rx = CmdResponse::send(device_id, sn, ident, msg, expires).await?;
+        let deadline = Instant::now() + expires;
+        let mut info: Option<ConfigDownloadInfo> = None;
+        while let Ok(Some(Some(bytes))) = time::timeout_at(deadline, rx.recv()).await {
+            if let Ok(Body::Response(manscdp::Response::ConfigDownload(res))) =
+                Manscdp::parse(&bytes)
+                    .hand_log(|msg| error!("{msg}"))
+                    .map(|msg| msg.body)
+            {
+                let part = ConfigDownloadInfo::from(res);
+                let merged = match info.take() {
+                    None => part,
+                    Some(mut info) => {
+                        info.merge(part);
+                        info
+                    }
+                };
+                let complete = config_types.iter().all(|tp| merged.contains(tp));
+                info = Some(merged);
+                if complete {
+                    break;
+                }
+            }
+        }
+        CmdResponse::unlisten(device_id, sn);
+        info.ok_or_else(|| {
+            GlobalError::new_biz_error(1000, "设备配置查询未响应或超时", |msg| {
+                error!("{msg}")
+            })
+        })
     }
     pub async fn lazy_query_device_info(device_id: &String) -> GlobalResult<()> {
         let (ident, msg) = RequestBuilder::query_device_info(device_id).await?;
@@ -54,14 +352,184 @@ impl CmdQuery {
             Container::build_actor(ident, msg, None),
         )
     }
-    pub async fn lazy_subscribe_device_catalog(device_id: &String) -> GlobalResult<()> {
-        let (ident, msg) = RequestBuilder::subscribe_device_catalog(device_id).await?;
-        let when = Instant::now() + Duration::from_secs(2);
-        EventSession::listen_event(
-            &ident.clone(),
-            when,
-            Container::build_actor(ident, msg, None),
+}
+
+pub struct CmdSubscribe;
+
+impl CmdSubscribe {
+    //注册应答后延迟发起订阅
+    pub fn lazy_subscribe_catalog(device_id: &String) {
+        let device_id = device_id.clone();
+        common::tokio::spawn(async move {
+            common::tokio::time::sleep(Duration::from_secs(2)).await;
+            let _ = Self::subscribe_catalog(&device_id)
+                .await
+                .hand_log(|msg| warn!("设备 = [{}],订阅目录失败;err={}", &device_id, msg));
+        });
+    }
+
+    pub async fn subscribe_catalog(device_id: &String) -> GlobalResult<()> {
+        let body = RequestBuilder::catalog_subscribe_body(device_id);
+        Self::subscribe(device_id, SUBSCRIBE_CATALOG, "Catalog", body, None).await
+    }
+
+    pub async fn subscribe_alarm(model: &AlarmSubscribeModel) -> GlobalResult<()> {
+        let body = RequestBuilder::alarm_subscribe_body(model);
+        Self::subscribe(
+            model.get_device_id(),
+            SUBSCRIBE_ALARM,
+            "presence",
+            body,
+            *model.get_expires(),
         )
+        .await
+    }
+
+    pub async fn subscribe_mobile_position(
+        device_id: &String,
+        interval: u32,
+        expires: Option<u32>,
+    ) -> GlobalResult<()> {
+        let body = RequestBuilder::mobile_position_subscribe_body(device_id, interval);
+        Self::subscribe(
+            device_id,
+            SUBSCRIBE_MOBILE_POSITION,
+            "presence",
+            body,
+            expires,
+        )
+        .await
+    }
+
+    //同类型的订阅重复发起时,先取消原订阅
+    async fn subscribe(
+        device_id: &String,
+        kind: &str,
+        event: &str,
+        body: String,
+        expires: Option<u32>,
+    ) -> GlobalResult<()> {
+        Self::unsubscribe(device_id, kind).await;
+        let dialog = {
+            let mut rng = rng();
+            SubscribeDialog {
+                kind: kind.to_string(),
+                event: event.to_string(),
+                event_id: rng.random_range(123456789u32..987654321u32),
+                call_id: uuid::Uuid::new_v4().as_simple().to_string(),
+                from_tag: rng.random_range(123456789u32..987654321u32).to_string(),
+                to_tag: None,
+                seq: rng.random_range(12u32..255u32),
+                expires: expires.unwrap_or_else(|| {
+                    SessionConf::get_session_by_conf()
+                        .get_subscribe_expires()
+                        .unwrap_or(SUBSCRIBE_EXPIRES)
+                }),
+                body,
+            }
+        };
+        Self::send_subscribe(device_id, dialog).await
+    }
+
+    //取消订阅：Expires为0,不等待应答
+    pub async fn unsubscribe(device_id: &String, kind: &str) -> bool {
+        let key = format!("{KEY_SUBSCRIBE}{kind}:{device_id}");
+        match Self::get_dialog(&key) {
+            Some(mut dialog) => {
+                Cache::state_remove(&key);
+                dialog.seq += 1;
+                dialog.expires = 0;
+                if let Ok((ident, msg)) = RequestBuilder::subscribe(device_id, &dialog).await {
+                    let _ = RequestOutput::new(ident, msg, None).do_send();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    //设备注销时取消全部订阅
+    pub async fn unsubscribe_all(device_id: &String) {
+        for kind in SUBSCRIBE_KINDS {
+            Self::unsubscribe(device_id, kind).await;
+        }
+    }
+
+    fn get_dialog(key: &str) -> Option<SubscribeDialog> {
+        Cache::state_get(key).and_then(|(bytes, _)| serde_json::from_slice(&bytes).ok())
+    }
+
+    //发送订阅并记录对话,成功后在有效期的4/5处刷新
+    async fn send_subscribe(device_id: &String, mut dialog: SubscribeDialog) -> GlobalResult<()> {
+        let (ident, msg) = RequestBuilder::subscribe(device_id, &dialog).await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        RequestOutput::new(ident.clone(), msg, Some(tx)).do_send()?;
+        let res = rx.recv().await;
+        EventSession::remove_event(&ident);
+        let key = format!("{KEY_SUBSCRIBE}{}:{device_id}", dialog.kind);
+        match res {
+            Some((Some(res), _)) if res.status_code.code() < 300 => {
+                if dialog.to_tag.is_none() {
+                    dialog.to_tag = ResponseBuilder::get_tag_by_header_to(&res).ok();
+                }
+                //以设备应答的有效期为准
+                if let Some(expires) = res
+                    .expires_header()
+                    .and_then(|expires| expires.seconds().ok())
+                    .filter(|expires| *expires > 0)
+                {
+                    dialog.expires = expires;
+                }
+                Cache::state_insert_obj(key, &dialog, None);
+                Self::schedule_refresh(device_id.clone(), dialog);
+                Ok(())
+            }
+            Some((Some(res), _)) => {
+                Cache::state_remove(&key);
+                Err(GlobalError::new_biz_error(
+                    3000,
+                    &res.status_code.to_string(),
+                    |msg| error!("{msg}"),
+                ))
+            }
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                "设备订阅响应超时",
+                |msg| error!("{msg}"),
+            )),
+        }
+    }
+
+    fn schedule_refresh(device_id: String, dialog: SubscribeDialog) {
+        let delay = Duration::from_secs((dialog.expires - dialog.expires / 5).max(1) as u64);
+        common::tokio::spawn(async move {
+            common::tokio::time::sleep(delay).await;
+            let key = format!("{KEY_SUBSCRIBE}{}:{device_id}", dialog.kind);
+            //已取消或已被新的订阅替换
+            match Self::get_dialog(&key) {
+                Some(current) if current.call_id == dialog.call_id && current.seq == dialog.seq => {
+                }
+                _ => return,
+            }
+            if !RWSession::has_session_by_device_id(&device_id) {
+                Cache::state_remove(&key);
+                return;
+            }
+            let mut refresh = dialog.clone();
+            refresh.seq += 1;
+            if Self::send_subscribe(&device_id, refresh).await.is_err() {
+                //刷新失败则重新建立订阅
+                let _ = Self::subscribe(
+                    &device_id,
+                    &dialog.kind,
+                    &dialog.event,
+                    dialog.body.clone(),
+                    Some(dialog.expires),
+                )
+                .await
+                .hand_log(|msg| warn!("设备 = [{}],重建订阅失败;err={}", &device_id, msg));
+            }
+        });
     }
 }
 
@@ -72,6 +540,56 @@ impl CmdControl {
         let (ident, msg) = RequestBuilder::control_ptz(ptz_control_model).await?;
         RequestOutput::new(ident, msg, None).do_send()
     }
+    //设备以DeviceConfig应答Result,ERROR视为失败
+    pub async fn control_device_config(
+        model: &DeviceConfigModel,
+        expires: Duration,
+    ) -> GlobalResult<()> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) = RequestBuilder::control_device_config(model, sn).await?;
+        let device_id = model.get_device_id();
+        let rx = CmdResponse::send(device_id, sn, ident, msg, expires).await?;
+        CmdResponse::recv_result(rx, device_id, sn, "设备配置", expires).await
+    }
+
+    //无应答的指令仅等待SIP层200
+    pub async fn control_device(
+        device_id: &String,
+        channel_id_opt: Option<&String>,
+        cmd: &DeviceControlCmd,
+        expires: Duration,
+    ) -> GlobalResult<()> {
+        let sn = rng().random_range(100000u32..999999u32);
+        let (ident, msg) =
+            RequestBuilder::control_device(device_id, channel_id_opt, sn, cmd).await?;
+        let rx = CmdResponse::send(device_id, sn, ident, msg, expires).await?;
+        if cmd.has_response() {
+            CmdResponse::recv_result(rx, device_id, sn, cmd.name(), expires).await
+        } else {
+            CmdResponse::unlisten(device_id, sn);
+            Ok(())
+        }
+    }
+
+    pub async fn control_ptz_cmd(
+        device_id: &String,
+        channel_id: &String,
+        cmd: &PtzCmd,
+    ) -> GlobalResult<()> {
+        let (ident, msg) = RequestBuilder::control_ptz_cmd(device_id, channel_id, cmd).await?;
+        RequestOutput::new(ident, msg, None).do_send()
+    }
+
+    //PTZCmd指令码原样转发
+    pub async fn control_ptz_line(
+        device_id: &String,
+        channel_id: &String,
+        cmd_line: &str,
+    ) -> GlobalResult<()> {
+        let (ident, msg) =
+            RequestBuilder::control_ptz_line(device_id, channel_id, cmd_line).await?;
+        RequestOutput::new(ident, msg, None).do_send()
+    }
 
     //device_id: &String, channel_id: &String, num: u8, interval: u8, uri: &String, session_id: u32
     // pub async fn snapshot_image(device_id: &String, _channel_id: &str) -> GlobalResult<()> {
@@ -111,6 +629,35 @@ impl CmdControl {
 
 pub struct CmdNotify;
 
+impl CmdNotify {
+    //下发语音广播通知,设备应答200后将主动发起音频INVITE
+    pub async fn broadcast(
+        device_id: &String,
+        channel_id: &String,
+        source_id: &String,
+    ) -> GlobalResult<()> {
+        let (ident, msg) =
+            RequestBuilder::notify_broadcast(device_id, channel_id, source_id).await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        RequestOutput::new(ident.clone(), msg, Some(tx)).do_send()?;
+        let res = rx.recv().await;
+        EventSession::remove_event(&ident);
+        match res {
+            Some((Some(res), _)) if res.status_code.code() == 200 => Ok(()),
+            Some((Some(res), _)) => Err(GlobalError::new_biz_error(
+                3000,
+                &res.status_code.to_string(),
+                |msg| error!("{msg}"),
+            )),
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                "摄像机响应超时",
+                |msg| error!("{msg}"),
+            )),
+        }
+    }
+}
+
 pub struct CmdStream;
 
 impl CmdStream {
@@ -121,7 +668,7 @@ impl CmdStream {
         stream_mode: StreamMode,
         ssrc: &String,
         range: TimeRange,
-    ) -> GlobalResult<(Response, HashMap<u8, String>, String, String)> {
+    ) -> GlobalResult<(Response, HashMap<u8, String>)> {
         let (ident, msg) = RequestBuilder::playback(
             device_id,
             channel_id,
@@ -140,7 +687,7 @@ impl CmdStream {
         media_address: MediaAddress,
         stream_mode: StreamMode,
         ssrc: &String,
-    ) -> GlobalResult<(Response, HashMap<u8, String>, String, String)> {
+    ) -> GlobalResult<(Response, HashMap<u8, String>)> {
         let (ident, msg) = RequestBuilder::play_live_request(
             device_id,
             channel_id,
@@ -152,21 +699,37 @@ impl CmdStream {
         .hand_log(|msg| warn!("{msg}"))?;
         Self::invite_stream(ident, msg).await
     }
+    pub async fn download_invite(
+        device_id: &String,
+        channel_id: &String,
+        media_address: MediaAddress,
+        stream_mode: StreamMode,
+        ssrc: &String,
+        range: TimeRange,
+        speed: u8,
+    ) -> GlobalResult<(Response, HashMap<u8, String>)> {
+        let (ident, msg) = RequestBuilder::download(
+            device_id,
+            channel_id,
+            media_address,
+            stream_mode,
+            ssrc,
+            range,
+            speed,
+        )
+        .await
+        .hand_log(|msg| warn!("{msg}"))?;
+        Self::invite_stream(ident, msg).await
+    }
 
-    pub fn invite_ack(device_id: &String, response: &Response) -> GlobalResult<(String, u32)> {
-        let ack_request = RequestBuilder::build_ack_request_by_response(response)?;
-        let call_id = ack_request
-            .call_id_header()
-            .hand_log(|msg| warn!("{msg}"))?
-            .value()
-            .to_string();
-        let seq = ack_request
-            .cseq_header()
-            .hand_log(|msg| warn!("{msg}"))?
-            .seq()
-            .hand_log(|msg| warn!("{msg}"))?;
+    //2xx建立对话并回复ACK：Request-URI取应答Contact,携带路由集
+    pub fn invite_ack(device_id: &String, response: &Response) -> GlobalResult<String> {
+        let dialog = Dialog::uac(device_id, response)?;
+        let ack_request = RequestBuilder::build_ack_request_by_response(response, &dialog)?;
+        let call_id = dialog.get_call_id().to_string();
+        Dialog::track(dialog);
         RequestOutput::do_send_off(device_id, ack_request).hand_log(|msg| warn!("{msg}"))?;
-        Ok((call_id, seq))
+        Ok(call_id)
     }
     pub async fn play_speed(
         device_id: &String,
@@ -235,18 +798,74 @@ impl CmdStream {
         ))
     }
 
-    pub async fn play_bye(
-        seq: u32,
-        call_id: String,
+    //回放暂停MANSRTSP消息体
+    pub fn pause_body() -> String {
+        SdpBuilder::info_pause()
+    }
+    //暂停后恢复播放MANSRTSP消息体
+    pub fn replay_body() -> String {
+        SdpBuilder::info_replay()
+    }
+    //回放控制：在点播对话内下发MANSRTSP INFO,等待设备200
+    pub async fn play_info(
         device_id: &String,
         channel_id: &String,
+        body: &str,
         from_tag: &str,
         to_tag: &str,
+        seq: u32,
+        call_id: String,
     ) -> GlobalResult<()> {
-        let (ident, msg) = RequestBuilder::build_bye_request(
-            seq, call_id, device_id, channel_id, from_tag, to_tag,
+        let (ident, msg) =
+            RequestBuilder::info(device_id, channel_id, body, from_tag, to_tag, seq, call_id)
+                .await?;
+        let (tx, mut rx) = mpsc::channel(10);
+        RequestOutput::new(ident.clone(), msg, Some(tx))
+            .do_send()
+            .hand_log(|msg| error!("未响应：{msg}"))?;
+        let res = time::timeout(Duration::from_secs(EXPIRES), rx.recv())
+            .await
+            .ok()
+            .flatten();
+        EventSession::remove_event(&ident);
+        let method = body.split_whitespace().next().unwrap_or_default();
+        match res {
+            Some((Some(res), _)) if res.status_code.code() == 200 => Ok(()),
+            Some((Some(res), _)) => Err(GlobalError::new_biz_error(
+                3000,
+                &format!(
+                    "{method}: channel_id = {channel_id},res = {}",
+                    res.status_code
+                ),
+                |msg| error!("{msg}"),
+            )),
+            _ => Err(GlobalError::new_biz_error(
+                1000,
+                &format!("{method}未响应或超时"),
+                |msg| error!("{msg}"),
+            )),
+        }
+    }
+    //对话内BYE：发出后即终止对话,不依赖设备应答
+    pub async fn play_bye(
+        call_id: String,
+        device_id: &String,
+        channel_id: &String,
+    ) -> GlobalResult<()> {
+        let (seq, from_tag, to_tag) = Dialog::next_request(&call_id).ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "对话不存在或已结束", |msg| error!("{msg}"))
+        })?;
+        let bye = RequestBuilder::build_bye_request(
+            seq,
+            call_id.clone(),
+            device_id,
+            channel_id,
+            &from_tag,
+            &to_tag,
         )
-        .await?;
+        .await;
+        Dialog::terminate(&call_id);
+        let (ident, msg) = bye?;
         let (tx, mut rx) = mpsc::channel(10);
 
         RequestOutput::new(ident.clone(), msg, Some(tx))
@@ -273,51 +892,132 @@ impl CmdStream {
     async fn invite_stream(
         ident: Ident,
         msg: SipMessage,
-    ) -> GlobalResult<(Response, HashMap<u8, String>, String, String)> {
+    ) -> GlobalResult<(Response, HashMap<u8, String>)> {
+        let mut guard = InviteGuard {
+            ident: ident.clone(),
+            invite: match &msg {
+                SipMessage::Request(req) => Some(req.clone()),
+                SipMessage::Response(_) => None,
+            },
+            proceeding: false,
+            done: false,
+        };
         let (tx, mut rx) = mpsc::channel(10);
         RequestOutput::new(ident.clone(), msg, Some(tx)).do_send()?;
         let re = Regex::new(r"\s+").unwrap();
-        while let Some((Some(res), _)) = rx.recv().await {
-            let code = res.status_code.code();
-            let code_msg = res.status_code.to_string();
-            debug!("{ident:?} : {code} => {code_msg}");
-            if code >= 300 {
-                EventSession::remove_event(&ident);
-                return Err(GlobalError::new_biz_error(3000, &code_msg, |msg| {
-                    error!("{msg}")
-                }));
-            }
-            if code == 200 {
-                let session = sdp_types::Session::parse(res.body()).unwrap();
-                debug!("{ident:?} :{:?}", &session);
-                let mut media_map = HashMap::new();
-                for media in session.medias {
-                    for attr in media.attributes {
-                        if attr.attribute.eq("rtpmap") {
-                            if let Some(info) = attr.value {
-                                if let Some((key, val)) =
-                                    re.replace_all(info.trim(), " ").split_once(" ")
-                                {
-                                    let tp = key.parse::<u8>().hand_log(|msg| error!("{msg}"))?;
-                                    let i = val.find('/').unwrap_or(val.len());
-                                    media_map.insert(tp, val[0..i].to_uppercase());
+        //Timer B内无最终应答视为超时,guard释放时CANCEL
+        let answer = time::timeout(TIMER_B, async {
+            while let Some((Some(res), _)) = rx.recv().await {
+                let code = res.status_code.code();
+                let code_msg = res.status_code.to_string();
+                debug!("{ident:?} : {code} => {code_msg}");
+                if code < 200 {
+                    guard.proceeding = true;
+                    //带To tag的临时应答建立early对话
+                    if let Ok(dialog) = Dialog::uac(ident.get_device_id(), &res) {
+                        Dialog::track(dialog);
+                    }
+                    continue;
+                }
+                if code >= 300 {
+                    guard.done = true;
+                    Dialog::terminate(ident.get_call_id());
+                    return Err(GlobalError::new_biz_error(3000, &code_msg, |msg| {
+                        error!("{msg}")
+                    }));
+                }
+                if code == 200 {
+                    guard.done = true;
+                    let session = sdp_types::Session::parse(res.body()).map_err(|err| {
+                        GlobalError::new_biz_error(
+                            1100,
+                            &format!("应答SDP解析失败:{err}"),
+                            |msg| error!("{msg}"),
+                        )
+                    })?;
+                    debug!("{ident:?} :{:?}", &session);
+                    let mut media_map = HashMap::new();
+                    for media in session.medias {
+                        for attr in media.attributes {
+                            if attr.attribute.eq("rtpmap") {
+                                if let Some(info) = attr.value {
+                                    if let Some((key, val)) =
+                                        re.replace_all(info.trim(), " ").split_once(" ")
+                                    {
+                                        let tp =
+                                            key.parse::<u8>().hand_log(|msg| error!("{msg}"))?;
+                                        let i = val.find('/').unwrap_or(val.len());
+                                        media_map.insert(tp, val[0..i].to_uppercase());
+                                    }
                                 }
                             }
                         }
                     }
+                    return Ok(Some((res, media_map)));
                 }
-                let from_tag = ResponseBuilder::get_tag_by_header_from(&res)?;
-                let to_tag = ResponseBuilder::get_tag_by_header_to(&res)?;
-                EventSession::remove_event(&ident);
-                return Ok((res, media_map, from_tag, to_tag));
+            }
+            Ok(None)
+        })
+        .await
+        .unwrap_or(Ok(None))?;
+        answer.ok_or_else(|| {
+            GlobalError::new_biz_error(1000, "摄像机响应超时", |msg| error!("{msg}"))
+        })
+    }
+
+    //无等待者的INVITE 2xx
+    pub fn is_stray_invite_ok(res: &Response) -> bool {
+        res.status_code.code() / 100 == 2
+            && res
+                .cseq_header()
+                .is_ok_and(|cseq| cseq.method().is_ok_and(|method| method == Method::Invite))
+            && res
+                .call_id_header()
+                .is_ok_and(|call_id| !EventSession::contains_call_id(call_id.value()))
+    }
+
+    //已建立对话的2xx重传：补发ACK；已取消或超时后迟到的2xx：回复ACK后立即BYE
+    pub async fn stray_invite_ok(device_id: &String, res: &Response) -> GlobalResult<()> {
+        let dialog = Dialog::uac(device_id, res)?;
+        let ack_request = RequestBuilder::build_ack_request_by_response(res, &dialog)?;
+        RequestOutput::do_send_off(device_id, ack_request)?;
+        let call_id = dialog.get_call_id().to_string();
+        if Dialog::contains(&call_id) {
+            return Ok(());
+        }
+        warn!("INVITE迟到应答,挂断: device_id = {device_id},call_id = {call_id}");
+        let channel_id = parser::header::get_device_id_by_response(res)?;
+        Dialog::track(dialog);
+        Self::play_bye(call_id, device_id, &channel_id).await
+    }
+}
+
+//INVITE等待守卫：最终应答前等待方放弃(future被drop)或超时,清理事件与early对话；已收到临时应答则发送CANCEL
+struct InviteGuard {
+    ident: Ident,
+    invite: Option<rsip::Request>,
+    proceeding: bool,
+    done: bool,
+}
+
+impl Drop for InviteGuard {
+    fn drop(&mut self) {
+        EventSession::remove_event(&self.ident);
+        if self.done {
+            return;
+        }
+        Dialog::terminate(self.ident.get_call_id());
+        //未收到临时应答时不可CANCEL,迟到的2xx由stray_invite_ok挂断
+        if !self.proceeding {
+            return;
+        }
+        if let Some(invite) = &self.invite {
+            if let Ok(cancel) = RequestBuilder::build_cancel_request(invite) {
+                warn!("{:?},取消INVITE", &self.ident);
+                let _ = RequestOutput::do_send_off(self.ident.get_device_id(), cancel)
+                    .hand_log(|msg| warn!("{msg}"));
             }
         }
-        EventSession::remove_event(&ident);
-        Err(GlobalError::new_biz_error(
-            1000,
-            "摄像机响应超时",
-            |msg| error!("{msg}"),
-        ))
     }
 }
 
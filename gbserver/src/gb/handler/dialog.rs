@@ -0,0 +1,283 @@
+use common::constructor::Get;
+use common::dashmap::DashMap;
+use common::exception::{GlobalError, GlobalResult, TransError};
+use common::log::warn;
+use common::once_cell::sync::Lazy;
+use rsip::headers::ToTypedHeader;
+use rsip::message::HeadersExt;
+use rsip::prelude::UntypedHeader;
+use rsip::{Header, Request, Response, Uri};
+
+//INVITE对话(RFC 3261 §12)：call_id -> 对话
+static DIALOGS: Lazy<DashMap<String, Dialog>> = Lazy::new(DashMap::new);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogState {
+    //收到带To tag的临时应答
+    Early,
+    //收到2xx(主叫)或ACK(被叫)
+    Confirmed,
+    //BYE后移除
+    Terminated,
+}
+
+#[derive(Debug, Clone, Get)]
+pub struct Dialog {
+    device_id: String,
+    call_id: String,
+    local_tag: String,
+    remote_tag: String,
+    local_seq: u32,
+    remote_seq: Option<u32>,
+    //远端目标：对端Contact,对话内请求的Request-URI
+    remote_target: Option<Uri>,
+    //路由集：Record-Route,已按本端发送顺序排列
+    route_set: Vec<String>,
+    state: DialogState,
+}
+
+impl Dialog {
+    //主叫：由INVITE的应答建立,1xx为early,2xx为confirmed
+    pub fn uac(device_id: &str, res: &Response) -> GlobalResult<Dialog> {
+        let remote_tag = res
+            .to_header()
+            .hand_log(|msg| warn!("{msg}"))?
+            .tag()
+            .hand_log(|msg| warn!("{msg}"))?
+            .ok_or_else(|| {
+                GlobalError::new_sys_error("应答缺少To tag", |msg| warn!("{msg}"))
+            })?;
+        let local_tag = res
+            .from_header()
+            .hand_log(|msg| warn!("{msg}"))?
+            .tag()
+            .hand_log(|msg| warn!("{msg}"))?
+            .map(|tag| tag.to_string())
+            .unwrap_or_default();
+        let mut route_set = record_route(&res.headers);
+        route_set.reverse();
+        Ok(Dialog {
+            device_id: device_id.to_string(),
+            call_id: res
+                .call_id_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .value()
+                .to_string(),
+            local_tag,
+            remote_tag: remote_tag.to_string(),
+            local_seq: res
+                .cseq_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .seq()
+                .hand_log(|msg| warn!("{msg}"))?,
+            remote_seq: None,
+            remote_target: res
+                .contact_header()
+                .ok()
+                .and_then(|contact| contact.typed().ok())
+                .map(|contact| contact.uri),
+            route_set,
+            state: if res.status_code.code() < 200 {
+                DialogState::Early
+            } else {
+                DialogState::Confirmed
+            },
+        })
+    }
+
+    //被叫：由设备发起的INVITE及本端2xx应答建立,收到ACK后确认
+    pub fn uas(device_id: &str, req: &Request, local_tag: &str) -> GlobalResult<Dialog> {
+        let remote_tag = req
+            .from_header()
+            .hand_log(|msg| warn!("{msg}"))?
+            .tag()
+            .hand_log(|msg| warn!("{msg}"))?
+            .map(|tag| tag.to_string())
+            .unwrap_or_default();
+        Ok(Dialog {
+            device_id: device_id.to_string(),
+            call_id: req
+                .call_id_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .value()
+                .to_string(),
+            local_tag: local_tag.to_string(),
+            remote_tag,
+            local_seq: 1,
+            remote_seq: Some(
+                req.cseq_header()
+                    .hand_log(|msg| warn!("{msg}"))?
+                    .seq()
+                    .hand_log(|msg| warn!("{msg}"))?,
+            ),
+            remote_target: req
+                .contact_header()
+                .ok()
+                .and_then(|contact| contact.typed().ok())
+                .map(|contact| contact.uri),
+            route_set: record_route(&req.headers),
+            state: DialogState::Early,
+        })
+    }
+
+    //登记对话：early对话收到2xx后被confirmed对话替换
+    pub fn track(dialog: Dialog) {
+        DIALOGS.insert(dialog.call_id.clone(), dialog);
+    }
+
+    pub fn contains(call_id: &str) -> bool {
+        DIALOGS.contains_key(call_id)
+    }
+
+    //被叫收到ACK
+    pub fn confirm(call_id: &str) {
+        if let Some(mut dialog) = DIALOGS.get_mut(call_id) {
+            dialog.state = DialogState::Confirmed;
+        }
+    }
+
+    //对话内下一个请求：seq,from_tag,to_tag
+    pub fn next_request(call_id: &str) -> Option<(u32, String, String)> {
+        DIALOGS.get_mut(call_id).map(|mut dialog| {
+            dialog.local_seq += 1;
+            (
+                dialog.local_seq,
+                dialog.local_tag.clone(),
+                dialog.remote_tag.clone(),
+            )
+        })
+    }
+
+    //对话内请求：Request-URI取远端目标,并携带路由集(按松散路由处理)
+    pub fn route(call_id: &str, uri: &mut Uri, headers: &mut rsip::Headers) {
+        if let Some(dialog) = DIALOGS.get(call_id) {
+            dialog.apply_route(uri, headers);
+        }
+    }
+
+    pub fn apply_route(&self, uri: &mut Uri, headers: &mut rsip::Headers) {
+        if let Some(target) = &self.remote_target {
+            *uri = target.clone();
+        }
+        for route in &self.route_set {
+            headers.push(rsip::headers::Route::new(route.clone()).into());
+        }
+    }
+
+    //校验设备发起的对话内请求：不匹配返回481,CSeq回退返回500
+    pub fn receive(req: &Request) -> Result<Dialog, u16> {
+        let call_id = req
+            .call_id_header()
+            .map_err(|_| 400u16)?
+            .value()
+            .to_string();
+        let from_tag = req
+            .from_header()
+            .ok()
+            .and_then(|from| from.tag().ok().flatten())
+            .map(|tag| tag.to_string())
+            .unwrap_or_default();
+        let to_tag = req
+            .to_header()
+            .ok()
+            .and_then(|to| to.tag().ok().flatten())
+            .map(|tag| tag.to_string())
+            .unwrap_or_default();
+        let seq = req
+            .cseq_header()
+            .ok()
+            .and_then(|cseq| cseq.seq().ok())
+            .ok_or(400u16)?;
+        let mut dialog = DIALOGS.get_mut(&call_id).ok_or(481u16)?;
+        if dialog.remote_tag != from_tag || dialog.local_tag != to_tag {
+            return Err(481);
+        }
+        if dialog.remote_seq.is_some_and(|remote_seq| seq < remote_seq) {
+            return Err(500);
+        }
+        dialog.remote_seq = Some(seq);
+        Ok(dialog.clone())
+    }
+
+    pub fn terminate(call_id: &str) -> Option<Dialog> {
+        DIALOGS.remove(call_id).map(|(_, mut dialog)| {
+            dialog.state = DialogState::Terminated;
+            dialog
+        })
+    }
+}
+
+fn record_route(headers: &rsip::Headers) -> Vec<String> {
+    headers
+        .iter()
+        .filter_map(|header| match header {
+            Header::RecordRoute(record_route) => Some(record_route.value().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok_response() -> Response {
+        let raw = "SIP/2.0 200 OK\r\n\
+                   Via: SIP/2.0/UDP 10.0.0.2:5060;rport;branch=z9hG4bKdialog\r\n\
+                   Record-Route: <sip:10.0.0.3:5060;lr>\r\n\
+                   Record-Route: <sip:10.0.0.4:5060;lr>\r\n\
+                   From: <sip:34020000002000000001@3402000000>;tag=local\r\n\
+                   To: <sip:34020000001320000001@3402000000>;tag=remote\r\n\
+                   Call-ID: dialog@10.0.0.2\r\n\
+                   CSeq: 5 INVITE\r\n\
+                   Contact: <sip:34020000001320000001@10.0.0.1:5070>\r\n\
+                   Content-Length: 0\r\n\r\n";
+        Response::try_from(raw.as_bytes()).unwrap()
+    }
+
+    fn bye(from_tag: &str, seq: u32) -> Request {
+        let raw = format!(
+            "BYE sip:34020000002000000001@10.0.0.2:5060 SIP/2.0\r\n\
+             Via: SIP/2.0/UDP 10.0.0.1:5070;rport;branch=z9hG4bKbye{seq}\r\n\
+             From: <sip:34020000001320000001@3402000000>;tag={from_tag}\r\n\
+             To: <sip:34020000002000000001@3402000000>;tag=local\r\n\
+             Call-ID: dialog@10.0.0.2\r\n\
+             CSeq: {seq} BYE\r\n\
+             Content-Length: 0\r\n\r\n"
+        );
+        Request::try_from(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_uac_dialog() {
+        let dialog = Dialog::uac("34020000001320000001", &ok_response()).unwrap();
+        assert_eq!(dialog.state, DialogState::Confirmed);
+        assert_eq!(dialog.local_tag, "local");
+        assert_eq!(dialog.remote_tag, "remote");
+        assert_eq!(
+            dialog.remote_target.as_ref().unwrap().to_string(),
+            "sip:34020000001320000001@10.0.0.1:5070"
+        );
+        assert_eq!(
+            dialog.route_set,
+            vec!["<sip:10.0.0.4:5060;lr>", "<sip:10.0.0.3:5060;lr>"]
+        );
+        let mut uri = Uri::try_from("sip:34020000001320000001@3402000000").unwrap();
+        let mut headers = rsip::Headers::default();
+        dialog.apply_route(&mut uri, &mut headers);
+        assert_eq!(uri.to_string(), "sip:34020000001320000001@10.0.0.1:5070");
+        assert_eq!(headers.iter().count(), 2);
+
+        Dialog::track(dialog);
+        assert_eq!(
+            Dialog::next_request("dialog@10.0.0.2"),
+            Some((6, "local".to_string(), "remote".to_string()))
+        );
+        assert_eq!(Dialog::receive(&bye("other", 1)).unwrap_err(), 481);
+        assert!(Dialog::receive(&bye("remote", 2)).is_ok());
+        assert_eq!(Dialog::receive(&bye("remote", 1)).unwrap_err(), 500);
+        let dialog = Dialog::terminate("dialog@10.0.0.2").unwrap();
+        assert_eq!(dialog.state, DialogState::Terminated);
+        assert_eq!(Dialog::receive(&bye("remote", 3)).unwrap_err(), 481);
+    }
+}
@@ -84,9 +84,18 @@ pub mod event {
                 }
                 Entry::Vacant(en) => {
                     en.insert(ident.device_id.clone());
+                    //新事件早于当前最近到期时间时,唤醒清理任务重新计时
+                    let notify = state
+                        .expirations
+                        .first()
+                        .map(|(first, _)| *first > when)
+                        .unwrap_or(true);
                     state.expirations.insert((when, ident.clone()));
                     state.ident_map.insert(ident.clone(), (when, container));
-
+                    drop(guard);
+                    if notify {
+                        EVENT_SESSION.shared.background_task.notify_one();
+                    }
                     Ok(())
                 }
             }
@@ -103,6 +112,16 @@ pub mod event {
             });
         }
 
+        //是否有等待该会话应答的事件
+        pub fn contains_call_id(call_id: &str) -> bool {
+            EVENT_SESSION
+                .shared
+                .state
+                .lock()
+                .device_session
+                .contains_key(call_id)
+        }
+
         pub async fn handle_response(
             to_device_id: String,
             call_id: String,
@@ -0,0 +1,1050 @@
+//! GB/T 28181 MANSCDP(xml)消息的类型化模型
+//!
+//! 消息体按根元素(Query/Control/Response/Notify)与CmdType反序列化为具体结构,
+//! 列表统一为`ItemList<T>`,重复标签与嵌套列表均按结构解析。
+use common::log::debug;
+use common::serde::{Deserialize, Deserializer};
+use encoding_rs::{GB18030, UTF_8};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManscdpError {
+    ///字符集解码失败
+    Decode(String),
+    ///xml格式错误或字段类型不匹配
+    Xml(String),
+    ///根元素不是Query/Control/Response/Notify
+    UnknownRoot(String),
+    MissingCmdType {
+        root: String,
+    },
+    UnknownCmdType {
+        root: String,
+        cmd_type: String,
+    },
+}
+
+impl Display for ManscdpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManscdpError::Decode(msg) => write!(f, "MANSCDP解码失败: {msg}"),
+            ManscdpError::Xml(msg) => write!(f, "MANSCDP解析失败: {msg}"),
+            ManscdpError::UnknownRoot(root) => write!(f, "MANSCDP未知消息类型: {root}"),
+            ManscdpError::MissingCmdType { root } => write!(f, "MANSCDP缺少CmdType: {root}"),
+            ManscdpError::UnknownCmdType { root, cmd_type } => {
+                write!(f, "MANSCDP不支持的CmdType: {root}/{cmd_type}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManscdpError {}
+
+impl ManscdpError {
+    //对应的SIP最终应答码：未知CmdType为501,其余按请求格式错误400
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ManscdpError::UnknownCmdType { .. } => 501,
+            _ => 400,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Root {
+    Query,
+    Control,
+    Response,
+    Notify,
+}
+
+impl Root {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Root::Query => "Query",
+            Root::Control => "Control",
+            Root::Response => "Response",
+            Root::Notify => "Notify",
+        }
+    }
+}
+
+impl FromStr for Root {
+    type Err = ManscdpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Query" => Ok(Root::Query),
+            "Control" => Ok(Root::Control),
+            "Response" => Ok(Root::Response),
+            "Notify" => Ok(Root::Notify),
+            other => Err(ManscdpError::UnknownRoot(other.to_string())),
+        }
+    }
+}
+
+//各类消息共有的头部字段
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct Head {
+    #[serde(rename = "CmdType", default)]
+    pub cmd_type: String,
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Manscdp {
+    pub root: Root,
+    pub head: Head,
+    pub body: Body,
+}
+
+#[derive(Debug, Clone)]
+pub enum Body {
+    Query(Query),
+    Control(Control),
+    Response(Response),
+    Notify(Notify),
+}
+
+#[derive(Debug, Clone)]
+pub enum Query {
+    Catalog(Head),
+    DeviceInfo(Head),
+    DeviceStatus(Head),
+    RecordInfo(RecordInfoQuery),
+    ConfigDownload(ConfigDownloadQuery),
+    PresetQuery(Head),
+    Alarm(AlarmQuery),
+    MobilePosition(MobilePositionQuery),
+}
+
+#[derive(Debug, Clone)]
+pub enum Control {
+    DeviceControl(DeviceControl),
+    DeviceConfig(DeviceConfig),
+}
+
+#[derive(Debug, Clone)]
+pub enum Response {
+    Catalog(CatalogList),
+    DeviceInfo(DeviceInfoResponse),
+    DeviceStatus(DeviceStatusResponse),
+    RecordInfo(RecordInfoResponse),
+    ConfigDownload(ConfigDownloadResponse),
+    PresetQuery(PresetQueryResponse),
+    DeviceControl(ResultResponse),
+    DeviceConfig(ResultResponse),
+    Alarm(ResultResponse),
+    Broadcast(ResultResponse),
+}
+
+#[derive(Debug, Clone)]
+pub enum Notify {
+    Keepalive(KeepaliveNotify),
+    Alarm(AlarmNotify),
+    Catalog(CatalogList),
+    MediaStatus(MediaStatusNotify),
+    MobilePosition(MobilePositionNotify),
+    Broadcast(BroadcastNotify),
+}
+
+impl Manscdp {
+    //按根元素 + CmdType解析消息体
+    pub fn parse(body: &[u8]) -> Result<Self, ManscdpError> {
+        let xml = decode(body)?;
+        let (root, head) = head(&xml)?;
+        let cmd_type = head.cmd_type.trim();
+        if cmd_type.is_empty() {
+            return Err(ManscdpError::MissingCmdType {
+                root: root.as_str().to_string(),
+            });
+        }
+        let body = match root {
+            Root::Query => Body::Query(match cmd_type {
+                "Catalog" => Query::Catalog(head.clone()),
+                "DeviceInfo" => Query::DeviceInfo(head.clone()),
+                "DeviceStatus" => Query::DeviceStatus(head.clone()),
+                "RecordInfo" => Query::RecordInfo(from_xml(&xml)?),
+                "ConfigDownload" => Query::ConfigDownload(from_xml(&xml)?),
+                "PresetQuery" => Query::PresetQuery(head.clone()),
+                "Alarm" => Query::Alarm(from_xml(&xml)?),
+                "MobilePosition" => Query::MobilePosition(from_xml(&xml)?),
+                _ => return Err(unknown(root, cmd_type)),
+            }),
+            Root::Control => Body::Control(match cmd_type {
+                "DeviceControl" => Control::DeviceControl(from_xml(&xml)?),
+                "DeviceConfig" => Control::DeviceConfig(from_xml(&xml)?),
+                _ => return Err(unknown(root, cmd_type)),
+            }),
+            Root::Response => Body::Response(match cmd_type {
+                "Catalog" => Response::Catalog(from_xml(&xml)?),
+                "DeviceInfo" => Response::DeviceInfo(from_xml(&xml)?),
+                "DeviceStatus" => Response::DeviceStatus(from_xml(&xml)?),
+                "RecordInfo" => Response::RecordInfo(from_xml(&xml)?),
+                "ConfigDownload" => Response::ConfigDownload(from_xml(&xml)?),
+                "PresetQuery" => Response::PresetQuery(from_xml(&xml)?),
+                "DeviceControl" => Response::DeviceControl(from_xml(&xml)?),
+                "DeviceConfig" => Response::DeviceConfig(from_xml(&xml)?),
+                "Alarm" => Response::Alarm(from_xml(&xml)?),
+                "Broadcast" => Response::Broadcast(from_xml(&xml)?),
+                _ => return Err(unknown(root, cmd_type)),
+            }),
+            Root::Notify => Body::Notify(match cmd_type {
+                "Keepalive" => Notify::Keepalive(from_xml(&xml)?),
+                "Alarm" => Notify::Alarm(from_xml(&xml)?),
+                "Catalog" => Notify::Catalog(from_xml(&xml)?),
+                "MediaStatus" => Notify::MediaStatus(from_xml(&xml)?),
+                "MobilePosition" => Notify::MobilePosition(from_xml(&xml)?),
+                "Broadcast" => Notify::Broadcast(from_xml(&xml)?),
+                _ => return Err(unknown(root, cmd_type)),
+            }),
+        };
+        debug!("{:?}", &body);
+        Ok(Manscdp { root, head, body })
+    }
+}
+
+//仅解析根元素与头部字段,用于消息体无法完整解析时的错误应答
+pub fn parse_head(body: &[u8]) -> Result<(Root, Head), ManscdpError> {
+    head(&decode(body)?)
+}
+
+fn head(xml: &str) -> Result<(Root, Head), ManscdpError> {
+    let root: Root = root_name(xml)?.parse()?;
+    Ok((root, from_xml(xml)?))
+}
+
+fn unknown(root: Root, cmd_type: &str) -> ManscdpError {
+    ManscdpError::UnknownCmdType {
+        root: root.as_str().to_string(),
+        cmd_type: cmd_type.to_string(),
+    }
+}
+
+fn from_xml<'de, T: Deserialize<'de>>(xml: &'de str) -> Result<T, ManscdpError> {
+    quick_xml::de::from_str(xml).map_err(|err| ManscdpError::Xml(err.to_string()))
+}
+
+fn root_name(xml: &str) -> Result<String, ManscdpError> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                return Ok(String::from_utf8_lossy(e.name().as_ref()).to_string())
+            }
+            Ok(Event::Eof) => return Err(ManscdpError::Xml("缺少根元素".to_string())),
+            Err(err) => return Err(ManscdpError::Xml(err.to_string())),
+            _ => {}
+        }
+    }
+}
+
+//按xml声明的encoding解码：GB2312/GBK/GB18030统一按GB18030(超集),
+//未声明时优先UTF-8,失败再按GB18030
+pub fn decode(body: &[u8]) -> Result<String, ManscdpError> {
+    let declared = declared_encoding(body).map(|enc| enc.to_ascii_uppercase());
+    let gb = matches!(
+        declared.as_deref(),
+        Some("GB2312") | Some("GBK") | Some("GB18030")
+    );
+    if !gb {
+        if let Some(text) = UTF_8.decode_without_bom_handling_and_without_replacement(body) {
+            return Ok(text.into_owned());
+        }
+    }
+    let (text, _, had_errors) = GB18030.decode(body);
+    if had_errors {
+        return Err(ManscdpError::Decode(format!(
+            "encoding = {:?}",
+            declared.unwrap_or_default()
+        )));
+    }
+    Ok(text.into_owned())
+}
+
+fn declared_encoding(body: &[u8]) -> Option<String> {
+    let head = &body[..body.len().min(128)];
+    let head = String::from_utf8_lossy(head);
+    let decl = head.trim_start().strip_prefix("<?xml")?;
+    let decl = &decl[..decl.find("?>")?];
+    let pos = decl.find("encoding")?;
+    let rest = decl[pos + "encoding".len()..]
+        .trim_start()
+        .strip_prefix('=')?;
+    let rest = rest.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    Some(rest[..rest.find(quote)?].trim().to_string())
+}
+
+//设备常以空标签表示缺省,数值解析失败按缺省处理
+fn opt_num<'de, D, T>(de: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    let val: Option<String> = Option::deserialize(de)?;
+    Ok(val.and_then(|v| v.trim().parse().ok()))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct ItemList<T> {
+    #[serde(rename = "@Num", default, deserialize_with = "opt_num")]
+    pub num: Option<u32>,
+    #[serde(rename = "Item", default = "Vec::new")]
+    pub items: Vec<T>,
+}
+
+impl<T> Default for ItemList<T> {
+    fn default() -> Self {
+        Self {
+            num: None,
+            items: Vec::new(),
+        }
+    }
+}
+
+/* ---------------------------------- Query ---------------------------------- */
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct RecordInfoQuery {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "StartTime", default)]
+    pub start_time: String,
+    #[serde(rename = "EndTime", default)]
+    pub end_time: String,
+    #[serde(rename = "FilePath")]
+    pub file_path: Option<String>,
+    #[serde(rename = "Address")]
+    pub address: Option<String>,
+    #[serde(rename = "Secrecy", default, deserialize_with = "opt_num")]
+    pub secrecy: Option<u8>,
+    ///time/alarm/manual/all
+    #[serde(rename = "Type")]
+    pub record_type: Option<String>,
+    #[serde(rename = "RecorderID")]
+    pub recorder_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct ConfigDownloadQuery {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    ///多个类型以"/"分隔
+    #[serde(rename = "ConfigType", default)]
+    pub config_type: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmQuery {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "StartAlarmPriority", default, deserialize_with = "opt_num")]
+    pub start_alarm_priority: Option<u8>,
+    #[serde(rename = "EndAlarmPriority", default, deserialize_with = "opt_num")]
+    pub end_alarm_priority: Option<u8>,
+    #[serde(rename = "AlarmMethod")]
+    pub alarm_method: Option<String>,
+    #[serde(rename = "AlarmType")]
+    pub alarm_type: Option<String>,
+    #[serde(rename = "StartAlarmTime")]
+    pub start_alarm_time: Option<String>,
+    #[serde(rename = "EndAlarmTime")]
+    pub end_alarm_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct MobilePositionQuery {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Interval", default, deserialize_with = "opt_num")]
+    pub interval: Option<u32>,
+}
+
+/* --------------------------------- Control --------------------------------- */
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct DragZoom {
+    #[serde(rename = "Length", default, deserialize_with = "opt_num")]
+    pub length: Option<u32>,
+    #[serde(rename = "Width", default, deserialize_with = "opt_num")]
+    pub width: Option<u32>,
+    #[serde(rename = "MidPointX", default, deserialize_with = "opt_num")]
+    pub mid_point_x: Option<u32>,
+    #[serde(rename = "MidPointY", default, deserialize_with = "opt_num")]
+    pub mid_point_y: Option<u32>,
+    #[serde(rename = "LengthX", default, deserialize_with = "opt_num")]
+    pub length_x: Option<u32>,
+    #[serde(rename = "LengthY", default, deserialize_with = "opt_num")]
+    pub length_y: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct ControlInfo {
+    #[serde(rename = "ControlPriority", default, deserialize_with = "opt_num")]
+    pub control_priority: Option<u8>,
+    #[serde(rename = "AlarmMethod")]
+    pub alarm_method: Option<String>,
+    #[serde(rename = "AlarmType")]
+    pub alarm_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct DeviceControl {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "PTZCmd")]
+    pub ptz_cmd: Option<String>,
+    #[serde(rename = "TeleBoot")]
+    pub tele_boot: Option<String>,
+    ///Record/StopRecord
+    #[serde(rename = "RecordCmd")]
+    pub record_cmd: Option<String>,
+    ///SetGuard/ResetGuard
+    #[serde(rename = "GuardCmd")]
+    pub guard_cmd: Option<String>,
+    ///ResetAlarm
+    #[serde(rename = "AlarmCmd")]
+    pub alarm_cmd: Option<String>,
+    ///Send
+    #[serde(rename = "IFameCmd")]
+    pub ifame_cmd: Option<String>,
+    #[serde(rename = "DragZoomIn")]
+    pub drag_zoom_in: Option<DragZoom>,
+    #[serde(rename = "DragZoomOut")]
+    pub drag_zoom_out: Option<DragZoom>,
+    #[serde(rename = "Info")]
+    pub info: Option<ControlInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct BasicParam {
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+    #[serde(rename = "DeviceID")]
+    pub device_id: Option<String>,
+    #[serde(rename = "SIPServerID")]
+    pub sip_server_id: Option<String>,
+    #[serde(rename = "SIPServerIP")]
+    pub sip_server_ip: Option<String>,
+    #[serde(rename = "SIPServerPort", default, deserialize_with = "opt_num")]
+    pub sip_server_port: Option<u16>,
+    #[serde(rename = "DomainName")]
+    pub domain_name: Option<String>,
+    #[serde(rename = "Expiration", default, deserialize_with = "opt_num")]
+    pub expiration: Option<u32>,
+    #[serde(rename = "Password")]
+    pub password: Option<String>,
+    #[serde(rename = "HeartBeatInterval", default, deserialize_with = "opt_num")]
+    pub heart_beat_interval: Option<u32>,
+    #[serde(rename = "HeartBeatCount", default, deserialize_with = "opt_num")]
+    pub heart_beat_count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmReport {
+    #[serde(rename = "MotionDetection", default, deserialize_with = "opt_num")]
+    pub motion_detection: Option<u8>,
+    #[serde(rename = "FieldDetection", default, deserialize_with = "opt_num")]
+    pub field_detection: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct DeviceConfig {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "BasicParam")]
+    pub basic_param: Option<BasicParam>,
+    #[serde(rename = "AlarmReport")]
+    pub alarm_report: Option<AlarmReport>,
+}
+
+/* --------------------------------- Response -------------------------------- */
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct ResultResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    ///OK/ERROR
+    #[serde(rename = "Result", default)]
+    pub result: String,
+}
+
+impl ResultResponse {
+    pub fn is_ok(&self) -> bool {
+        self.result.trim().eq_ignore_ascii_case("OK")
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct CatalogItemInfo {
+    #[serde(rename = "PTZType", default, deserialize_with = "opt_num")]
+    pub ptz_type: Option<u8>,
+    #[serde(rename = "SupplyLightType", default, deserialize_with = "opt_num")]
+    pub supply_light_type: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct CatalogItem {
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Manufacturer")]
+    pub manufacturer: Option<String>,
+    #[serde(rename = "Model")]
+    pub model: Option<String>,
+    #[serde(rename = "Owner")]
+    pub owner: Option<String>,
+    #[serde(rename = "CivilCode")]
+    pub civil_code: Option<String>,
+    #[serde(rename = "Block")]
+    pub block: Option<String>,
+    #[serde(rename = "Address")]
+    pub address: Option<String>,
+    #[serde(rename = "Parental", default, deserialize_with = "opt_num")]
+    pub parental: Option<u8>,
+    #[serde(rename = "ParentID")]
+    pub parent_id: Option<String>,
+    #[serde(rename = "SafetyWay", default, deserialize_with = "opt_num")]
+    pub safety_way: Option<u8>,
+    #[serde(rename = "RegisterWay", default, deserialize_with = "opt_num")]
+    pub register_way: Option<u8>,
+    #[serde(rename = "Secrecy", default, deserialize_with = "opt_num")]
+    pub secrecy: Option<u8>,
+    #[serde(rename = "IPAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "Port", default, deserialize_with = "opt_num")]
+    pub port: Option<u16>,
+    #[serde(rename = "Password")]
+    pub password: Option<String>,
+    ///ON/OFF
+    #[serde(rename = "Status", default)]
+    pub status: String,
+    #[serde(rename = "Longitude", default, deserialize_with = "opt_num")]
+    pub longitude: Option<f32>,
+    #[serde(rename = "Latitude", default, deserialize_with = "opt_num")]
+    pub latitude: Option<f32>,
+    #[serde(rename = "SupplyLightType", default, deserialize_with = "opt_num")]
+    pub supply_light_type: Option<u8>,
+    #[serde(rename = "Info")]
+    pub info: Option<CatalogItemInfo>,
+    ///目录通知事件：ON/OFF/VLOST/DEFECT/ADD/DEL/UPDATE,查询应答中为空
+    #[serde(rename = "Event")]
+    pub event: Option<String>,
+}
+
+//目录查询应答与目录变更通知结构一致
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct CatalogList {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "SumNum", default, deserialize_with = "opt_num")]
+    pub sum_num: Option<u32>,
+    #[serde(rename = "DeviceList", default)]
+    pub device_list: ItemList<CatalogItem>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct DeviceInfoResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "DeviceName")]
+    pub device_name: Option<String>,
+    #[serde(rename = "Result")]
+    pub result: Option<String>,
+    #[serde(rename = "Manufacturer", default)]
+    pub manufacturer: String,
+    #[serde(rename = "Model", default)]
+    pub model: String,
+    #[serde(rename = "Firmware", default)]
+    pub firmware: String,
+    #[serde(rename = "DeviceType")]
+    pub device_type: Option<String>,
+    #[serde(rename = "MaxCamera", default, deserialize_with = "opt_num")]
+    pub max_camera: Option<u8>,
+    #[serde(rename = "MaxAlarm", default, deserialize_with = "opt_num")]
+    pub max_alarm: Option<u8>,
+    #[serde(rename = "Channel", default, deserialize_with = "opt_num")]
+    pub channel: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmStatusItem {
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "DutyStatus", default)]
+    pub duty_status: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct DeviceStatusResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Result")]
+    pub result: Option<String>,
+    ///ONLINE/OFFLINE
+    #[serde(rename = "Online", default)]
+    pub online: String,
+    #[serde(rename = "Status")]
+    pub status: Option<String>,
+    #[serde(rename = "Reason")]
+    pub reason: Option<String>,
+    ///ON/OFF
+    #[serde(rename = "Encode")]
+    pub encode: Option<String>,
+    ///ON/OFF
+    #[serde(rename = "Record")]
+    pub record: Option<String>,
+    #[serde(rename = "DeviceTime")]
+    pub device_time: Option<String>,
+    //标准为Alarmstatus,部分设备为AlarmStatus
+    #[serde(rename = "Alarmstatus", alias = "AlarmStatus", default)]
+    pub alarm_status: ItemList<AlarmStatusItem>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct RecordItem {
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "FilePath")]
+    pub file_path: Option<String>,
+    #[serde(rename = "Address")]
+    pub address: Option<String>,
+    #[serde(rename = "StartTime", default)]
+    pub start_time: String,
+    #[serde(rename = "EndTime", default)]
+    pub end_time: String,
+    #[serde(rename = "Secrecy", default, deserialize_with = "opt_num")]
+    pub secrecy: Option<u8>,
+    #[serde(rename = "Type")]
+    pub record_type: Option<String>,
+    #[serde(rename = "RecorderID")]
+    pub recorder_id: Option<String>,
+    #[serde(rename = "FileSize", default, deserialize_with = "opt_num")]
+    pub file_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct RecordInfoResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+    #[serde(rename = "SumNum", default, deserialize_with = "opt_num")]
+    pub sum_num: Option<u32>,
+    #[serde(rename = "RecordList", default)]
+    pub record_list: ItemList<RecordItem>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct VideoParamOpt {
+    #[serde(rename = "DownloadSpeed")]
+    pub download_speed: Option<String>,
+    #[serde(rename = "Resolution")]
+    pub resolution: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct RoiParam {
+    #[serde(rename = "ROIFlag", default, deserialize_with = "opt_num")]
+    pub roi_flag: Option<u8>,
+    #[serde(rename = "ROINumber", default, deserialize_with = "opt_num")]
+    pub roi_number: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct SvcParam {
+    #[serde(rename = "SVCSpaceDomainMode", default, deserialize_with = "opt_num")]
+    pub svc_space_domain_mode: Option<u8>,
+    #[serde(rename = "SVCTimeDomainMode", default, deserialize_with = "opt_num")]
+    pub svc_time_domain_mode: Option<u8>,
+    #[serde(rename = "SVCSpaceSupportMode", default, deserialize_with = "opt_num")]
+    pub svc_space_support_mode: Option<u8>,
+    #[serde(rename = "SVCTimeSupportMode", default, deserialize_with = "opt_num")]
+    pub svc_time_support_mode: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct SurveillanceParam {
+    #[serde(rename = "TimeFlag", default, deserialize_with = "opt_num")]
+    pub time_flag: Option<u8>,
+    #[serde(rename = "EventFlag", default, deserialize_with = "opt_num")]
+    pub event_flag: Option<u8>,
+    #[serde(rename = "AlertFlag", default, deserialize_with = "opt_num")]
+    pub alert_flag: Option<u8>,
+    #[serde(rename = "TimeShowFlag", default, deserialize_with = "opt_num")]
+    pub time_show_flag: Option<u8>,
+    #[serde(rename = "EventShowFlag", default, deserialize_with = "opt_num")]
+    pub event_show_flag: Option<u8>,
+    //标准文本中存在AlerShowtFlag拼写
+    #[serde(
+        rename = "AlertShowFlag",
+        alias = "AlerShowtFlag",
+        default,
+        deserialize_with = "opt_num"
+    )]
+    pub alert_show_flag: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AudioParam {
+    #[serde(rename = "AudioRecognitionFlag", default, deserialize_with = "opt_num")]
+    pub audio_recognition_flag: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct SvacEncodeConfig {
+    #[serde(rename = "ROIParam")]
+    pub roi_param: Option<RoiParam>,
+    #[serde(rename = "SVCParam")]
+    pub svc_param: Option<SvcParam>,
+    #[serde(rename = "SurveillanceParam")]
+    pub surveillance_param: Option<SurveillanceParam>,
+    #[serde(rename = "AudioParam")]
+    pub audio_param: Option<AudioParam>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct SvacDecodeConfig {
+    #[serde(rename = "SVCParam")]
+    pub svc_param: Option<SvcParam>,
+    #[serde(rename = "SurveillanceParam")]
+    pub surveillance_param: Option<SurveillanceParam>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct ConfigDownloadResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Result")]
+    pub result: Option<String>,
+    #[serde(rename = "BasicParam")]
+    pub basic_param: Option<BasicParam>,
+    #[serde(rename = "VideoParamOpt")]
+    pub video_param_opt: Option<VideoParamOpt>,
+    #[serde(rename = "SVACEncodeConfig")]
+    pub svac_encode_config: Option<SvacEncodeConfig>,
+    #[serde(rename = "SVACDecodeConfig")]
+    pub svac_decode_config: Option<SvacDecodeConfig>,
+    #[serde(rename = "AlarmReport")]
+    pub alarm_report: Option<AlarmReport>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct PresetItem {
+    #[serde(rename = "PresetID", default, deserialize_with = "opt_num")]
+    pub preset_id: Option<u8>,
+    #[serde(rename = "PresetName")]
+    pub preset_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct PresetQueryResponse {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "SumNum", default, deserialize_with = "opt_num")]
+    pub sum_num: Option<u32>,
+    #[serde(rename = "PresetList", default)]
+    pub preset_list: ItemList<PresetItem>,
+}
+
+/* ---------------------------------- Notify --------------------------------- */
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct KeepaliveInfo {
+    ///故障设备列表
+    #[serde(rename = "DeviceID", default)]
+    pub device_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct KeepaliveNotify {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    ///OK/ERROR
+    #[serde(rename = "Status", default)]
+    pub status: String,
+    #[serde(rename = "Info")]
+    pub info: Option<KeepaliveInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmTypeParam {
+    #[serde(rename = "EventType", default, deserialize_with = "opt_num")]
+    pub event_type: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmNotifyInfo {
+    #[serde(rename = "AlarmType", default, deserialize_with = "opt_num")]
+    pub alarm_type: Option<u8>,
+    #[serde(rename = "AlarmTypeParam")]
+    pub alarm_type_param: Option<AlarmTypeParam>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmNotify {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "AlarmPriority", default, deserialize_with = "opt_num")]
+    pub alarm_priority: Option<u8>,
+    #[serde(rename = "AlarmMethod", default, deserialize_with = "opt_num")]
+    pub alarm_method: Option<u8>,
+    #[serde(rename = "AlarmTime", default)]
+    pub alarm_time: String,
+    #[serde(rename = "AlarmDescription")]
+    pub alarm_description: Option<String>,
+    #[serde(rename = "Longitude", default, deserialize_with = "opt_num")]
+    pub longitude: Option<f64>,
+    #[serde(rename = "Latitude", default, deserialize_with = "opt_num")]
+    pub latitude: Option<f64>,
+    #[serde(rename = "Info")]
+    pub info: Option<AlarmNotifyInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct MediaStatusNotify {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    ///121：历史媒体文件发送结束
+    #[serde(rename = "NotifyType", default)]
+    pub notify_type: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct MobilePositionNotify {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "DeviceID", default)]
+    pub device_id: String,
+    #[serde(rename = "Time", default)]
+    pub time: String,
+    #[serde(rename = "Longitude", default, deserialize_with = "opt_num")]
+    pub longitude: Option<f64>,
+    #[serde(rename = "Latitude", default, deserialize_with = "opt_num")]
+    pub latitude: Option<f64>,
+    #[serde(rename = "Speed", default, deserialize_with = "opt_num")]
+    pub speed: Option<f64>,
+    #[serde(rename = "Direction", default, deserialize_with = "opt_num")]
+    pub direction: Option<f64>,
+    #[serde(rename = "Altitude", default, deserialize_with = "opt_num")]
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct BroadcastNotify {
+    #[serde(rename = "SN", default)]
+    pub sn: String,
+    #[serde(rename = "SourceID", default)]
+    pub source_id: String,
+    #[serde(rename = "TargetID", default)]
+    pub target_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_catalog_nested_items() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>Catalog</CmdType>
+<SN>17430</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<SumNum>2</SumNum>
+<DeviceList Num="2">
+<Item>
+<DeviceID>34020000001320000001</DeviceID>
+<Name>gate</Name>
+<Port></Port>
+<Status>ON</Status>
+<Info><PTZType>1</PTZType></Info>
+</Item>
+<Item>
+<DeviceID>34020000001320000002</DeviceID>
+<Name>yard</Name>
+<Port>5060</Port>
+<Longitude>116.39</Longitude>
+<Status>OFF</Status>
+</Item>
+</DeviceList>
+</Response>"#;
+        let msg = Manscdp::parse(xml.as_bytes()).unwrap();
+        assert_eq!(msg.root, Root::Response);
+        assert_eq!(msg.head.sn, "17430");
+        let Body::Response(Response::Catalog(list)) = msg.body else {
+            panic!("not catalog response");
+        };
+        assert_eq!(list.sum_num, Some(2));
+        assert_eq!(list.device_list.num, Some(2));
+        let items = &list.device_list.items;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].port, None);
+        assert_eq!(items[0].info.as_ref().and_then(|i| i.ptz_type), Some(1));
+        assert_eq!(items[1].port, Some(5060));
+        assert_eq!(items[1].status, "OFF");
+    }
+
+    #[test]
+    fn test_parse_gb2312_body() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Notify>
+<CmdType>Alarm</CmdType>
+<SN>8</SN>
+<DeviceID>34020000001340000001</DeviceID>
+<AlarmPriority>1</AlarmPriority>
+<AlarmMethod>5</AlarmMethod>
+<AlarmTime>2024-05-01T08:30:00</AlarmTime>
+<AlarmDescription>区域入侵</AlarmDescription>
+<Info><AlarmType>2</AlarmType><AlarmTypeParam><EventType>1</EventType></AlarmTypeParam></Info>
+</Notify>"#;
+        let (bytes, _, _) = GB18030.encode(xml);
+        let msg = Manscdp::parse(&bytes).unwrap();
+        let Body::Notify(Notify::Alarm(alarm)) = msg.body else {
+            panic!("not alarm notify");
+        };
+        assert_eq!(alarm.alarm_description.as_deref(), Some("区域入侵"));
+        assert_eq!(alarm.alarm_method, Some(5));
+        let info = alarm.info.unwrap();
+        assert_eq!(info.alarm_type, Some(2));
+        assert_eq!(info.alarm_type_param.and_then(|p| p.event_type), Some(1));
+    }
+
+    #[test]
+    fn test_parse_query_and_control() {
+        let xml = r#"<?xml version="1.0"?>
+<Query><CmdType>RecordInfo</CmdType><SN>3</SN><DeviceID>34020000001320000001</DeviceID>
+<StartTime>2024-05-01T00:00:00</StartTime><EndTime>2024-05-01T23:59:59</EndTime><Type>all</Type></Query>"#;
+        let msg = Manscdp::parse(xml.as_bytes()).unwrap();
+        let Body::Query(Query::RecordInfo(query)) = msg.body else {
+            panic!("not record query");
+        };
+        assert_eq!(query.record_type.as_deref(), Some("all"));
+
+        let xml = r#"<?xml version="1.0"?>
+<Control><CmdType>DeviceControl</CmdType><SN>4</SN><DeviceID>34020000001320000001</DeviceID>
+<AlarmCmd>ResetAlarm</AlarmCmd><Info><AlarmMethod>2</AlarmMethod><AlarmType>1</AlarmType></Info></Control>"#;
+        let msg = Manscdp::parse(xml.as_bytes()).unwrap();
+        let Body::Control(Control::DeviceControl(ctl)) = msg.body else {
+            panic!("not device control");
+        };
+        assert_eq!(ctl.alarm_cmd.as_deref(), Some("ResetAlarm"));
+        assert_eq!(ctl.info.unwrap().alarm_method.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_parse_unknown_cmd_type() {
+        let xml = r#"<?xml version="1.0"?>
+<Notify><CmdType>Weather</CmdType><SN>1</SN><DeviceID>34020000001110000001</DeviceID></Notify>"#;
+        assert_eq!(
+            Manscdp::parse(xml.as_bytes()).unwrap_err(),
+            ManscdpError::UnknownCmdType {
+                root: "Notify".to_string(),
+                cmd_type: "Weather".to_string(),
+            }
+        );
+        assert_eq!(
+            Manscdp::parse(xml.as_bytes()).unwrap_err().status_code(),
+            501
+        );
+        let (root, head) = parse_head(xml.as_bytes()).unwrap();
+        assert_eq!((root, head.sn.as_str()), (Root::Notify, "1"));
+        let xml = r#"<Notify><SN>1</SN></Notify>"#;
+        assert!(matches!(
+            Manscdp::parse(xml.as_bytes()),
+            Err(ManscdpError::MissingCmdType { .. })
+        ));
+        assert!(matches!(
+            Manscdp::parse(b"<Notify><CmdType>Keepalive</CmdType>"),
+            Err(ManscdpError::Xml(_))
+        ));
+        assert_eq!(
+            Manscdp::parse(b"<Notify><CmdType>Keepalive</CmdType>")
+                .unwrap_err()
+                .status_code(),
+            400
+        );
+    }
+}
@@ -1,5 +1,10 @@
+pub mod alarm;
 mod builder;
+pub mod cascade;
+pub mod catalog;
 pub mod cmd;
+pub mod dialog;
 pub mod events;
+pub mod manscdp;
 pub mod parser;
 pub mod requester;
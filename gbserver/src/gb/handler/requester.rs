@@ -1,45 +1,76 @@
-use crate::gb::handler::builder::ResponseBuilder;
-use crate::gb::handler::{cmd::CmdQuery, parser, parser::xml::KV2Model};
-use crate::gb::shared::rw::RWSession;
-use crate::general::model::AlarmInfo;
-use crate::service::callback;
-use crate::store::entity::{GbsDevice, GbsDeviceChannel, GbsDeviceExt, GbsOauth};
+use crate::gb::handler::alarm::AlarmPipeline;
+use crate::gb::handler::builder::{RequestBuilder, ResponseBuilder, MANSCDP_CONTENT_TYPE};
+use crate::gb::handler::cascade::Cascade;
+use crate::gb::handler::catalog::CatalogSync;
+use crate::gb::handler::cmd::{CmdQuery, CmdResponse, CmdSubscribe, KEY_BROADCAST};
+use crate::gb::handler::dialog::Dialog;
+use crate::gb::handler::manscdp::{
+    self, AlarmNotify, Body, DeviceInfoResponse, KeepaliveNotify, Manscdp, MediaStatusNotify,
+    MobilePositionNotify, Response, Root,
+};
+use crate::gb::handler::parser;
+use crate::gb::shared::rw::{RWSession, RequestOutput};
+use crate::gb::shared::transaction::Transaction;
+use crate::general::cache::{Cache, PlayType};
+use crate::general::model::{BroadcastInfo, MobilePosition};
+use crate::service::{callback, handler};
+use crate::store::entity::{
+    GbsDevice, GbsDeviceChannel, GbsDeviceExt, GbsDevicePosition, GbsDevicePreset, GbsOauth,
+};
 use crate::store::mapper;
 use common::anyhow::anyhow;
 use common::bytes::Bytes;
 use common::chrono::{Duration, Local};
 use common::exception::GlobalError::SysErr;
 use common::exception::{GlobalResult, TransError};
-use common::log::{debug, info, LevelFilter};
+use common::log::{debug, info};
 use common::log::{error, warn};
 use common::net::state::{Association, Package, Zip};
+use common::serde_json;
 use common::tokio::sync::mpsc::Sender;
 use encoding_rs::GB18030;
-use quick_xml::encoding;
 use rsip::headers::ToTypedHeader;
 use rsip::message::HeadersExt;
+use rsip::prelude::UntypedHeader;
 use rsip::services::DigestGenerator;
-use rsip::{Method, Request};
+use rsip::{Header, Method, Request};
 
 pub async fn hand_request(req: Request, tx: Sender<Zip>, bill: &Association) -> GlobalResult<()> {
     let device_id = parser::header::get_device_id_by_request(&req)?;
+    //上级平台的请求
+    if let Some(platform) = Cascade::platform(&device_id) {
+        return match req.method {
+            Method::Cancel => InDialog::cancel(req, tx, bill).await,
+            _ => Cascade::hand_request(platform, req, tx, bill).await,
+        };
+    }
     //校验设备是否注册
     if req.method == Method::Register {
         let _ = Register::process(&device_id, req, tx, bill)
             .await
             .hand_log(|msg| error!("设备 = [{}],注册失败;err={}", &device_id, msg));
         Ok(())
+    } else if req.method == Method::Options {
+        //能力探测无需注册,直接应答
+        let response = ResponseBuilder::build_options_response(&req, bill.get_remote_addr())?;
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        Ok(())
     } else {
         match State::check_session(tx.clone(), bill, &device_id).await? {
             State::Usable | State::ReCache => match req.method {
-                Method::Ack => Ok(()),
-                Method::Bye => Ok(()),
-                Method::Cancel => Ok(()),
-                Method::Info => Ok(()),
-                Method::Invite => Ok(()),
+                Method::Ack => {
+                    if let Ok(call_id) = req.call_id_header() {
+                        Dialog::confirm(call_id.value());
+                    }
+                    Ok(())
+                }
+                Method::Bye => InDialog::bye(&device_id, req, tx.clone(), bill).await,
+                Method::Cancel => InDialog::cancel(req, tx.clone(), bill).await,
+                Method::Info => InDialog::reply(req, tx.clone(), bill).await.map(|_| ()),
+                Method::Invite => Invite::process(&device_id, req, tx.clone(), bill).await,
                 Method::Message => Message::process(&device_id, req, tx.clone(), bill).await,
                 Method::Notify => Notify::process(&device_id, req, tx.clone(), bill).await,
-                Method::Options => Ok(()),
                 Method::PRack => Ok(()),
                 Method::Publish => Ok(()),
                 Method::Refer => Ok(()),
@@ -199,10 +230,9 @@ impl Register {
         let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(ok_response)));
         let _ = tx.clone().send(zip).await.hand_log(|msg| warn!("{msg}"));
 
-        info!("register ok. sent queryDeviceInfo & queryDeviceCatalog.");
-        // query subscribe device msg
+        info!("register ok. sent queryDeviceInfo & queryDeviceCatalog & subscribeCatalog.");
         CmdQuery::lazy_query_device_info(device_id).await?;
-        // CmdQuery::lazy_subscribe_device_catalog(device_id).await?;
+        CmdSubscribe::lazy_subscribe_catalog(device_id);
         CmdQuery::lazy_query_device_catalog(device_id).await
     }
 
@@ -215,6 +245,7 @@ impl Register {
         let ok_response = ResponseBuilder::build_logout_ok_response(req, bill.get_remote_addr())?;
         let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(ok_response)));
         let _ = tx.clone().send(zip).await.hand_log(|msg| warn!("{msg}"));
+        CmdSubscribe::unsubscribe_all(device_id).await;
         GbsDevice::update_gbs_device_status_by_device_id(device_id, 0).await?;
         RWSession::clean_rw_session_and_net(device_id).await;
         Ok(())
@@ -241,99 +272,359 @@ impl Message {
         tx: Sender<Zip>,
         bill: &Association,
     ) -> GlobalResult<()> {
-        use parser::xml::*;
-        match parse_xlm_to_vec(&req.body) {
-            Ok(vs) => {
-                let response =
-                    ResponseBuilder::build_register_ok_response(&req, bill.get_remote_addr())?;
-                for (k, v) in &vs {
-                    if MESSAGE_TYPE.contains(&&k[..]) {
-                        match &v[..] {
-                            MESSAGE_KEEP_ALIVE => {
-                                Self::keep_alive(device_id, vs, bill).await;
-                            }
-                            MESSAGE_CONFIG_DOWNLOAD => {}
-                            MESSAGE_NOTIFY_CATALOG => {
-                                Self::device_catalog(device_id, vs).await;
-                            }
-                            MESSAGE_DEVICE_INFO => {
-                                Self::device_info(vs).await;
-                            }
-                            MESSAGE_ALARM => {
-                                let _ = Self::message_notify_alarm(device_id, vs).await;
-                            }
-                            MESSAGE_RECORD_INFO => {}
-                            MESSAGE_MEDIA_STATUS => {}
-                            MESSAGE_BROADCAST => {}
-                            MESSAGE_DEVICE_STATUS => {}
-                            MESSAGE_DEVICE_CONTROL => {}
-                            MESSAGE_DEVICE_CONFIG => {}
-                            MESSAGE_PRESET_QUERY => {}
-                            _ => {
-                                warn!("device_id = {};message -- > {} 不支持。", device_id, v)
-                            }
-                        }
-                        let zip =
-                            Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
-                        let _ = tx.clone().send(zip).await.hand_log(|msg| error!("{msg}"));
-                        break;
-                    }
-                }
-                Ok(())
-            }
-            Err(err) => {
-                let val = encoding::decode(&req.body, GB18030).hand_log(|msg| error!("{msg}"))?;
-                Err(SysErr(anyhow!("xml解析失败: {err:?}; xml = [{}]", val)))?
+        let status = match Self::accept(device_id, &req) {
+            Ok(msg) => Self::handle(device_id, &req, msg, bill).await,
+            Err(status) => status,
+        };
+        let response = match status {
+            200 => ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), 200)?,
+            _ => ResponseBuilder::build_manscdp_reject_response(
+                &req,
+                bill.get_remote_addr(),
+                status,
+            )?,
+        };
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.clone().send(zip).await.hand_log(|msg| error!("{msg}"));
+        if status != 200 {
+            Self::response_error(device_id, &req.body);
+        }
+        Ok(())
+    }
+
+    //校验消息体类型并解析,失败时返回SIP应答码：415 类型不支持；400 格式错误；501 未知CmdType
+    fn accept(device_id: &String, req: &Request) -> Result<Manscdp, u16> {
+        let content_type = req.headers.iter().find_map(|header| match header {
+            Header::ContentType(content_type) => Some(content_type.value().to_string()),
+            _ => None,
+        });
+        //未携带Content-Type时按MANSCDP处理
+        if let Some(content_type) = content_type {
+            let mime = content_type.split(';').next().unwrap_or_default().trim();
+            if !mime.eq_ignore_ascii_case(MANSCDP_CONTENT_TYPE) {
+                warn!(
+                    "device_id = {};不支持的消息体类型: {}",
+                    device_id, content_type
+                );
+                return Err(415);
             }
         }
+        Manscdp::parse(&req.body).map_err(|err| {
+            let (val, _, _) = GB18030.decode(&req.body);
+            warn!("device_id = {};{err}; xml = [{}]", device_id, val);
+            err.status_code()
+        })
     }
-    async fn keep_alive(device_id: &String, vs: Vec<(String, String)>, bill: &Association) {
-        use parser::xml::{NOTIFY_DEVICE_ID, NOTIFY_STATUS};
-        if common::log::max_level() <= LevelFilter::Info {
-            let (mut device_id, mut status) = (String::new(), String::new());
-            for (k, v) in &vs {
-                match &k[..] {
-                    NOTIFY_DEVICE_ID => {
-                        device_id = v.to_string();
-                    }
-                    NOTIFY_STATUS => {
-                        status = v.to_string();
-                    }
-                    _ => {}
-                }
+
+    //设备的Query/Control无法处理时,以应用层Response告知Result=ERROR
+    fn response_error(device_id: &String, body: &[u8]) {
+        let Ok((Root::Query | Root::Control, head)) = manscdp::parse_head(body) else {
+            return;
+        };
+        if head.cmd_type.trim().is_empty() || head.sn.trim().is_empty() {
+            return;
+        }
+        let device_id = device_id.clone();
+        common::tokio::spawn(async move {
+            if let Ok((ident, msg)) = RequestBuilder::response_result(
+                &device_id,
+                head.cmd_type.trim(),
+                head.sn.trim(),
+                &head.device_id,
+                "ERROR",
+            )
+            .await
+            {
+                let _ = RequestOutput::new(ident, msg, None)
+                    .do_send()
+                    .hand_log(|msg| warn!("{msg}"));
+            }
+        });
+    }
+
+    //返回SIP应答码：暂不处理设备发起的Query/Control,应答501
+    async fn handle(device_id: &String, req: &Request, msg: Manscdp, bill: &Association) -> u16 {
+        let sn = msg.head.sn;
+        match msg.body {
+            Body::Notify(manscdp::Notify::Keepalive(keepalive)) => {
+                Self::keep_alive(device_id, &keepalive, bill).await;
+            }
+            Body::Response(Response::Catalog(list)) => {
+                CatalogSync::feed(device_id, list).await;
+            }
+            Body::Notify(manscdp::Notify::Catalog(list)) => {
+                let _ = GbsDeviceChannel::apply_catalog_notify(device_id, &list)
+                    .await
+                    .hand_log(|msg| error!("{msg}"));
+            }
+            Body::Response(Response::DeviceInfo(info)) => {
+                Self::device_info(&info).await;
+            }
+            Body::Notify(manscdp::Notify::Alarm(alarm)) => {
+                Self::message_notify_alarm(device_id, alarm).await;
+            }
+            Body::Notify(manscdp::Notify::MediaStatus(status)) => {
+                Self::media_status(device_id, req, status).await;
+            }
+            Body::Notify(manscdp::Notify::Broadcast(_)) => {}
+            Body::Response(
+                Response::ConfigDownload(_)
+                | Response::RecordInfo(_)
+                | Response::DeviceStatus(_)
+                | Response::DeviceControl(_)
+                | Response::DeviceConfig(_)
+                | Response::Alarm(_)
+                | Response::Broadcast(_),
+            ) => {
+                CmdResponse::dispatch(device_id, &sn, &req.body);
+            }
+            Body::Response(Response::PresetQuery(res)) => {
+                let _ = GbsDevicePreset::save_gbs_device_preset(device_id, &res)
+                    .await
+                    .hand_log(|msg| error!("{msg}"));
+            }
+            Body::Notify(manscdp::Notify::MobilePosition(notify)) => {
+                let _ = Self::mobile_position(device_id, notify).await;
+            }
+            body @ (Body::Query(_) | Body::Control(_)) => {
+                warn!("device_id = {};message -- > {:?} 不支持。", device_id, body);
+                return 501;
             }
-            debug!(
-                "keep_alive: device_id = {},status = {}",
-                &device_id, &status
-            );
         }
+        200
+    }
+
+    async fn keep_alive(device_id: &String, keepalive: &KeepaliveNotify, bill: &Association) {
+        debug!(
+            "keep_alive: device_id = {},status = {}",
+            &keepalive.device_id, &keepalive.status
+        );
         RWSession::heart(device_id, bill.clone());
     }
 
-    async fn device_info(vs: Vec<(String, String)>) {
-        let _ = GbsDeviceExt::update_gbs_device_ext_info(vs)
+    async fn device_info(info: &DeviceInfoResponse) {
+        let _ = GbsDeviceExt::update_gbs_device_ext_info(info)
             .await
             .hand_log(|msg| error!("{msg}"));
     }
 
-    async fn device_catalog(device_id: &String, vs: Vec<(String, String)>) {
-        if let Ok(_arr) = GbsDeviceChannel::insert_gbs_device_channel(device_id, vs)
+    //121:历史媒体文件发送结束;优先按会话Call-ID匹配流,其次按通道匹配下载/回放流
+    async fn media_status(device_id: &String, req: &Request, status: MediaStatusNotify) {
+        if status.notify_type.trim() != "121" {
+            debug!(
+                "media_status: device_id = {},notify_type = {} 不处理",
+                device_id, status.notify_type
+            );
+            return;
+        }
+        let channel_id = Some(status.device_id).filter(|id| !id.is_empty());
+        let stream_id = req
+            .call_id_header()
+            .ok()
+            .and_then(|call_id| {
+                Cache::stream_map_query_stream_id_by_call_id(&call_id.value().to_string())
+            })
+            .or_else(|| {
+                channel_id.and_then(|channel_id| {
+                    Cache::device_map_get_invite_info(device_id, &channel_id, &PlayType::Down)
+                        .or_else(|| {
+                            Cache::device_map_get_invite_info(
+                                device_id,
+                                &channel_id,
+                                &PlayType::Back,
+                            )
+                        })
+                        .map(|(stream_id, _ssrc)| stream_id)
+                })
+            });
+        match stream_id {
+            None => warn!("media_status: device_id = {},未匹配到媒体流", device_id),
+            Some(stream_id) => {
+                //BYE需等待设备响应,不阻塞读取
+                common::tokio::spawn(async move {
+                    handler::media_file_end(&stream_id).await;
+                });
+            }
+        }
+    }
+
+    async fn mobile_position(device_id: &str, notify: MobilePositionNotify) -> GlobalResult<()> {
+        let mut pos = MobilePosition::try_from(notify)?;
+        pos.device_id = device_id.to_string();
+        GbsDevicePosition::build(&pos)
+            .insert_gbs_device_position()
             .await
-            .hand_log(|msg| error!("{msg}"))
-        {
-            //通过预置位探测是否有云台可用
-            // for dc in arr {
-            //     let _ = CmdQuery::query_preset(&dc.device_id, Some(&dc.channel_id)).await.hand_log(|msg| error!("{msg}"));
-            // }
+    }
+
+    //报警通知需以应用层Response应答,否则设备会重发
+    async fn message_notify_alarm(device_id: &String, alarm: AlarmNotify) {
+        if !alarm.sn.trim().is_empty() {
+            let device_id = device_id.clone();
+            let sn = alarm.sn.trim().to_string();
+            let target_id = alarm.device_id.clone();
+            common::tokio::spawn(async move {
+                if let Ok((ident, msg)) =
+                    RequestBuilder::response_result(&device_id, "Alarm", &sn, &target_id, "OK")
+                        .await
+                {
+                    let _ = RequestOutput::new(ident, msg, None)
+                        .do_send()
+                        .hand_log(|msg| warn!("{msg}"));
+                }
+            });
+        }
+        AlarmPipeline::feed(device_id, alarm).await;
+    }
+}
+
+struct Invite;
+
+impl Invite {
+    //语音广播：设备以音频输出通道发起INVITE,匹配待建立的广播并应答sendonly音频
+    async fn process(
+        target_id: &String,
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<()> {
+        let key = format!("{KEY_BROADCAST}{target_id}");
+        let response: rsip::SipMessage = match Cache::state_get(&key) {
+            Some((bytes, Some(call_tx))) => {
+                let mut info: BroadcastInfo =
+                    serde_json::from_slice(&bytes).hand_log(|msg| error!("{msg}"))?;
+                match info.apply_offer(&req.body) {
+                    Ok(()) => {
+                        let response = ResponseBuilder::build_broadcast_ok_response(
+                            &req,
+                            bill.get_remote_addr(),
+                            &info,
+                        )?;
+                        info.call_id = req
+                            .call_id_header()
+                            .hand_log(|msg| warn!("{msg}"))?
+                            .value()
+                            .to_string();
+                        info.remote_tag = req
+                            .from_header()
+                            .hand_log(|msg| warn!("{msg}"))?
+                            .tag()
+                            .hand_log(|msg| warn!("{msg}"))?
+                            .map(|tag| tag.to_string())
+                            .unwrap_or_default();
+                        info.local_tag = ResponseBuilder::get_tag_by_header_to(&response)?;
+                        info.seq = 1;
+                        Dialog::track(Dialog::uas(target_id, &req, &info.local_tag)?);
+                        //待建立 -> 已建立：去除超时,保留至停止广播
+                        Cache::state_remove(&key);
+                        Cache::state_insert_obj(key, &info, None);
+                        let _ = call_tx
+                            .try_send(Some(Bytes::from(serde_json::to_vec(&info).unwrap())))
+                            .hand_log(|msg| warn!("{msg}"));
+                        response.into()
+                    }
+                    Err(err) => {
+                        warn!("语音广播 = [{target_id}],{err}");
+                        Cache::state_remove(&key);
+                        let _ = call_tx.try_send(None);
+                        ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), 488)?
+                    }
+                }
+            }
+            _ => {
+                //对话外的新INVITE,无匹配广播按目标不存在应答
+                warn!("未知INVITE = [{target_id}],无待建立的语音广播");
+                ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), 404)?
+            }
+        };
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        Ok(())
+    }
+}
+
+struct InDialog;
+
+impl InDialog {
+    //设备主动挂断：应答200,终止对话并清理对应的媒体流或语音广播
+    async fn bye(
+        device_id: &String,
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<()> {
+        let Some(dialog) = Self::reply(req, tx, bill).await? else {
+            return Ok(());
+        };
+        let call_id = dialog.get_call_id();
+        Dialog::terminate(call_id);
+        Cascade::device_bye(call_id);
+        if let Some(stream_id) = Cache::stream_map_query_stream_id_by_call_id(call_id) {
+            common::tokio::spawn(async move {
+                handler::device_bye(&stream_id).await;
+            });
         }
+        //语音广播由设备音频输出通道发起,以其ID为键
+        let key = format!("{KEY_BROADCAST}{device_id}");
+        if let Some((bytes, _)) = Cache::state_get(&key) {
+            if serde_json::from_slice::<BroadcastInfo>(&bytes)
+                .is_ok_and(|info| info.call_id.eq(call_id))
+            {
+                info!("语音广播 = [{device_id}],设备挂断");
+                Cache::state_remove(&key);
+            }
+        }
+        Ok(())
     }
 
-    async fn message_notify_alarm(device_id: &str, vs: Vec<(String, String)>) -> GlobalResult<()> {
-        let mut info = AlarmInfo::kv_to_model(vs)?;
-        info.deviceId = device_id.to_string();
-        callback::call_alarm_info(&info).await?;
+    //CANCEL：无匹配INVITE事务应答481；INVITE尚未最终应答时,CANCEL应答200且INVITE应答487
+    async fn cancel(req: Request, tx: Sender<Zip>, bill: &Association) -> GlobalResult<()> {
+        let pending = Transaction::server_invite_pending(&req);
+        let code = if pending.is_some() { 200 } else { 481 };
+        let mut responses = vec![ResponseBuilder::build_status_response(
+            &req,
+            bill.get_remote_addr(),
+            code,
+        )?];
+        if pending == Some(true) {
+            let seq = req
+                .cseq_header()
+                .hand_log(|msg| warn!("{msg}"))?
+                .seq()
+                .hand_log(|msg| warn!("{msg}"))?;
+            let mut invite = req.clone();
+            invite.method = Method::Invite;
+            invite
+                .headers
+                .retain(|header| !matches!(header, Header::CSeq(_)));
+            invite
+                .headers
+                .push(rsip::headers::CSeq::new(format!("{seq} INVITE")).into());
+            responses.push(ResponseBuilder::build_status_response(
+                &invite,
+                bill.get_remote_addr(),
+                487,
+            )?);
+        }
+        for response in responses {
+            let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+            let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        }
         Ok(())
     }
+
+    //对话内请求：匹配对话应答200,否则按校验结果应答481/500
+    async fn reply(
+        req: Request,
+        tx: Sender<Zip>,
+        bill: &Association,
+    ) -> GlobalResult<Option<Dialog>> {
+        let dialog = Dialog::receive(&req);
+        let code = dialog.as_ref().map_or_else(|code| *code, |_| 200);
+        let response = ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), code)?;
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.send(zip).await.hand_log(|msg| error!("{msg}"));
+        Ok(dialog.ok())
+    }
 }
 
 struct Notify;
@@ -345,34 +636,38 @@ impl Notify {
         tx: Sender<Zip>,
         bill: &Association,
     ) -> GlobalResult<()> {
-        use parser::xml::*;
-        match parse_xlm_to_vec(&req.body) {
-            Ok(vs) => {
-                let response =
-                    ResponseBuilder::build_register_ok_response(&req, bill.get_remote_addr())?;
-                for (k, v) in &vs {
-                    if MESSAGE_TYPE.contains(&&**k) {
-                        match &v[..] {
-                            MESSAGE_NOTIFY_CATALOG => {
-                                GbsDeviceChannel::insert_gbs_device_channel(device_id, vs).await?;
-                            }
-                            _ => {
-                                debug!("cmdType暂不支持;{k} : {v}");
-                            }
-                        }
-                        let zip =
-                            Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
-                        let _ = tx.clone().send(zip).await.hand_log(|msg| error!("{msg}"));
-                        break;
-                    }
+        let msg = Message::accept(device_id, &req);
+        let response = match &msg {
+            Ok(_) => ResponseBuilder::build_status_response(&req, bill.get_remote_addr(), 200)?,
+            Err(status) => ResponseBuilder::build_manscdp_reject_response(
+                &req,
+                bill.get_remote_addr(),
+                *status,
+            )?,
+        };
+        if let Ok(msg) = msg {
+            match msg.body {
+                //入库失败不影响应答,避免设备重传并终止订阅
+                Body::Notify(manscdp::Notify::Catalog(list)) => {
+                    let _ = GbsDeviceChannel::apply_catalog_notify(device_id, &list)
+                        .await
+                        .hand_log(|msg| error!("{msg}"));
+                }
+                //订阅通知以SIP应答确认即可
+                Body::Notify(manscdp::Notify::Alarm(alarm)) => {
+                    AlarmPipeline::feed(device_id, alarm).await;
+                }
+                Body::Notify(manscdp::Notify::MobilePosition(notify)) => {
+                    let _ = Message::mobile_position(device_id, notify).await;
+                }
+                body => {
+                    debug!("cmdType暂不支持;{:?}", body);
                 }
-                Ok(())
-            }
-            Err(err) => {
-                let val = encoding::decode(&req.body, GB18030).hand_log(|msg| error!("{msg}"))?;
-                Err(SysErr(anyhow!("xml解析失败: {err:?}; xml = [{}]", val)))?
             }
         }
+        let zip = Zip::build_data(Package::new(bill.clone(), Bytes::from(response)));
+        let _ = tx.clone().send(zip).await.hand_log(|msg| error!("{msg}"));
+        Ok(())
     }
 }
 
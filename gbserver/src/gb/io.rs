@@ -1,9 +1,11 @@
 use crate::gb::handler;
+use crate::gb::handler::cmd::CmdStream;
 use crate::gb::handler::events::event::EventSession;
 use crate::gb::handler::parser;
 pub use crate::gb::shared::rw::RWSession;
+use crate::gb::shared::transaction::Transaction;
 use common::exception::TransError;
-use common::log::{debug, error, info};
+use common::log::{debug, error, info, warn};
 use common::net::state::{Package, Zip};
 use common::tokio::sync::mpsc::{Receiver, Sender};
 use encoding_rs::GB18030;
@@ -26,6 +28,10 @@ pub async fn read(mut input: Receiver<Zip>, output_tx: Sender<Zip>) {
                             &req.headers,
                             GB18030.decode(&req.body).0
                         );
+                        //重传请求由服务端事务吸收
+                        if !Transaction::server_received(&req, &output_tx, &association) {
+                            continue;
+                        }
                         let _ =
                             handler::requester::hand_request(req, output_tx.clone(), &association)
                                 .await;
@@ -39,6 +45,21 @@ pub async fn read(mut input: Receiver<Zip>, output_tx: Sender<Zip>) {
                             &res.headers,
                             GB18030.decode(&res.body).0
                         );
+                        //重传应答由客户端事务吸收
+                        if !Transaction::client_received(&res) {
+                            continue;
+                        }
+                        if CmdStream::is_stray_invite_ok(&res) {
+                            match RWSession::get_device_id_by_association(&association) {
+                                Some(device_id) => {
+                                    common::tokio::spawn(async move {
+                                        let _ = CmdStream::stray_invite_ok(&device_id, &res).await;
+                                    });
+                                }
+                                None => warn!("INVITE应答:未知设备 {:?}", &association),
+                            }
+                            continue;
+                        }
                         match (
                             res.call_id_header(),
                             res.cseq_header(),
@@ -89,6 +110,7 @@ pub async fn write(mut output_rx: Receiver<Zip>, output: Sender<Zip>) {
                     pkg.get_association(),
                     GB18030.decode(pkg.get_data()).0
                 );
+                Transaction::server_respond(pkg.get_data());
             }
             Zip::Event(ent) => {
                 info!(
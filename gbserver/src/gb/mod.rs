@@ -21,6 +21,10 @@ pub struct SessionConf {
     wan_ip: Ipv4Addr,
     lan_port: u16,
     wan_port: u16,
+    //目录订阅有效期(秒),默认3600
+    subscribe_expires: Option<u32>,
+    //心跳超时后以OPTIONS探测等待应答的秒数,有应答则续期,不配置则直接离线
+    options_probe: Option<u8>,
 }
 
 impl SessionConf {
@@ -43,6 +47,7 @@ impl SessionConf {
     pub async fn run(tu: (Option<std::net::TcpListener>, Option<UdpSocket>)) -> GlobalResult<()> {
         let (output, input) = net::sdx::run_by_tokio(tu).await?;
         let (output_tx, output_rx) = mpsc::channel(CHANNEL_BUFFER_SIZE);
+        handler::cascade::Cascade::start(output_tx.clone());
         let read_task = common::tokio::spawn(async move {
             io::read(input, output_tx).await;
         });
@@ -2,14 +2,17 @@
 /// UDP：三次心跳超时则移除会话
 /// TCP：连接断开或三次心跳超时则移除会话
 pub mod rw {
+    use super::transaction::{Transaction, TIMER_B};
+    use crate::gb::handler::cmd::CmdQuery;
     use crate::gb::handler::events::event::{Container, EventSession, Ident, EXPIRES};
+    use crate::gb::SessionConf;
     use crate::store::entity::GbsDevice;
     use common::anyhow::anyhow;
     use common::bytes::Bytes;
     use common::constructor::New;
     use common::exception::GlobalError::SysErr;
     use common::exception::{GlobalResult, TransError};
-    use common::log::{error, warn};
+    use common::log::{debug, error, warn};
     use common::net::state::{Association, Event, Package, Protocol, Zip};
     use common::once_cell::sync::Lazy;
     use common::tokio;
@@ -18,8 +21,8 @@ pub mod rw {
     use common::tokio::time;
     use common::tokio::time::Instant;
     use parking_lot::Mutex;
-    use rsip::{Response, SipMessage};
-    use std::collections::{BTreeSet, HashMap};
+    use rsip::{Method, Response, SipMessage};
+    use std::collections::{BTreeSet, HashMap, HashSet};
     use std::sync::Arc;
     use std::thread;
     use std::time::Duration;
@@ -30,21 +33,30 @@ pub mod rw {
         shared: Arc<Shared>,
         //更新设备状态
         db_task: Sender<String>,
+        //心跳超时的设备OPTIONS探测
+        probe_task: Sender<String>,
+        probe: Option<Duration>,
     }
 
     impl RWSession {
         fn init() -> Self {
             let (tx, rx) = mpsc::channel(16);
+            let (probe_tx, probe_rx) = mpsc::channel(16);
             let session = RWSession {
                 shared: Arc::new(Shared {
                     state: Mutex::new(State {
                         sessions: HashMap::new(),
                         expirations: BTreeSet::new(),
                         bill_map: HashMap::new(),
+                        probing: HashSet::new(),
                     }),
                     background_task: Notify::new(),
                 }),
                 db_task: tx.clone(),
+                probe_task: probe_tx,
+                probe: SessionConf::get_session_by_conf()
+                    .get_options_probe()
+                    .map(|secs| Duration::from_secs(secs as u64)),
             };
             let shared = session.shared.clone();
             thread::Builder::new()
@@ -60,6 +72,9 @@ pub mod rw {
                         let db_task = tokio::spawn(async move {
                             Self::do_update_device_status(rx).await;
                         });
+                        tokio::spawn(async move {
+                            Self::do_probe_device(probe_rx).await;
+                        });
                         let clean_task = tokio::spawn(async move {
                             let _ = Self::purge_expired_task(shared).await;
                         });
@@ -76,6 +91,21 @@ pub mod rw {
             }
         }
 
+        //探测有应答则按心跳续期,否则等待探测期满后离线
+        async fn do_probe_device(mut rx: Receiver<String>) {
+            let limit = RW_SESSION.probe.unwrap_or(Duration::from_secs(EXPIRES));
+            while let Some(device_id) = rx.recv().await {
+                tokio::spawn(async move {
+                    if let Ok(code) = CmdQuery::options(&device_id, limit).await {
+                        debug!("设备 = [{device_id}],OPTIONS探测应答 {code}");
+                        if let Some(bill) = Self::get_bill_by_device_id(&device_id) {
+                            Self::heart(&device_id, bill);
+                        }
+                    }
+                });
+            }
+        }
+
         async fn purge_expired_task(shared: Arc<Shared>) -> GlobalResult<()> {
             loop {
                 if let Some(when) = shared.purge_expired_state().await? {
@@ -119,6 +149,7 @@ pub mod rw {
 
             let state = &mut *guard;
             if let Some(device_id) = state.bill_map.remove(bill) {
+                state.probing.remove(&device_id);
                 if let Some((_tx, when, _expires, _bill)) = state.sessions.remove(&device_id) {
                     state.expirations.remove(&(when, device_id));
                 }
@@ -137,6 +168,7 @@ pub mod rw {
                 let mut guard = RW_SESSION.shared.state.lock();
 
                 let state = &mut *guard;
+                state.probing.remove(device_id);
                 if let Some((tx, when, _expires, bill)) = state.sessions.remove(device_id) {
                     state.expirations.remove(&(when, device_id.clone()));
                     state.bill_map.remove(&bill);
@@ -170,6 +202,7 @@ pub mod rw {
                     state.bill_map.insert(old_bill, device_id.clone());
                     *bill = new_bill;
                 }
+                state.probing.remove(device_id);
                 let old_when = *when;
                 state.expirations.remove(&(old_when, device_id.clone()));
                 let ct = Instant::now() + *expires;
@@ -229,9 +262,7 @@ pub mod rw {
         pub fn do_send_off(device_id: &String, msg: SipMessage) -> GlobalResult<()> {
             let (request_sender, bill) = RWSession::get_output_sender_by_device_id(device_id)
                 .ok_or(SysErr(anyhow!("设备 {device_id},已下线")))?;
-            let _ = request_sender
-                .try_send(Zip::build_data(Package::new(bill, Bytes::from(msg))))
-                .hand_log(|msg| warn!("{msg}"));
+            Self::output(&request_sender, bill, msg);
             Ok(())
         }
 
@@ -239,11 +270,14 @@ pub mod rw {
             let device_id = self.ident.get_device_id();
             let (request_sender, bill) = RWSession::get_output_sender_by_device_id(device_id)
                 .ok_or(SysErr(anyhow!("设备 {device_id},已下线")))?;
-            let when = Instant::now() + Duration::from_secs(EXPIRES);
+            //INVITE事务由 Timer B 控制超时，不受通用事件过期约束
+            let expires = match &self.msg {
+                SipMessage::Request(req) if req.method == Method::Invite => TIMER_B,
+                _ => Duration::from_secs(EXPIRES),
+            };
+            let when = Instant::now() + expires;
             EventSession::listen_event(&self.ident, when, Container::build_res(self.event_sender))?;
-            let _ = request_sender
-                .try_send(Zip::build_data(Package::new(bill, Bytes::from(self.msg))))
-                .hand_log(|msg| error!("{msg}"));
+            Self::output(&request_sender, bill, self.msg);
             Ok(())
         }
 
@@ -251,10 +285,16 @@ pub mod rw {
             let device_id = self.ident.get_device_id();
             let (request_sender, bill) = RWSession::get_output_sender_by_device_id(device_id)
                 .ok_or(SysErr(anyhow!("设备 {device_id},已下线")))?;
+            Self::output(&request_sender, bill, self.msg);
+            Ok(())
+        }
+
+        //登记客户端事务后发送，UDP下由事务层负责重传
+        fn output(request_sender: &Sender<Zip>, bill: Association, msg: SipMessage) {
+            Transaction::client_send(&msg, request_sender, &bill);
             let _ = request_sender
-                .try_send(Zip::build_data(Package::new(bill, Bytes::from(self.msg))))
+                .try_send(Zip::build_data(Package::new(bill, Bytes::from(msg))))
                 .hand_log(|msg| error!("{msg}"));
-            Ok(())
         }
     }
 
@@ -270,9 +310,27 @@ pub mod rw {
 
             let state = &mut *guard;
             let now = Instant::now();
-            while let Some((when, device_id)) = state.expirations.iter().next() {
-                if when > &now {
-                    return Ok(Some(*when));
+            while let Some((when, device_id)) = state.expirations.first().cloned() {
+                if when > now {
+                    return Ok(Some(when));
+                }
+                //心跳超时：配置探测时先下发OPTIONS,探测期满仍无应答再离线
+                if let Some(probe) = RW_SESSION.probe {
+                    if state.probing.insert(device_id.clone()) {
+                        let next = now + probe;
+                        state.expirations.remove(&(when, device_id.clone()));
+                        state.expirations.insert((next, device_id.clone()));
+                        if let Some((_tx, when, _dur, _bill)) = state.sessions.get_mut(&device_id) {
+                            *when = next;
+                        }
+                        let _ = RW_SESSION
+                            .probe_task
+                            .clone()
+                            .try_send(device_id)
+                            .hand_log(|msg| warn!("{msg}"));
+                        continue;
+                    }
+                    state.probing.remove(&device_id);
                 }
                 //放入队列中处理，避免阻塞导致锁长期占用:更新DB中设备状态为离线
                 let _ = RW_SESSION
@@ -282,9 +340,10 @@ pub mod rw {
                     .hand_log(|msg| warn!("{msg}"));
                 // GbsDevice::update_gbs_device_status_by_device_id(device_id, 0);
                 //移除会话map
-                if let Some((tx, when, _dur, bill)) = state.sessions.remove(device_id) {
+                state.expirations.remove(&(when, device_id.clone()));
+                if let Some((tx, when, _dur, bill)) = state.sessions.remove(&device_id) {
                     state.bill_map.remove(&bill);
-                    state.expirations.remove(&(when, device_id.to_string()));
+                    state.expirations.remove(&(when, device_id));
                     //通知网络出口关闭TCP连接
                     if &Protocol::TCP == bill.get_protocol() {
                         let _ = tx
@@ -305,6 +364,8 @@ pub mod rw {
         expirations: BTreeSet<(Instant, String)>,
         //映射网络三元组与设备ID，bill,device_id
         bill_map: HashMap<Association, String>,
+        //心跳超时后正在OPTIONS探测的设备
+        probing: HashSet<String>,
     }
 
     impl State {
@@ -315,6 +376,651 @@ pub mod rw {
     }
 }
 
+/// SIP事务层(RFC 3261 §17)：以 Via branch + CSeq 方法标识事务
+/// 客户端事务：UDP下按 Timer A/E 重传请求，Timer B/F 超时终止，INVITE 非2xx最终应答由事务层回复ACK
+/// 服务端事务：缓存已发送的应答，吸收对端重传的请求并重放应答
+pub mod transaction {
+    use crate::gb::handler::events::event::EventSession;
+    use crate::gb::handler::parser;
+    use common::bytes::Bytes;
+    use common::exception::TransError;
+    use common::log::{debug, error, warn};
+    use common::net::state::{Association, Package, Protocol, Zip};
+    use common::once_cell::sync::Lazy;
+    use common::tokio;
+    use common::tokio::sync::mpsc::Sender;
+    use common::tokio::sync::Notify;
+    use common::tokio::time;
+    use common::tokio::time::Instant;
+    use parking_lot::Mutex;
+    use rsip::headers::ToTypedHeader;
+    use rsip::message::HeadersExt;
+    use rsip::prelude::UntypedHeader;
+    use rsip::{Header, Method, Request, Response, SipMessage};
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// RTT估值
+    pub const T1: Duration = Duration::from_millis(500);
+    /// 非INVITE请求最大重传间隔
+    pub const T2: Duration = Duration::from_secs(4);
+    /// 消息在网络中的最大存活时间
+    pub const T4: Duration = Duration::from_secs(5);
+    /// INVITE客户端事务超时 64*T1
+    pub const TIMER_B: Duration = Duration::from_secs(32);
+    /// 非INVITE客户端事务超时 64*T1
+    pub const TIMER_F: Duration = Duration::from_secs(32);
+    //UDP下INVITE客户端事务收到非2xx最终应答后，等待应答重传
+    const TIMER_D: Duration = Duration::from_secs(32);
+    //UDP下服务端事务发出最终应答后，吸收请求重传 64*T1
+    const TIMER_J: Duration = Duration::from_secs(32);
+    const MAGIC_COOKIE: &str = "z9hG4bK";
+
+    static TRANSACTION: Lazy<Transaction> = Lazy::new(Transaction::init);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct TxKey {
+        branch: String,
+        method: String,
+    }
+
+    impl TxKey {
+        //RFC 3261 branch以z9hG4bK开头；兼容RFC 2543设备时退化为 call_id:seq
+        pub fn build<T: HeadersExt>(msg: &T) -> Option<TxKey> {
+            let via = msg.via_header().ok()?.typed().ok()?;
+            let cseq = msg.cseq_header().ok()?.typed().ok()?;
+            let branch = match via.branch() {
+                Some(branch) if branch.to_string().starts_with(MAGIC_COOKIE) => branch.to_string(),
+                _ => format!("{}:{}", msg.call_id_header().ok()?.value(), cseq.seq),
+            };
+            Some(TxKey {
+                branch,
+                method: cseq.method.to_string(),
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    enum Side {
+        Client,
+        Server,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TxState {
+        Trying,
+        Proceeding,
+        Completed,
+    }
+
+    struct ClientTx {
+        request: Request,
+        data: Bytes,
+        output: Sender<Zip>,
+        bill: Association,
+        reliable: bool,
+        state: TxState,
+        //当前重传间隔，None:不再重传
+        interval: Option<Duration>,
+        retransmit_at: Option<Instant>,
+        //Timer B/F；进入Completed后为 Timer D/K
+        deadline: Instant,
+        //INVITE非2xx最终应答对应的ACK，用于应答重传时重发
+        ack: Option<Bytes>,
+        //当前登记的定时刻度
+        when: Instant,
+    }
+
+    impl ClientTx {
+        fn next_when(&self) -> Instant {
+            self.retransmit_at
+                .map_or(self.deadline, |at| at.min(self.deadline))
+        }
+    }
+
+    struct ServerTx {
+        response: Option<Bytes>,
+        final_code: Option<u16>,
+        reliable: bool,
+        when: Instant,
+    }
+
+    pub struct Transaction {
+        shared: Arc<Shared>,
+    }
+
+    impl Transaction {
+        fn init() -> Self {
+            let transaction = Transaction {
+                shared: Arc::new(Shared {
+                    state: Mutex::new(State {
+                        clients: HashMap::new(),
+                        servers: HashMap::new(),
+                        timers: BTreeSet::new(),
+                    }),
+                    background_task: Notify::new(),
+                }),
+            };
+            let shared = transaction.shared.clone();
+            thread::Builder::new()
+                .name("Shared:transaction".to_string())
+                .spawn(|| {
+                    let rt = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .hand_log(|msg| error!("{msg}"))
+                        .unwrap();
+                    rt.block_on(Self::timer_task(shared));
+                })
+                .expect("Shared:transaction background thread create failed");
+            transaction
+        }
+
+        async fn timer_task(shared: Arc<Shared>) {
+            loop {
+                let (next, timeouts) = shared.fire_expired_timers();
+                //Timer B/F超时以408通知等待方
+                for res in timeouts {
+                    if let (Ok(call_id), Ok(cs_eq)) = (res.call_id_header(), res.cseq_header()) {
+                        let _ = EventSession::handle_response(
+                            parser::header::get_device_id_by_response(&res).unwrap_or_default(),
+                            call_id.clone().into(),
+                            cs_eq.clone().into(),
+                            res,
+                        )
+                        .await;
+                    }
+                }
+                if let Some(when) = next {
+                    tokio::select! {
+                        _ = time::sleep_until(when) =>{},
+                        _ = shared.background_task.notified() =>{},
+                    }
+                } else {
+                    shared.background_task.notified().await;
+                }
+            }
+        }
+
+        //登记客户端事务；ACK不建立事务
+        pub fn client_send(msg: &SipMessage, output: &Sender<Zip>, bill: &Association) {
+            let SipMessage::Request(req) = msg else {
+                return;
+            };
+            if req.method == Method::Ack {
+                return;
+            }
+            let Some(key) = TxKey::build(req) else {
+                warn!("请求缺少Via/CSeq,无法建立事务");
+                return;
+            };
+            let now = Instant::now();
+            let reliable = &Protocol::TCP == bill.get_protocol();
+            let interval = (!reliable).then_some(T1);
+            let timeout = if req.method == Method::Invite {
+                TIMER_B
+            } else {
+                TIMER_F
+            };
+            let mut tx = ClientTx {
+                request: req.clone(),
+                data: Bytes::from(msg.clone()),
+                output: output.clone(),
+                bill: bill.clone(),
+                reliable,
+                state: TxState::Trying,
+                interval,
+                retransmit_at: interval.map(|interval| now + interval),
+                deadline: now + timeout,
+                ack: None,
+                when: now,
+            };
+            tx.when = tx.next_when();
+
+            let mut guard = TRANSACTION.shared.state.lock();
+            let state = &mut *guard;
+            let notify = state.next_timer().map(|ts| ts > tx.when).unwrap_or(true);
+            state.timers.insert((tx.when, Side::Client, key.clone()));
+            if let Some(old) = state.clients.insert(key.clone(), tx) {
+                state.timers.remove(&(old.when, Side::Client, key));
+            }
+            drop(guard);
+            if notify {
+                TRANSACTION.shared.background_task.notify_one();
+            }
+        }
+
+        //应答交由客户端事务处理；返回false表示为重传应答，已被吸收
+        pub fn client_received(res: &Response) -> bool {
+            let Some(key) = TxKey::build(res) else {
+                return true;
+            };
+            let code = res.status_code.code();
+            let now = Instant::now();
+            let mut guard = TRANSACTION.shared.state.lock();
+            let State {
+                clients, timers, ..
+            } = &mut *guard;
+            let Some(tx) = clients.get_mut(&key) else {
+                return true;
+            };
+            let invite = tx.request.method == Method::Invite;
+            match (tx.state, code) {
+                (TxState::Completed, _) => {
+                    if let Some(ack) = &tx.ack {
+                        let _ = tx
+                            .output
+                            .try_send(Zip::build_data(Package::new(tx.bill.clone(), ack.clone())))
+                            .hand_log(|msg| warn!("{msg}"));
+                    }
+                    debug!("{:?},吸收重传应答", key);
+                    return false;
+                }
+                (_, 100..=199) => {
+                    tx.state = TxState::Proceeding;
+                    if invite {
+                        //INVITE收到临时应答后停止重传
+                        tx.interval = None;
+                        tx.retransmit_at = None;
+                    } else if !tx.reliable {
+                        tx.interval = Some(T2);
+                    }
+                }
+                (_, 200..=299) if invite => {
+                    //2xx的ACK由TU发送，事务直接终止
+                    timers.remove(&(tx.when, Side::Client, key.clone()));
+                    clients.remove(&key);
+                    return true;
+                }
+                _ => {
+                    tx.state = TxState::Completed;
+                    tx.interval = None;
+                    tx.retransmit_at = None;
+                    let linger = if invite {
+                        tx.ack = ack_for(&tx.request, res);
+                        if let Some(ack) = &tx.ack {
+                            let _ = tx
+                                .output
+                                .try_send(Zip::build_data(Package::new(
+                                    tx.bill.clone(),
+                                    ack.clone(),
+                                )))
+                                .hand_log(|msg| warn!("{msg}"));
+                        }
+                        TIMER_D
+                    } else {
+                        T4
+                    };
+                    tx.deadline = if tx.reliable { now } else { now + linger };
+                }
+            }
+            timers.remove(&(tx.when, Side::Client, key.clone()));
+            tx.when = tx.next_when();
+            timers.insert((tx.when, Side::Client, key));
+            true
+        }
+
+        //请求交由服务端事务处理；返回false表示为重传请求，已重放应答或仍在处理中
+        pub fn server_received(req: &Request, output: &Sender<Zip>, bill: &Association) -> bool {
+            let Some(mut key) = TxKey::build(req) else {
+                return true;
+            };
+            let mut guard = TRANSACTION.shared.state.lock();
+            let state = &mut *guard;
+            if req.method == Method::Ack {
+                //非2xx最终应答的ACK与INVITE同属一个事务，由事务层吸收；2xx的ACK交由TU
+                key.method = Method::Invite.to_string();
+                return match state.servers.get(&key) {
+                    Some(tx) if tx.final_code.is_some_and(|code| code >= 300) => {
+                        state.timers.remove(&(tx.when, Side::Server, key.clone()));
+                        state.servers.remove(&key);
+                        false
+                    }
+                    _ => true,
+                };
+            }
+            if let Some(tx) = state.servers.get(&key) {
+                match &tx.response {
+                    Some(data) => {
+                        debug!("{:?},重传请求,重放应答", key);
+                        let _ = output
+                            .try_send(Zip::build_data(Package::new(bill.clone(), data.clone())))
+                            .hand_log(|msg| warn!("{msg}"));
+                    }
+                    None => debug!("{:?},重传请求,正在处理", key),
+                }
+                return false;
+            }
+            //未应答的服务端事务最长保留 64*T1
+            let when = Instant::now() + TIMER_F;
+            let notify = state.next_timer().map(|ts| ts > when).unwrap_or(true);
+            state.timers.insert((when, Side::Server, key.clone()));
+            state.servers.insert(
+                key,
+                ServerTx {
+                    response: None,
+                    final_code: None,
+                    reliable: &Protocol::TCP == bill.get_protocol(),
+                    when,
+                },
+            );
+            drop(guard);
+            if notify {
+                TRANSACTION.shared.background_task.notify_one();
+            }
+            true
+        }
+
+        //CANCEL匹配的INVITE服务端事务：None无匹配,Some(true)尚未发送最终应答
+        pub fn server_invite_pending(cancel: &Request) -> Option<bool> {
+            let mut key = TxKey::build(cancel)?;
+            key.method = Method::Invite.to_string();
+            let guard = TRANSACTION.shared.state.lock();
+            guard.servers.get(&key).map(|tx| tx.final_code.is_none())
+        }
+
+        //网络出口发送应答时缓存至服务端事务，最终应答后UDP保留 Timer J 以吸收重传
+        pub fn server_respond(data: &Bytes) {
+            let Ok(SipMessage::Response(res)) = SipMessage::try_from(data.clone()) else {
+                return;
+            };
+            let Some(key) = TxKey::build(&res) else {
+                return;
+            };
+            let code = res.status_code.code();
+            let mut guard = TRANSACTION.shared.state.lock();
+            let State {
+                servers, timers, ..
+            } = &mut *guard;
+            let Some(tx) = servers.get_mut(&key) else {
+                return;
+            };
+            tx.response = Some(data.clone());
+            if code >= 200 {
+                tx.final_code = Some(code);
+                if tx.reliable {
+                    timers.remove(&(tx.when, Side::Server, key.clone()));
+                    servers.remove(&key);
+                    return;
+                }
+                let when = Instant::now() + TIMER_J;
+                timers.remove(&(tx.when, Side::Server, key.clone()));
+                timers.insert((when, Side::Server, key));
+                tx.when = when;
+            }
+        }
+    }
+
+    //Timer A：INVITE重传间隔逐次翻倍；Timer E：非INVITE翻倍至T2，收到临时应答后固定为T2
+    fn backoff(invite: bool, state: TxState, interval: Duration) -> Duration {
+        match (invite, state) {
+            (true, _) => interval * 2,
+            (false, TxState::Proceeding) => T2,
+            (false, _) => (interval * 2).min(T2),
+        }
+    }
+
+    //事务超时由本端合成408应答
+    fn timeout_response(req: &Request) -> Option<Response> {
+        let mut headers: rsip::Headers = Default::default();
+        headers.push(req.via_header().ok()?.clone().into());
+        headers.push(req.from_header().ok()?.clone().into());
+        headers.push(req.to_header().ok()?.clone().into());
+        headers.push(req.call_id_header().ok()?.clone().into());
+        headers.push(req.cseq_header().ok()?.clone().into());
+        headers.push(rsip::headers::ContentLength::default().into());
+        Some(Response {
+            status_code: rsip::StatusCode::RequestTimeout,
+            version: rsip::common::version::Version::V2,
+            headers,
+            body: Default::default(),
+        })
+    }
+
+    //INVITE非2xx最终应答的ACK：与INVITE同branch、同Request-URI，To取自应答
+    fn ack_for(req: &Request, res: &Response) -> Option<Bytes> {
+        let mut headers: rsip::Headers = Default::default();
+        headers.push(req.via_header().ok()?.clone().into());
+        for header in req.headers.iter() {
+            if let Header::Route(route) = header {
+                headers.push(route.clone().into());
+            }
+        }
+        headers.push(req.from_header().ok()?.clone().into());
+        headers.push(res.to_header().ok()?.clone().into());
+        headers.push(req.call_id_header().ok()?.clone().into());
+        let seq = req.cseq_header().ok()?.seq().ok()?;
+        headers.push(rsip::headers::CSeq::new(format!("{seq} ACK")).into());
+        headers.push(rsip::headers::MaxForwards::new("70").into());
+        headers.push(rsip::headers::ContentLength::default().into());
+        let ack: SipMessage = Request {
+            method: Method::Ack,
+            uri: req.uri.clone(),
+            headers,
+            version: rsip::common::version::Version::V2,
+            body: Default::default(),
+        }
+        .into();
+        Some(Bytes::from(ack))
+    }
+
+    struct Shared {
+        state: Mutex<State>,
+        background_task: Notify,
+    }
+
+    impl Shared {
+        //处理到期定时器：重传请求或终止事务，并返回下一个定时刻度
+        fn fire_expired_timers(&self) -> (Option<Instant>, Vec<Response>) {
+            let mut guard = self.state.lock();
+            let state = &mut *guard;
+            let now = Instant::now();
+            let mut timeouts = Vec::new();
+            while let Some((when, side, key)) = state.timers.first().cloned() {
+                if when > now {
+                    return (Some(when), timeouts);
+                }
+                state.timers.remove(&(when, side, key.clone()));
+                match side {
+                    Side::Server => {
+                        if state.servers.get(&key).is_some_and(|tx| tx.when == when) {
+                            state.servers.remove(&key);
+                        }
+                    }
+                    Side::Client => {
+                        let Some(tx) = state.clients.get_mut(&key) else {
+                            continue;
+                        };
+                        if tx.when != when {
+                            continue;
+                        }
+                        if now >= tx.deadline {
+                            if tx.state != TxState::Completed {
+                                warn!("{:?},事务超时", key);
+                                timeouts.extend(timeout_response(&tx.request));
+                            }
+                            state.clients.remove(&key);
+                            continue;
+                        }
+                        if let (Some(at), Some(interval)) = (tx.retransmit_at, tx.interval) {
+                            if at <= now {
+                                debug!("{:?},重传请求", key);
+                                let _ = tx
+                                    .output
+                                    .try_send(Zip::build_data(Package::new(
+                                        tx.bill.clone(),
+                                        tx.data.clone(),
+                                    )))
+                                    .hand_log(|msg| warn!("{msg}"));
+                                let invite = tx.request.method == Method::Invite;
+                                let interval = backoff(invite, tx.state, interval);
+                                tx.interval = Some(interval);
+                                tx.retransmit_at = Some(now + interval);
+                            }
+                        }
+                        tx.when = tx.next_when();
+                        state.timers.insert((tx.when, Side::Client, key));
+                    }
+                }
+            }
+            (None, timeouts)
+        }
+    }
+
+    struct State {
+        clients: HashMap<TxKey, ClientTx>,
+        servers: HashMap<TxKey, ServerTx>,
+        //定时刻度，instant,side,key
+        timers: BTreeSet<(Instant, Side, TxKey)>,
+    }
+
+    impl State {
+        fn next_timer(&self) -> Option<Instant> {
+            self.timers.first().map(|timer| timer.0)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use common::tokio::sync::mpsc;
+        use std::net::SocketAddr;
+
+        fn invite(branch: &str) -> Request {
+            let raw = format!(
+                "INVITE sip:34020000001320000001@3402000000 SIP/2.0\r\n\
+                 Via: SIP/2.0/UDP 10.0.0.1:5060;rport;branch={branch}\r\n\
+                 Route: <sip:10.0.0.2:5060;lr>\r\n\
+                 From: <sip:34020000002000000001@3402000000>;tag=abc\r\n\
+                 To: <sip:34020000001320000001@3402000000>\r\n\
+                 Call-ID: 1234@10.0.0.1\r\n\
+                 CSeq: 7 INVITE\r\n\
+                 Max-Forwards: 70\r\n\
+                 Content-Length: 0\r\n\r\n"
+            );
+            Request::try_from(raw.as_bytes()).unwrap()
+        }
+
+        fn response(req: &Request, code: u16) -> Response {
+            let mut headers: rsip::Headers = Default::default();
+            headers.push(req.via_header().unwrap().clone().into());
+            headers.push(req.from_header().unwrap().clone().into());
+            headers.push(
+                rsip::headers::To::new("<sip:34020000001320000001@3402000000>;tag=dev").into(),
+            );
+            headers.push(req.call_id_header().unwrap().clone().into());
+            headers.push(req.cseq_header().unwrap().clone().into());
+            headers.push(rsip::headers::ContentLength::default().into());
+            Response {
+                status_code: code.into(),
+                version: rsip::common::version::Version::V2,
+                headers,
+                body: Default::default(),
+            }
+        }
+
+        fn bill() -> Association {
+            let local: SocketAddr = "10.0.0.2:5060".parse().unwrap();
+            let remote: SocketAddr = "10.0.0.1:5060".parse().unwrap();
+            Association::new(local, remote, Protocol::UDP)
+        }
+
+        #[test]
+        fn test_tx_key() {
+            let req = invite("z9hG4bK776asdhds");
+            let key = TxKey::build(&req).unwrap();
+            assert_eq!(key.branch, "z9hG4bK776asdhds");
+            assert_eq!(key.method, "INVITE");
+            assert_eq!(TxKey::build(&response(&req, 200)), Some(key));
+            let legacy = TxKey::build(&invite("1a2b3c")).unwrap();
+            assert_eq!(legacy.branch, "1234@10.0.0.1:7");
+        }
+
+        #[test]
+        fn test_backoff() {
+            let mut interval = T1;
+            let mut schedule = vec![];
+            for _ in 0..5 {
+                schedule.push(interval.as_millis());
+                interval = backoff(false, TxState::Trying, interval);
+            }
+            assert_eq!(schedule, vec![500, 1000, 2000, 4000, 4000]);
+            assert_eq!(backoff(false, TxState::Proceeding, T1), T2);
+            assert_eq!(backoff(true, TxState::Trying, T2 * 2), T2 * 4);
+        }
+
+        #[test]
+        fn test_ack_for_non_2xx() {
+            let req = invite("z9hG4bKack");
+            let data = ack_for(&req, &response(&req, 486)).unwrap();
+            let SipMessage::Request(ack) = SipMessage::try_from(data).unwrap() else {
+                panic!("not a request");
+            };
+            assert_eq!(ack.method, Method::Ack);
+            assert_eq!(ack.uri, req.uri);
+            assert_eq!(TxKey::build(&ack).unwrap().branch, "z9hG4bKack");
+            assert_eq!(ack.cseq_header().unwrap().value(), "7 ACK");
+            assert!(ack.to_header().unwrap().value().contains("tag=dev"));
+            assert!(ack.headers.iter().any(|h| matches!(h, Header::Route(_))));
+        }
+
+        #[test]
+        fn test_server_absorb_retransmission() {
+            let (tx, mut rx) = mpsc::channel(10);
+            let req = invite("z9hG4bKserver");
+            assert!(Transaction::server_received(&req, &tx, &bill()));
+            //处理中的重传被吸收且不回复
+            assert!(!Transaction::server_received(&req, &tx, &bill()));
+            assert!(rx.try_recv().is_err());
+            let res = Bytes::from(SipMessage::from(response(&req, 486)));
+            Transaction::server_respond(&res);
+            //最终应答后的重传重放缓存应答
+            assert!(!Transaction::server_received(&req, &tx, &bill()));
+            match rx.try_recv().unwrap() {
+                Zip::Data(pkg) => assert_eq!(pkg.get_data(), &res),
+                _ => panic!("not data"),
+            }
+            //非2xx的ACK由事务层吸收
+            let ack = ack_for(&req, &response(&req, 486)).unwrap();
+            let SipMessage::Request(ack) = SipMessage::try_from(ack).unwrap() else {
+                panic!("not a request");
+            };
+            assert!(!Transaction::server_received(&ack, &tx, &bill()));
+            assert!(Transaction::server_received(&req, &tx, &bill()));
+        }
+
+        #[test]
+        fn test_timeout_response() {
+            let req = invite("z9hG4bKtimeout");
+            let res = timeout_response(&req).unwrap();
+            assert_eq!(res.status_code.code(), 408);
+            assert_eq!(res.cseq_header().unwrap().value(), "7 INVITE");
+            assert_eq!(res.call_id_header().unwrap().value(), "1234@10.0.0.1");
+            assert_eq!(TxKey::build(&res), TxKey::build(&req));
+            assert_eq!(
+                parser::header::get_device_id_by_response(&res).unwrap(),
+                "34020000001320000001"
+            );
+        }
+
+        #[test]
+        fn test_client_absorb_final_retransmission() {
+            let (tx, mut rx) = mpsc::channel(10);
+            let req = invite("z9hG4bKclient");
+            Transaction::client_send(&SipMessage::from(req.clone()), &tx, &bill());
+            assert!(Transaction::client_received(&response(&req, 100)));
+            assert!(Transaction::client_received(&response(&req, 486)));
+            //事务层回复ACK
+            assert!(matches!(rx.try_recv().unwrap(), Zip::Data(_)));
+            //最终应答重传被吸收并重发ACK
+            assert!(!Transaction::client_received(&response(&req, 486)));
+            assert!(matches!(rx.try_recv().unwrap(), Zip::Data(_)));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::hash_map::Entry;
@@ -1,4 +1,6 @@
+use crate::gb::handler::dialog::Dialog;
 use crate::general;
+use crate::general::model::StreamMode;
 use common::bytes::Bytes;
 use common::dashmap::mapref::entry::Entry;
 use common::dashmap::{DashMap, DashSet};
@@ -88,10 +90,8 @@ impl Cache {
         stream_id: String,
         stream_node_name: String,
         call_id: String,
-        seq: u32,
         play_type: PlayType,
-        from_tag: String,
-        to_tag: String,
+        stream_mode: StreamMode,
     ) -> bool {
         match GENERAL_CACHE.shared.stream_map.entry(stream_id) {
             Entry::Occupied(_) => false,
@@ -100,10 +100,8 @@ impl Cache {
                     gbs_token_sets: HashSet::new(),
                     stream_node_name,
                     call_id,
-                    seq,
                     play_type,
-                    from_tag,
-                    to_tag,
+                    stream_mode,
                 };
                 vac.insert(stream_table);
                 true
@@ -147,25 +145,38 @@ impl Cache {
         }
     }
 
+    //流所属对话的下一个对话内请求：call_id,seq,from_tag,to_tag
     pub fn stream_map_build_call_id_seq_from_to_tag(
         stream_id: &String,
     ) -> Option<(String, u32, String, String)> {
+        let call_id = Self::stream_map_query_call_id_by_stream_id(stream_id)?;
+        Dialog::next_request(&call_id)
+            .map(|(seq, from_tag, to_tag)| (call_id, seq, from_tag, to_tag))
+    }
+
+    pub fn stream_map_query_call_id_by_stream_id(stream_id: &String) -> Option<String> {
         GENERAL_CACHE
             .shared
             .stream_map
-            .get_mut(stream_id)
-            .map(|mut ref_mut| {
-                // let (_tokens, _node_name, call_id, seq, _play_type, from_tag, to_tag) = ref_mut.value_mut();
-                let stream_table = ref_mut.value_mut();
-                let seq = &mut stream_table.seq;
-                *seq += 1;
-                (
-                    stream_table.call_id.clone(),
-                    *seq,
-                    stream_table.from_tag.clone(),
-                    stream_table.to_tag.clone(),
-                )
-            })
+            .get(stream_id)
+            .map(|res| res.value().call_id.clone())
+    }
+
+    pub fn stream_map_query_stream_id_by_call_id(call_id: &String) -> Option<String> {
+        GENERAL_CACHE
+            .shared
+            .stream_map
+            .iter()
+            .find(|item| item.value().call_id.eq(call_id))
+            .map(|item| item.key().clone())
+    }
+
+    pub fn stream_map_query_stream_mode_by_stream_id(stream_id: &String) -> Option<StreamMode> {
+        GENERAL_CACHE
+            .shared
+            .stream_map
+            .get(stream_id)
+            .map(|res| res.value().stream_mode)
     }
 
     pub fn stream_map_query_play_type_by_stream_id(stream_id: &String) -> Option<PlayType> {
@@ -227,9 +238,9 @@ impl Cache {
                         s_vec.retain(|device_table| match channel_ssrc {
                             None => !device_table.channel_id.eq(channel_id),
                             Some((play_type, ssrc)) => {
-                                !device_table.channel_id.eq(channel_id)
-                                    && !device_table.play_type.eq(&play_type)
-                                    && !device_table.ssrc.eq(ssrc)
+                                !(device_table.channel_id.eq(channel_id)
+                                    && device_table.play_type.eq(&play_type)
+                                    && device_table.ssrc.eq(ssrc))
                             }
                         });
                         // 如果vec empty，则删除device_id
@@ -279,13 +290,26 @@ impl Cache {
             callback_sender: call_tx,
         };
 
-        match expire {
-            None => {
-                guard.entities.insert(key, entity);
-            }
-            Some(ins) => {
-                guard.entities.insert(key.clone(), entity);
-                guard.expirations.insert((ins, key));
+        //覆盖旧值时移除其过期时间,避免新值被提前清理
+        if let Some(StateEntity {
+            expiration_time: Some(old),
+            ..
+        }) = guard.entities.insert(key.clone(), entity)
+        {
+            guard.expirations.remove(&(old, key.clone()));
+        }
+        if let Some(ins) = expire {
+            //新过期时间早于当前最近到期时间时,唤醒清理任务重新计时
+            let notify = guard
+                .expirations
+                .iter()
+                .next()
+                .map(|(when, _)| *when > ins)
+                .unwrap_or(true);
+            guard.expirations.insert((ins, key));
+            drop(guard);
+            if notify {
+                GENERAL_CACHE.shared.background_task.notify_one();
             }
         }
     }
@@ -436,11 +460,10 @@ impl State {
 struct StreamTable {
     gbs_token_sets: HashSet<String>,
     stream_node_name: String,
+    //对话状态(seq,tag,路由)由gb::handler::dialog维护
     call_id: String,
-    seq: u32,
     play_type: PlayType,
-    from_tag: String,
-    to_tag: String,
+    stream_mode: StreamMode,
 }
 
 struct DeviceTable {
@@ -460,7 +483,7 @@ struct Shared {
     background_task: Notify,
     //存放原始可用的ssrc序号
     ssrc_sn: DashSet<u16>,
-    //stream_id:(set<gbs_token>,stream_node_name,call_id,seq,PlayType,from_tag,to_tag)
+    //stream_id:(set<gbs_token>,stream_node_name,call_id,PlayType,StreamMode)
     // stream_map: DashMap<String, (Option<HashSet<String>>, String, String, u32, PlayType, String, String)>,
     stream_map: DashMap<String, StreamTable>,
     //device_id:HashMap<channel_id,HashMap<playType,BiMap<stream_id,ssrc>>
@@ -498,17 +521,38 @@ impl Shared {
 pub enum PlayType {
     Live,
     Back,
-    // Down,
+    Down,
 }
 
-impl PlayType {}
+impl PlayType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayType::Live => "live",
+            PlayType::Back => "back",
+            PlayType::Down => "down",
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::general::cache::{Cache, PlayType, StreamTable, GENERAL_CACHE};
+    use crate::general::model::StreamMode;
     use common::dashmap::{DashMap, DashSet};
     use common::rand;
     use common::rand::prelude::IteratorRandom;
+    use std::time::Duration;
+
+    #[test]
+    fn test_state_expire() {
+        let key = "KEY_TEST_EXPIRE".to_string();
+        Cache::state_insert_obj_by_timer(key.clone(), &1u8, Duration::from_secs(3600), None);
+        //覆盖为更早过期,清理任务被唤醒后按新时间清理
+        Cache::state_insert_obj_by_timer(key.clone(), &2u8, Duration::from_millis(50), None);
+        assert!(Cache::state_get(&key).is_some());
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(Cache::state_get(&key).is_none());
+    }
 
     #[test]
     fn test_ref_mut() {
@@ -516,19 +560,20 @@ mod tests {
             gbs_token_sets: Default::default(),
             stream_node_name: "".to_string(),
             call_id: "".to_string(),
-            seq: 0,
             play_type: PlayType::Live,
-            from_tag: "".to_string(),
-            to_tag: "".to_string(),
+            stream_mode: StreamMode::Udp,
         };
         let map = DashMap::new();
         map.insert(1, table);
         if let Some(mut ref_mut) = map.get_mut(&1) {
             let stream_table = ref_mut.value_mut();
-            let seq = &mut stream_table.seq;
-            *seq += 1;
+            stream_table.gbs_token_sets.insert("token".to_string());
         }
-        println!("{:?}", map.get_mut(&1).map(|item| item.value().seq));
+        println!(
+            "{:?}",
+            map.get_mut(&1)
+                .map(|item| item.value().gbs_token_sets.len())
+        );
     }
 
     #[test]
@@ -19,31 +19,80 @@ pub mod schedule;
 #[conf(prefix = "server.alarm", check)]
 pub struct AlarmConf {
     pub enable: bool,
+    //告警推送地址,不配置则只入库不推送
     pub push_url: Option<String>,
     #[serde(default = "default_priority")]
     pub priority: u8,
+    //去重窗口(秒)：同一通道、报警方式与类型在窗口内只受理一次,0不去重
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: u64,
+    //推送失败重试次数,间隔按2、4、8...秒退避
+    #[serde(default = "default_push_retry")]
+    pub push_retry: u8,
+    //告警联动规则：按报警方式与类型匹配,取第一条
+    #[serde(default)]
+    pub linkage: Vec<AlarmLinkage>,
 }
+
+#[derive(Debug, Get, Deserialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmLinkage {
+    method: u8,
+    //为空匹配该方式下全部报警类型
+    #[serde(default)]
+    alarm_types: Vec<u8>,
+    //抓拍张数,0不抓拍
+    #[serde(default)]
+    snap_num: u8,
+    #[serde(default = "default_snap_interval")]
+    snap_interval: u8,
+    //录像片段：告警前后秒数,均为0不下载
+    #[serde(default)]
+    clip_pre: u32,
+    #[serde(default)]
+    clip_post: u32,
+    //录像片段取流方式：0 UDP 1 TCP被动 2 TCP主动
+    #[serde(default)]
+    clip_mode: u8,
+}
+serde_default!(default_snap_interval, u8, 1);
 serde_default!(default_priority, u8, 4);
+serde_default!(default_dedup_window, u64, 30);
+serde_default!(default_push_retry, u8, 3);
 static ALARM_CONF: OnceLock<AlarmConf> = OnceLock::new();
 
 impl AlarmConf {
     pub fn get_alarm_conf() -> &'static Self {
         ALARM_CONF.get_or_init(AlarmConf::conf)
     }
+
+    pub fn linkage(&self, method: u8, alarm_type: u8) -> Option<&AlarmLinkage> {
+        self.linkage.iter().find(|rule| {
+            rule.method == method
+                && (rule.alarm_types.is_empty() || rule.alarm_types.contains(&alarm_type))
+        })
+    }
 }
 
 impl CheckFromConf for AlarmConf {
     fn _field_check(&self) -> Result<(), FieldCheckError> {
-        if self.enable {
-            if self.push_url.is_none() || self.push_url.as_ref().unwrap().is_empty() {
+        if let Some(push_url) = self.push_url.as_ref().filter(|url| !url.is_empty()) {
+            if Url::parse(push_url).is_err() {
                 return Err(FieldCheckError::BizError(
-                    "server.alarm.push_url不能为空".to_string(),
+                    "server.alarm.push_url非有效的url地址".to_string(),
                 ));
             }
+        }
 
-            if Url::parse(self.push_url.as_ref().unwrap()).is_err() {
+        for rule in &self.linkage {
+            if rule.snap_num > 10 || rule.snap_interval == 0 {
                 return Err(FieldCheckError::BizError(
-                    "server.alarm.push_url非有效的url地址".to_string(),
+                    "server.alarm.linkage.snap_num最大10,snap_interval不能为0".to_string(),
+                ));
+            }
+            if rule.clip_mode > 2 {
+                return Err(FieldCheckError::BizError(
+                    "server.alarm.linkage.clip_mode必须为0|1|2".to_string(),
                 ));
             }
         }
@@ -63,6 +112,8 @@ impl CheckFromConf for AlarmConf {
 pub struct StreamConf {
     proxy_enable: bool,
     proxy_addr: Option<String>,
+    //流事件推送地址,不配置则不推送
+    event_url: Option<String>,
     #[serde(default = "default_node_map")]
     node_map: HashMap<String, StreamNode>,
     nodes: Vec<StreamNode>,
@@ -129,4 +180,34 @@ mod tests {
         print_banner('M');
         print_banner('V');
     }
+
+    #[test]
+    fn test_alarm_linkage() {
+        let rule = |method: u8, alarm_types: Vec<u8>, snap_num: u8| super::AlarmLinkage {
+            method,
+            alarm_types,
+            snap_num,
+            snap_interval: 1,
+            clip_pre: 10,
+            clip_post: 20,
+            clip_mode: 0,
+        };
+        let conf = super::AlarmConf {
+            enable: true,
+            push_url: None,
+            priority: 4,
+            dedup_window: 30,
+            push_retry: 3,
+            linkage: vec![
+                rule(5, vec![2, 3], 3),
+                rule(5, vec![], 1),
+                rule(2, vec![1], 1),
+            ],
+        };
+        assert_eq!(*conf.linkage(5, 3).unwrap().get_snap_num(), 3);
+        assert_eq!(*conf.linkage(5, 9).unwrap().get_snap_num(), 1);
+        assert!(conf.linkage(2, 1).is_some());
+        assert!(conf.linkage(2, 2).is_none());
+        assert!(conf.linkage(1, 1).is_none());
+    }
 }
@@ -1,9 +1,10 @@
-use crate::gb::handler::parser::xml::KV2Model;
+use crate::gb::handler::manscdp;
 use crate::general;
 use common::anyhow::anyhow;
+use common::chrono::{Local, NaiveDateTime, TimeZone};
 use common::constructor::{Get, New, Set};
 use common::exception::GlobalError::SysErr;
-use common::exception::{GlobalResult, TransError};
+use common::exception::{GlobalError, GlobalResult, TransError};
 use common::log::error;
 use common::serde::{Deserialize, Serialize};
 use poem_openapi::types::{ParseFromJSON, ToJSON, Type};
@@ -34,8 +35,29 @@ impl TimeRange {
             end_time,
         }
     }
+
+    //时间戳转国标时间格式：2024-01-01T00:00:00
+    pub fn to_gb_time(ts: u32) -> String {
+        Local
+            .timestamp_opt(ts as i64, 0)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%S").to_string())
+            .unwrap_or_default()
+    }
+
+    //国标时间格式转时间戳,兼容部分设备使用空格分隔日期与时间
+    pub fn parse_gb_time(time: &str) -> Option<u32> {
+        let time = time.trim();
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S"))
+            .ok()
+            .and_then(|ndt| Local.from_local_datetime(&ndt).single())
+            .map(|dt| dt.timestamp() as u32)
+    }
 }
 
+//媒体传输方式(平台视角)：0 UDP；1 TCP被动,设备主动连接平台；2 TCP主动,平台主动连接设备
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum StreamMode {
     Udp,
     TcpActive,
@@ -46,11 +68,63 @@ impl StreamMode {
     pub fn build(m: u8) -> GlobalResult<Self> {
         match m {
             0 => Ok(StreamMode::Udp),
-            1 => Ok(StreamMode::TcpActive),
-            2 => Ok(StreamMode::TcpPassive),
+            1 => Ok(StreamMode::TcpPassive),
+            2 => Ok(StreamMode::TcpActive),
             _ => Err(SysErr(anyhow!("无效流模式"))),
         }
     }
+
+    //校验设备应答SDP的传输方式与请求是否一致；设备未声明setup时按协议判断
+    pub fn check_answer(&self, sdp: &[u8]) -> GlobalResult<()> {
+        let session = sdp_types::Session::parse(sdp)
+            .map_err(|err| SysErr(anyhow!("应答SDP解析失败:{err}")))?;
+        let media = Self::answer_media(&session)?;
+        let tcp = media.proto.to_uppercase().starts_with("TCP");
+        let setup = media
+            .attributes
+            .iter()
+            .find(|attr| attr.attribute.eq("setup"))
+            .and_then(|attr| attr.value.as_deref())
+            .map(|val| val.trim().to_lowercase());
+        let matched = match self {
+            StreamMode::Udp => !tcp,
+            StreamMode::TcpPassive => tcp && setup.as_deref().is_none_or(|s| s == "active"),
+            StreamMode::TcpActive => tcp && setup.as_deref().is_none_or(|s| s == "passive"),
+        };
+        if matched {
+            Ok(())
+        } else {
+            Err(SysErr(anyhow!(
+                "设备应答传输方式不一致:{:?} => {} setup:{:?}",
+                self,
+                media.proto,
+                setup
+            )))
+        }
+    }
+
+    //TCP主动模式下设备应答的监听地址,媒体级c=优先于会话级
+    pub fn answer_address(sdp: &[u8]) -> GlobalResult<(String, u16)> {
+        let session = sdp_types::Session::parse(sdp)
+            .map_err(|err| SysErr(anyhow!("应答SDP解析失败:{err}")))?;
+        let media = Self::answer_media(&session)?;
+        let ip = media
+            .connections
+            .first()
+            .or(session.connection.as_ref())
+            .map(|conn| conn.connection_address.clone())
+            .ok_or_else(|| SysErr(anyhow!("应答SDP缺少连接地址")))?;
+        Ok((ip, media.port))
+    }
+
+    fn answer_media(session: &sdp_types::Session) -> GlobalResult<&sdp_types::Media> {
+        session
+            .medias
+            .iter()
+            .find(|media| media.media.eq_ignore_ascii_case("video"))
+            .or_else(|| session.medias.first())
+            .ok_or_else(|| SysErr(anyhow!("应答SDP缺少媒体描述")))
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Object)]
@@ -124,6 +198,497 @@ pub struct PlayBackModel {
     et: u32,
 }
 
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PlayDownModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    #[oai(validator(maximum(value = "2"), minimum(value = "0")))]
+    trans_mode: Option<u8>,
+    st: u32,
+    et: u32,
+    #[oai(validator(maximum(value = "4"), minimum(value = "1")))]
+    ///下载倍速：1、2、4；默认4
+    speed: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Clone)]
+#[serde(crate = "common::serde")]
+pub struct DownloadInfo {
+    pub stream_id: String,
+    pub device_id: String,
+    pub channel_id: String,
+    pub st: u32,
+    pub et: u32,
+    pub speed: u8,
+    ///开始下载时间戳
+    pub start_ts: i64,
+    ///结束下载时间戳
+    pub end_ts: Option<i64>,
+    ///下载状态：0 下载中 1 已完成 2 已终止
+    pub state: u8,
+    ///下载进度：0-100,下载中按录像时长与倍速估算
+    pub progress: u8,
+}
+
+impl DownloadInfo {
+    pub const DOWNLOADING: u8 = 0;
+    pub const FINISHED: u8 = 1;
+    pub const TERMINATED: u8 = 2;
+
+    pub fn estimate_progress(&self, now: i64) -> u8 {
+        if self.state == Self::FINISHED {
+            return 100;
+        }
+        let total = self.et.saturating_sub(self.st).max(1) as i64;
+        let elapsed = (now - self.start_ts).max(0) * self.speed.max(1) as i64;
+        (elapsed * 100 / total).min(99) as u8
+    }
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct RecordQueryModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    st: u32,
+    et: u32,
+    ///录像类型：all、time、alarm、manual；默认all
+    record_type: Option<String>,
+    #[oai(validator(maximum(value = "1"), minimum(value = "0")))]
+    ///保密属性：0 不涉密 1 涉密；默认0
+    secrecy: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct RecordItem {
+    pub device_id: String,
+    pub name: String,
+    pub file_path: Option<String>,
+    pub address: Option<String>,
+    pub st: u32,
+    pub et: u32,
+    pub secrecy: u8,
+    pub record_type: Option<String>,
+    pub recorder_id: Option<String>,
+    pub file_size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct AlarmStatusItem {
+    pub device_id: String,
+    ///ONDUTY 布防；OFFDUTY 撤防；ALARM 报警
+    pub duty_status: String,
+}
+
+//设备状态查询应答
+#[derive(Debug, Deserialize, Object, Serialize, Default)]
+#[serde(crate = "common::serde")]
+pub struct DeviceStatusInfo {
+    pub device_id: String,
+    ///查询结果：OK、ERROR
+    pub result: Option<String>,
+    pub online: bool,
+    ///设备工作状态：OK、ERROR
+    pub status: Option<String>,
+    ///状态异常原因
+    pub reason: Option<String>,
+    ///是否编码
+    pub encode: Option<bool>,
+    ///是否录像
+    pub record: Option<bool>,
+    pub device_time: Option<String>,
+    pub alarm_status: Vec<AlarmStatusItem>,
+}
+
+impl From<manscdp::DeviceStatusResponse> for DeviceStatusInfo {
+    fn from(res: manscdp::DeviceStatusResponse) -> Self {
+        let on_off = |v: Option<String>| {
+            v.and_then(|v| match &v.trim().to_ascii_uppercase()[..] {
+                "ON" => Some(true),
+                "OFF" => Some(false),
+                _ => None,
+            })
+        };
+        DeviceStatusInfo {
+            online: res.online.trim().eq_ignore_ascii_case("ONLINE"),
+            device_id: res.device_id,
+            result: res.result,
+            status: res.status,
+            reason: res.reason,
+            encode: on_off(res.encode),
+            record: on_off(res.record),
+            device_time: res.device_time,
+            alarm_status: res
+                .alarm_status
+                .items
+                .into_iter()
+                .filter(|item| !item.device_id.is_empty())
+                .map(|item| AlarmStatusItem {
+                    device_id: item.device_id,
+                    duty_status: item.duty_status,
+                })
+                .collect(),
+        }
+    }
+}
+
+//订阅对话：刷新与取消订阅时复用call_id、tag及订阅消息体
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(crate = "common::serde")]
+pub struct SubscribeDialog {
+    //订阅类型：Catalog、Alarm、MobilePosition
+    pub kind: String,
+    //Event头域事件名
+    pub event: String,
+    pub event_id: u32,
+    pub call_id: String,
+    pub from_tag: String,
+    pub to_tag: Option<String>,
+    pub seq: u32,
+    pub expires: u32,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct AlarmSubscribeModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(maximum(value = "4"), minimum(value = "0")))]
+    ///报警起始级别：0 全部；1 一级警情；2 二级警情；3 三级警情；4 四级警情
+    start_priority: Option<u8>,
+    #[oai(validator(maximum(value = "4"), minimum(value = "0")))]
+    ///报警终止级别,取值同上
+    end_priority: Option<u8>,
+    #[oai(validator(maximum(value = "7"), minimum(value = "0")))]
+    ///报警方式：0 全部；1 电话；2 设备；3 短信；4 GPS；5 视频；6 设备故障；7 其他
+    alarm_method: Option<u8>,
+    ///报警发生开始时间(时间戳)
+    start_time: Option<u32>,
+    ///报警发生结束时间(时间戳)
+    end_time: Option<u32>,
+    #[oai(validator(maximum(value = "86400"), minimum(value = "60")))]
+    ///订阅有效期(秒),默认取配置
+    expires: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct MobilePositionSubscribeModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(maximum(value = "3600"), minimum(value = "1")))]
+    ///位置上报间隔(秒),默认5
+    interval: Option<u32>,
+    #[oai(validator(maximum(value = "86400"), minimum(value = "60")))]
+    ///订阅有效期(秒),默认取配置
+    expires: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct SubscribeCancelModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    ///订阅类型：Catalog、Alarm、MobilePosition
+    kind: String,
+}
+
+//移动设备位置信息
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct MobilePosition {
+    pub device_id: String,
+    ///上报位置的目标编码
+    pub channel_id: String,
+    ///位置时间(时间戳)
+    pub time: u32,
+    pub longitude: f64,
+    pub latitude: f64,
+    ///速度(km/h)
+    pub speed: Option<f64>,
+    ///方向,正北为0,顺时针(度)
+    pub direction: Option<f64>,
+    ///海拔(m)
+    pub altitude: Option<f64>,
+}
+
+impl TryFrom<manscdp::MobilePositionNotify> for MobilePosition {
+    type Error = GlobalError;
+
+    fn try_from(notify: manscdp::MobilePositionNotify) -> GlobalResult<Self> {
+        let (Some(longitude), Some(latitude)) = (notify.longitude, notify.latitude) else {
+            return Err(SysErr(anyhow!(
+                "移动位置通知缺少经纬度: channel_id = {}",
+                notify.device_id
+            )));
+        };
+        let time = TimeRange::parse_gb_time(&notify.time)
+            .unwrap_or_else(|| Local::now().timestamp() as u32);
+        Ok(MobilePosition {
+            device_id: String::new(),
+            channel_id: notify.device_id,
+            time,
+            longitude,
+            latitude,
+            speed: notify.speed,
+            direction: notify.direction,
+            altitude: notify.altitude,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PositionQueryModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    ///开始时间(时间戳)
+    st: u32,
+    ///结束时间(时间戳)
+    et: u32,
+    #[oai(validator(maximum(value = "10000"), minimum(value = "1")))]
+    ///返回条数上限,默认1000
+    limit: Option<u32>,
+}
+
+//ConfigDownload支持的配置类型
+pub const CONFIG_TYPES: [&str; 5] = [
+    "BasicParam",
+    "VideoParamOpt",
+    "SVACEncodeConfig",
+    "SVACDecodeConfig",
+    "AlarmReport",
+];
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct ConfigQueryModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    ///配置类型：BasicParam、VideoParamOpt、SVACEncodeConfig、SVACDecodeConfig、AlarmReport
+    config_types: Vec<String>,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    ///等待设备应答秒数,默认8
+    timeout: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct BasicParam {
+    pub name: Option<String>,
+    pub device_id: Option<String>,
+    pub sip_server_id: Option<String>,
+    pub sip_server_ip: Option<String>,
+    pub sip_server_port: Option<u16>,
+    pub domain_name: Option<String>,
+    ///注册过期时间(秒)
+    pub expiration: Option<u32>,
+    pub password: Option<String>,
+    ///心跳间隔(秒)
+    pub heart_beat_interval: Option<u32>,
+    ///心跳超时次数
+    pub heart_beat_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct VideoParamOpt {
+    ///可选下载倍速,如1/2/4
+    pub download_speed: Option<String>,
+    ///可选分辨率
+    pub resolution: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct SvacEncodeConfig {
+    pub roi_flag: Option<u8>,
+    pub roi_number: Option<u8>,
+    pub svc_space_domain_mode: Option<u8>,
+    pub svc_time_domain_mode: Option<u8>,
+    pub time_flag: Option<u8>,
+    pub event_flag: Option<u8>,
+    pub alert_flag: Option<u8>,
+    pub audio_recognition_flag: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct SvacDecodeConfig {
+    pub svc_space_support_mode: Option<u8>,
+    pub svc_time_support_mode: Option<u8>,
+    pub time_show_flag: Option<u8>,
+    pub event_show_flag: Option<u8>,
+    pub alert_show_flag: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct AlarmReport {
+    ///移动侦测事件上报：0 关闭；1 打开
+    pub motion_detection: Option<u8>,
+    ///区域入侵事件上报：0 关闭；1 打开
+    pub field_detection: Option<u8>,
+}
+
+//设备配置查询应答
+#[derive(Debug, Deserialize, Object, Serialize, Default)]
+#[serde(crate = "common::serde")]
+pub struct ConfigDownloadInfo {
+    pub device_id: String,
+    pub result: Option<String>,
+    pub basic_param: Option<BasicParam>,
+    pub video_param_opt: Option<VideoParamOpt>,
+    pub svac_encode_config: Option<SvacEncodeConfig>,
+    pub svac_decode_config: Option<SvacDecodeConfig>,
+    pub alarm_report: Option<AlarmReport>,
+}
+
+impl ConfigDownloadInfo {
+    pub fn contains(&self, config_type: &str) -> bool {
+        match config_type {
+            "BasicParam" => self.basic_param.is_some(),
+            "VideoParamOpt" => self.video_param_opt.is_some(),
+            "SVACEncodeConfig" => self.svac_encode_config.is_some(),
+            "SVACDecodeConfig" => self.svac_decode_config.is_some(),
+            "AlarmReport" => self.alarm_report.is_some(),
+            _ => false,
+        }
+    }
+
+    pub fn merge(&mut self, other: ConfigDownloadInfo) {
+        self.result = other.result.or(self.result.take());
+        self.basic_param = other.basic_param.or(self.basic_param.take());
+        self.video_param_opt = other.video_param_opt.or(self.video_param_opt.take());
+        self.svac_encode_config = other.svac_encode_config.or(self.svac_encode_config.take());
+        self.svac_decode_config = other.svac_decode_config.or(self.svac_decode_config.take());
+        self.alarm_report = other.alarm_report.or(self.alarm_report.take());
+    }
+}
+
+impl From<manscdp::ConfigDownloadResponse> for ConfigDownloadInfo {
+    fn from(res: manscdp::ConfigDownloadResponse) -> Self {
+        ConfigDownloadInfo {
+            device_id: res.device_id,
+            result: res.result,
+            basic_param: res.basic_param.map(|p| BasicParam {
+                name: p.name,
+                device_id: p.device_id,
+                sip_server_id: p.sip_server_id,
+                sip_server_ip: p.sip_server_ip,
+                sip_server_port: p.sip_server_port,
+                domain_name: p.domain_name,
+                expiration: p.expiration,
+                password: p.password,
+                heart_beat_interval: p.heart_beat_interval,
+                heart_beat_count: p.heart_beat_count,
+            }),
+            video_param_opt: res.video_param_opt.map(|p| VideoParamOpt {
+                download_speed: p.download_speed,
+                resolution: p.resolution,
+            }),
+            svac_encode_config: res.svac_encode_config.map(|p| {
+                let roi = p.roi_param.unwrap_or_default();
+                let svc = p.svc_param.unwrap_or_default();
+                let surveillance = p.surveillance_param.unwrap_or_default();
+                SvacEncodeConfig {
+                    roi_flag: roi.roi_flag,
+                    roi_number: roi.roi_number,
+                    svc_space_domain_mode: svc.svc_space_domain_mode,
+                    svc_time_domain_mode: svc.svc_time_domain_mode,
+                    time_flag: surveillance.time_flag,
+                    event_flag: surveillance.event_flag,
+                    alert_flag: surveillance.alert_flag,
+                    audio_recognition_flag: p.audio_param.and_then(|a| a.audio_recognition_flag),
+                }
+            }),
+            svac_decode_config: res.svac_decode_config.map(|p| {
+                let svc = p.svc_param.unwrap_or_default();
+                let surveillance = p.surveillance_param.unwrap_or_default();
+                SvacDecodeConfig {
+                    svc_space_support_mode: svc.svc_space_support_mode,
+                    svc_time_support_mode: svc.svc_time_support_mode,
+                    time_show_flag: surveillance.time_show_flag,
+                    event_show_flag: surveillance.event_show_flag,
+                    alert_show_flag: surveillance.alert_show_flag,
+                }
+            }),
+            alarm_report: res.alarm_report.map(|p| AlarmReport {
+                motion_detection: p.motion_detection,
+                field_detection: p.field_detection,
+            }),
+        }
+    }
+}
+
+//设备配置下发,仅下发非空项
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct DeviceConfigModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    ///配置目标,为空时配置设备本身
+    channel_id: Option<String>,
+    #[oai(validator(max_length = "64"))]
+    name: Option<String>,
+    #[oai(validator(maximum(value = "86400"), minimum(value = "30")))]
+    ///注册过期时间(秒)
+    expiration: Option<u32>,
+    #[oai(validator(maximum(value = "255"), minimum(value = "5")))]
+    ///心跳间隔(秒)
+    heart_beat_interval: Option<u32>,
+    #[oai(validator(maximum(value = "255"), minimum(value = "1")))]
+    ///心跳超时次数
+    heart_beat_count: Option<u32>,
+    alarm_report: Option<AlarmReport>,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    ///等待设备应答秒数,默认8
+    timeout: Option<u8>,
+}
+
+//设备录像查询应答：按SumNum分包上报
+#[derive(Debug, Default)]
+pub struct RecordInfo {
+    pub sum_num: u32,
+    pub items: Vec<RecordItem>,
+}
+
+impl From<manscdp::RecordInfoResponse> for RecordInfo {
+    fn from(res: manscdp::RecordInfoResponse) -> Self {
+        RecordInfo {
+            sum_num: res.sum_num.unwrap_or_default(),
+            items: res
+                .record_list
+                .items
+                .into_iter()
+                .map(|item| RecordItem {
+                    st: TimeRange::parse_gb_time(&item.start_time).unwrap_or_default(),
+                    et: TimeRange::parse_gb_time(&item.end_time).unwrap_or_default(),
+                    device_id: item.device_id,
+                    name: item.name,
+                    file_path: item.file_path,
+                    address: item.address,
+                    secrecy: item.secrecy.unwrap_or_default(),
+                    record_type: item.record_type,
+                    recorder_id: item.recorder_id,
+                    file_size: item.file_size,
+                })
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Object, Serialize, Get)]
 #[serde(crate = "common::serde")]
 #[allow(non_snake_case)]
@@ -134,6 +699,96 @@ pub struct PlaySeekModel {
     seek_second: u32,
 }
 
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PlayStreamModel {
+    #[oai(validator(min_length = "24", max_length = "32"))]
+    stream_id: String,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct BroadcastModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    ///音频输出通道,为空时使用设备编码
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Clone, Default)]
+#[serde(crate = "common::serde")]
+pub struct BroadcastInfo {
+    pub device_id: String,
+    pub channel_id: String,
+    ///平台音频源编码
+    pub source_id: String,
+    ///发送音频的流媒体节点
+    pub node_name: String,
+    pub node_ip: String,
+    pub node_port: u16,
+    ///设备音频接收地址
+    pub device_ip: String,
+    pub device_port: u16,
+    ///传输方式(平台视角)：0 UDP；1 TCP被动；2 TCP主动
+    pub trans_mode: u8,
+    ///音频载荷类型：8 PCMA；0 PCMU
+    pub payload: u8,
+    pub ssrc: String,
+    pub call_id: String,
+    pub local_tag: String,
+    pub remote_tag: String,
+    pub seq: u32,
+}
+
+impl BroadcastInfo {
+    //解析设备INVITE携带的音频offer,确定接收地址/传输方式/载荷
+    pub fn apply_offer(&mut self, sdp: &[u8]) -> GlobalResult<()> {
+        let session = sdp_types::Session::parse(sdp)
+            .map_err(|err| SysErr(anyhow!("广播SDP解析失败:{err}")))?;
+        let media = session
+            .medias
+            .iter()
+            .find(|media| media.media.eq_ignore_ascii_case("audio"))
+            .ok_or_else(|| SysErr(anyhow!("广播SDP缺少音频描述")))?;
+        let connection = media
+            .connections
+            .first()
+            .or(session.connection.as_ref())
+            .ok_or_else(|| SysErr(anyhow!("广播SDP缺少连接地址")))?;
+        let payload = media
+            .fmt
+            .split_whitespace()
+            .filter_map(|fmt| fmt.parse::<u8>().ok())
+            .find(|pt| *pt == 8 || *pt == 0)
+            .ok_or_else(|| SysErr(anyhow!("不支持的音频格式:{}", media.fmt)))?;
+        let setup = media
+            .attributes
+            .iter()
+            .find(|attr| attr.attribute.eq("setup"))
+            .and_then(|attr| attr.value.as_deref())
+            .map(|val| val.trim().to_lowercase());
+        self.trans_mode = if !media.proto.to_uppercase().starts_with("TCP") {
+            0
+        } else if setup.as_deref() == Some("passive") {
+            2
+        } else {
+            1
+        };
+        self.device_ip = connection.connection_address.clone();
+        self.device_port = media.port;
+        self.payload = payload;
+        //y字段sdp_types不解析,需单独读取
+        if let Some(ssrc) = String::from_utf8_lossy(sdp)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("y=").map(|v| v.trim().to_string()))
+        {
+            self.ssrc = ssrc;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Object, Serialize, Get)]
 #[serde(crate = "common::serde")]
 #[allow(non_snake_case)]
@@ -170,6 +825,415 @@ pub struct PtzControlModel {
     #[oai(validator(maximum(value = "15"), minimum(value = "0")))]
     ///焦距缩放速度：0-15
     zoom_speed: u8,
+    #[oai(validator(maximum(value = "60000"), minimum(value = "0")))]
+    ///移动时长(毫秒)：0 持续移动,需另行下发停止；为空默认1000
+    duration: Option<u32>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct PresetInfo {
+    pub preset_id: u8,
+    pub preset_name: Option<String>,
+}
+
+///级联共享节点：node_id为行政区划(2-8位)、业务分组(类型215)或虚拟组织(类型216)编码
+#[derive(Debug, Deserialize, Object, Serialize, Get, New)]
+#[serde(crate = "common::serde")]
+pub struct CascadeNodeModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    platform_id: String,
+    #[oai(validator(min_length = "2", max_length = "20"))]
+    node_id: String,
+    #[oai(validator(min_length = "1", max_length = "64"))]
+    name: String,
+    ///上级节点,为空时挂在平台下
+    parent_id: Option<String>,
+    ///虚拟组织所属业务分组
+    business_group_id: Option<String>,
+}
+
+///级联共享通道：share_id为上报上级的编码,为空时使用原通道编码
+#[derive(Debug, Deserialize, Object, Serialize, Get, New)]
+#[serde(crate = "common::serde")]
+pub struct CascadeChannelModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    platform_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    share_id: Option<String>,
+    ///所属共享节点,为空时挂在平台下
+    parent_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct CascadeNodeRemoveModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    platform_id: String,
+    #[oai(validator(min_length = "2", max_length = "20"))]
+    node_id: String,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct CascadeChannelRemoveModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    platform_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct CascadeShareInfo {
+    pub nodes: Vec<CascadeNodeModel>,
+    pub channels: Vec<CascadeChannelModel>,
+}
+
+//PTZCmd扩展指令(GB/T 28181 附录A.3),云台方向移动见PtzControlModel
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PtzCmd {
+    ///光圈 0:停止 1:缩小 2:放大；聚焦 0:停止 1:近 2:远
+    Fi {
+        iris: u8,
+        focus: u8,
+        iris_speed: u8,
+        focus_speed: u8,
+    },
+    PresetSet(u8),
+    PresetCall(u8),
+    PresetDel(u8),
+    ///巡航组号,预置位号
+    CruiseAdd(u8, u8),
+    ///巡航组号,预置位号；预置位号为0时删除整条巡航
+    CruiseDel(u8, u8),
+    ///巡航组号,速度(12位)
+    CruiseSpeed(u8, u16),
+    ///巡航组号,停留时间秒(12位)
+    CruiseDwell(u8, u16),
+    CruiseStart(u8),
+    ScanStart(u8),
+    ScanLeft(u8),
+    ScanRight(u8),
+    ///扫描组号,速度(12位)
+    ScanSpeed(u8, u16),
+    AuxOn(u8),
+    AuxOff(u8),
+    Stop,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PtzFiModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    #[oai(validator(maximum(value = "2"), minimum(value = "0")))]
+    ///光圈 0:停止 1:缩小 2:放大
+    iris: u8,
+    #[oai(validator(maximum(value = "2"), minimum(value = "0")))]
+    ///聚焦 0:停止 1:近 2:远
+    focus: u8,
+    ///光圈速度：0-255
+    iris_speed: u8,
+    ///聚焦速度：0-255
+    focus_speed: u8,
+    #[oai(validator(maximum(value = "60000"), minimum(value = "0")))]
+    ///调节时长(毫秒)：0 持续调节,需另行下发停止；为空默认1000
+    duration: Option<u32>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PtzPresetModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    #[oai(validator(maximum(value = "3"), minimum(value = "1")))]
+    ///1:设置 2:调用 3:删除
+    action: u8,
+    #[oai(validator(maximum(value = "255"), minimum(value = "1")))]
+    ///预置位号：1-255
+    preset_id: u8,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PtzCruiseModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    #[oai(validator(maximum(value = "5"), minimum(value = "1")))]
+    ///1:加入巡航点 2:删除巡航点 3:设置速度 4:设置停留时间 5:开始巡航
+    action: u8,
+    ///巡航组号：0-255
+    group: u8,
+    ///预置位号：加入/删除巡航点时必填,删除时为0表示删除整条巡航
+    preset_id: Option<u8>,
+    #[oai(validator(maximum(value = "4095"), minimum(value = "1")))]
+    ///巡航速度或停留时间(秒)：1-4095
+    value: Option<u16>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PtzScanModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    #[oai(validator(maximum(value = "4"), minimum(value = "1")))]
+    ///1:开始自动扫描 2:设置左边界 3:设置右边界 4:设置扫描速度
+    action: u8,
+    ///扫描组号：0-255
+    group: u8,
+    #[oai(validator(maximum(value = "4095"), minimum(value = "1")))]
+    ///扫描速度：1-4095
+    speed: Option<u16>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct PtzAuxModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    ///辅助开关编号：1 雨刷；其他由设备定义
+    aux_id: u8,
+    ///true:开 false:关
+    on: bool,
+}
+
+impl PtzFiModel {
+    pub fn to_cmd(&self) -> PtzCmd {
+        PtzCmd::Fi {
+            iris: self.iris,
+            focus: self.focus,
+            iris_speed: self.iris_speed,
+            focus_speed: self.focus_speed,
+        }
+    }
+}
+
+impl PtzPresetModel {
+    pub fn to_cmd(&self) -> GlobalResult<PtzCmd> {
+        match self.action {
+            1 => Ok(PtzCmd::PresetSet(self.preset_id)),
+            2 => Ok(PtzCmd::PresetCall(self.preset_id)),
+            3 => Ok(PtzCmd::PresetDel(self.preset_id)),
+            _ => Err(SysErr(anyhow!("无效预置位指令"))),
+        }
+    }
+}
+
+impl PtzAuxModel {
+    pub fn to_cmd(&self) -> PtzCmd {
+        if self.on {
+            PtzCmd::AuxOn(self.aux_id)
+        } else {
+            PtzCmd::AuxOff(self.aux_id)
+        }
+    }
+}
+
+impl PtzCruiseModel {
+    pub fn to_cmd(&self) -> GlobalResult<PtzCmd> {
+        let preset = || {
+            self.preset_id
+                .ok_or_else(|| SysErr(anyhow!("预置位号不能为空")))
+        };
+        let value = || self.value.ok_or_else(|| SysErr(anyhow!("设置值不能为空")));
+        match self.action {
+            1 => Ok(PtzCmd::CruiseAdd(self.group, preset()?)),
+            2 => Ok(PtzCmd::CruiseDel(self.group, preset()?)),
+            3 => Ok(PtzCmd::CruiseSpeed(self.group, value()?)),
+            4 => Ok(PtzCmd::CruiseDwell(self.group, value()?)),
+            5 => Ok(PtzCmd::CruiseStart(self.group)),
+            _ => Err(SysErr(anyhow!("无效巡航指令"))),
+        }
+    }
+}
+
+impl PtzScanModel {
+    pub fn to_cmd(&self) -> GlobalResult<PtzCmd> {
+        match self.action {
+            1 => Ok(PtzCmd::ScanStart(self.group)),
+            2 => Ok(PtzCmd::ScanLeft(self.group)),
+            3 => Ok(PtzCmd::ScanRight(self.group)),
+            4 => Ok(PtzCmd::ScanSpeed(
+                self.group,
+                self.speed
+                    .ok_or_else(|| SysErr(anyhow!("扫描速度不能为空")))?,
+            )),
+            _ => Err(SysErr(anyhow!("无效扫描指令"))),
+        }
+    }
+}
+
+//拉框放大/缩小参数,坐标以播放窗口为基准
+#[derive(Object, Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq, Default)]
+#[serde(crate = "common::serde")]
+pub struct DragZoom {
+    ///播放窗口长度像素值
+    pub length: u32,
+    ///播放窗口宽度像素值
+    pub width: u32,
+    ///拉框中心的横轴坐标像素值
+    pub mid_point_x: u32,
+    ///拉框中心的纵轴坐标像素值
+    pub mid_point_y: u32,
+    ///拉框长度像素值
+    pub length_x: u32,
+    ///拉框宽度像素值
+    pub length_y: u32,
+}
+
+//DeviceControl非云台指令(GB/T 28181 A.2.3)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DeviceControlCmd {
+    TeleBoot,
+    //true:开始录像 false:停止录像
+    Record(bool),
+    //true:布防 false:撤防
+    Guard(bool),
+    ResetAlarm {
+        alarm_method: Option<u8>,
+        alarm_type: Option<u8>,
+    },
+    IFame,
+    DragZoomIn(DragZoom),
+    DragZoomOut(DragZoom),
+}
+
+impl DeviceControlCmd {
+    //远程启动、强制关键帧、拉框缩放设备无应答
+    pub fn has_response(&self) -> bool {
+        matches!(
+            self,
+            DeviceControlCmd::Record(_)
+                | DeviceControlCmd::Guard(_)
+                | DeviceControlCmd::ResetAlarm { .. }
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceControlCmd::TeleBoot => "远程启动",
+            DeviceControlCmd::Record(_) => "录像控制",
+            DeviceControlCmd::Guard(_) => "布撤防",
+            DeviceControlCmd::ResetAlarm { .. } => "报警复位",
+            DeviceControlCmd::IFame => "强制关键帧",
+            DeviceControlCmd::DragZoomIn(_) => "拉框放大",
+            DeviceControlCmd::DragZoomOut(_) => "拉框缩小",
+        }
+    }
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct DeviceControlModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    ///控制目标,为空时控制设备本身
+    channel_id: Option<String>,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    ///等待设备应答秒数,默认8
+    timeout: Option<u8>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct RecordControlModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    ///true:开始录像 false:停止录像
+    start: bool,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    timeout: Option<u8>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct GuardControlModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    ///true:布防 false:撤防
+    guard: bool,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    timeout: Option<u8>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct AlarmResetModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    #[oai(validator(maximum(value = "7"), minimum(value = "1")))]
+    ///报警方式：1 电话 2 设备 3 短信 4 GPS 5 视频 6 设备故障 7 其他
+    alarm_method: Option<u8>,
+    ///报警类型,取值随报警方式而定
+    alarm_type: Option<u8>,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    timeout: Option<u8>,
+}
+
+#[derive(Object, Debug, Deserialize, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct DragZoomModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: String,
+    ///true:拉框放大 false:拉框缩小
+    zoom_in: bool,
+    drag_zoom: DragZoom,
+}
+
+impl RecordControlModel {
+    pub fn to_cmd(&self) -> DeviceControlCmd {
+        DeviceControlCmd::Record(self.start)
+    }
+}
+
+impl GuardControlModel {
+    pub fn to_cmd(&self) -> DeviceControlCmd {
+        DeviceControlCmd::Guard(self.guard)
+    }
+}
+
+impl AlarmResetModel {
+    pub fn to_cmd(&self) -> DeviceControlCmd {
+        DeviceControlCmd::ResetAlarm {
+            alarm_method: self.alarm_method,
+            alarm_type: self.alarm_type,
+        }
+    }
+}
+
+impl DragZoomModel {
+    pub fn to_cmd(&self) -> DeviceControlCmd {
+        if self.zoom_in {
+            DeviceControlCmd::DragZoomIn(self.drag_zoom)
+        } else {
+            DeviceControlCmd::DragZoomOut(self.drag_zoom)
+        }
+    }
 }
 
 // rtsp://localhost:8554/rtp/07C04232
@@ -216,6 +1280,58 @@ impl StreamInfo {
     }
 }
 
+//目录同步结果：分包收全后以最终目录为准,设备上已不存在的通道置为离线
+#[derive(Debug, Deserialize, Object, Serialize, Default, Clone)]
+#[serde(crate = "common::serde")]
+pub struct CatalogSyncResult {
+    pub device_id: String,
+    pub sn: String,
+    ///设备上报的通道总数
+    pub sum_num: u32,
+    ///实际收到的通道条目数(含重复上报)
+    pub received: u32,
+    pub added: u32,
+    pub updated: u32,
+    ///设备未再上报、已置为离线的通道数
+    pub removed: u32,
+    ///false：分包未收全即超时,不下线通道
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct CatalogSyncEvent {
+    ///事件类型：catalog_sync 目录同步完成
+    pub event: String,
+    #[serde(flatten)]
+    pub result: CatalogSyncResult,
+    pub ts: i64,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct CatalogSyncModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: String,
+    #[oai(validator(maximum(value = "120"), minimum(value = "1")))]
+    ///等待目录同步完成秒数,默认30
+    timeout: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct StreamEvent {
+    ///事件类型：end 设备推流结束
+    pub event: String,
+    pub stream_id: String,
+    pub device_id: String,
+    pub channel_id: String,
+    ///点播类型：live、back、down
+    pub play_type: String,
+    ///事件时间戳
+    pub ts: i64,
+}
+
 #[derive(Debug, Deserialize, Object, Serialize, Default)]
 #[serde(crate = "common::serde")]
 #[allow(non_snake_case)]
@@ -226,33 +1342,74 @@ pub struct AlarmInfo {
     pub timeStr: String,
     pub deviceId: String,
     pub channelId: String,
+    pub description: Option<String>,
+    ///告警记录ID,入库后赋值
+    pub alarmId: Option<i64>,
+    ///确认时间(时间戳),未确认为空
+    pub ackTime: Option<i64>,
 }
 
-impl KV2Model for AlarmInfo {
-    fn kv_to_model(arr: Vec<(String, String)>) -> GlobalResult<Self> {
-        use crate::gb::handler::parser::xml::*;
-        let mut model = AlarmInfo::default();
-        for (k, v) in arr {
-            match &k[..] {
-                NOTIFY_DEVICE_ID => {
-                    model.channelId = v;
-                }
-                NOTIFY_ALARM_PRIORITY => {
-                    model.priority = v.parse::<u8>().hand_log(|msg| error!("{msg}"))?;
-                }
-                NOTIFY_ALARM_TIME => {
-                    model.timeStr = v;
-                }
-                NOTIFY_ALARM_METHOD => {
-                    model.method = v.parse::<u8>().hand_log(|msg| error!("{msg}"))?;
-                }
-                NOTIFY_INFO_ALARM_TYPE => {
-                    model.alarmType = v.parse::<u8>().hand_log(|msg| error!("{msg}"))?;
-                }
-                &_ => {}
-            }
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct AlarmQueryModel {
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    device_id: Option<String>,
+    #[oai(validator(min_length = "20", max_length = "20"))]
+    channel_id: Option<String>,
+    ///告警开始时间(时间戳)
+    st: Option<u32>,
+    ///告警结束时间(时间戳)
+    et: Option<u32>,
+    #[oai(validator(maximum(value = "4"), minimum(value = "1")))]
+    priority: Option<u8>,
+    #[oai(validator(maximum(value = "7"), minimum(value = "1")))]
+    ///报警方式：1 电话 2 设备 3 短信 4 GPS 5 视频 6 设备故障 7 其他
+    method: Option<u8>,
+    alarm_type: Option<u8>,
+    ///true:已确认 false:未确认,为空不过滤
+    acked: Option<bool>,
+    #[oai(validator(minimum(value = "1")))]
+    ///页码,从1开始,默认1
+    page: Option<u32>,
+    #[oai(validator(maximum(value = "1000"), minimum(value = "1")))]
+    ///每页条数,默认20
+    size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize)]
+#[serde(crate = "common::serde")]
+pub struct AlarmPage {
+    pub total: u64,
+    pub list: Vec<AlarmInfo>,
+}
+
+#[derive(Debug, Deserialize, Object, Serialize, Get)]
+#[serde(crate = "common::serde")]
+pub struct AlarmAckModel {
+    alarm_id: i64,
+    ///是否同时向设备下发报警复位,默认false
+    reset: Option<bool>,
+    #[oai(validator(maximum(value = "30"), minimum(value = "1")))]
+    ///等待设备应答秒数,默认8
+    timeout: Option<u8>,
+}
+
+impl From<manscdp::AlarmNotify> for AlarmInfo {
+    fn from(notify: manscdp::AlarmNotify) -> Self {
+        AlarmInfo {
+            priority: notify.alarm_priority.unwrap_or_default(),
+            method: notify.alarm_method.unwrap_or_default(),
+            alarmType: notify
+                .info
+                .and_then(|info| info.alarm_type)
+                .unwrap_or_default(),
+            timeStr: notify.alarm_time,
+            deviceId: String::new(),
+            channelId: notify.device_id,
+            description: notify.alarm_description,
+            alarmId: None,
+            ackTime: None,
         }
-        Ok(model)
     }
 }
 
@@ -261,7 +1418,11 @@ mod test {
     use poem_openapi::payload::Json;
     use poem_openapi::types::ToJSON;
 
-    use crate::general::model::{ResultMessageData, StreamInfo};
+    use crate::gb::handler::manscdp::{Body, Manscdp, Notify, Response};
+    use crate::general::model::{
+        BroadcastInfo, ConfigDownloadInfo, DeviceStatusInfo, DownloadInfo, MobilePosition,
+        RecordInfo, ResultMessageData, StreamInfo, StreamMode, TimeRange,
+    };
 
     #[test]
     fn t1() {
@@ -273,4 +1434,216 @@ mod test {
         let data = ResultMessageData::build_success(m);
         println!("{:#?}", Json(data).to_json_string());
     }
+
+    #[test]
+    fn test_record_info_response() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>RecordInfo</CmdType>
+<SN>17430</SN>
+<DeviceID>34020000001320000001</DeviceID>
+<Name>Camera 01</Name>
+<SumNum>3</SumNum>
+<RecordList Num="2">
+<Item>
+<DeviceID>34020000001320000001</DeviceID>
+<Name>Camera 01</Name>
+<StartTime>2024-05-01T08:00:00</StartTime>
+<EndTime>2024-05-01T09:00:00</EndTime>
+<Secrecy>0</Secrecy>
+<Type>time</Type>
+</Item>
+<Item>
+<DeviceID>34020000001320000001</DeviceID>
+<Name>Camera 01</Name>
+<StartTime>2024-05-01T09:00:00</StartTime>
+<EndTime>2024-05-01T10:00:00</EndTime>
+<Secrecy>0</Secrecy>
+<Type>alarm</Type>
+<FileSize>1024</FileSize>
+</Item>
+</RecordList>
+</Response>"#;
+        let Body::Response(Response::RecordInfo(res)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected RecordInfo");
+        };
+        let info = RecordInfo::from(res);
+        assert_eq!(info.sum_num, 3);
+        assert_eq!(info.items.len(), 2);
+        assert_eq!(info.items[0].record_type.as_deref(), Some("time"));
+        assert_eq!(info.items[1].file_size, Some(1024));
+        assert_eq!(info.items[0].et, info.items[1].st);
+        assert_eq!(
+            TimeRange::to_gb_time(info.items[1].et),
+            "2024-05-01T10:00:00"
+        );
+    }
+
+    #[test]
+    fn test_download_progress() {
+        let mut info = DownloadInfo {
+            stream_id: "".to_string(),
+            device_id: "".to_string(),
+            channel_id: "".to_string(),
+            st: 1000,
+            et: 1400,
+            speed: 4,
+            start_ts: 100,
+            end_ts: None,
+            state: DownloadInfo::DOWNLOADING,
+            progress: 0,
+        };
+        assert_eq!(info.estimate_progress(100), 0);
+        assert_eq!(info.estimate_progress(150), 50);
+        assert_eq!(info.estimate_progress(300), 99);
+        info.state = DownloadInfo::FINISHED;
+        assert_eq!(info.estimate_progress(150), 100);
+    }
+
+    #[test]
+    fn test_stream_mode_answer() {
+        assert_eq!(StreamMode::build(1).unwrap(), StreamMode::TcpPassive);
+        assert_eq!(StreamMode::build(2).unwrap(), StreamMode::TcpActive);
+        let tcp = "v=0\r\no=34020000001320000001 0 0 IN IP4 10.0.0.2\r\ns=Play\r\nc=IN IP4 10.0.0.2\r\nt=0 0\r\nm=video 15060 TCP/RTP/AVP 96\r\na=setup:active\r\na=sendonly\r\na=rtpmap:96 PS/90000\r\ny=0100000001\r\n";
+        assert!(StreamMode::TcpPassive.check_answer(tcp.as_bytes()).is_ok());
+        assert!(StreamMode::TcpActive.check_answer(tcp.as_bytes()).is_err());
+        assert!(StreamMode::Udp.check_answer(tcp.as_bytes()).is_err());
+        assert_eq!(
+            StreamMode::answer_address(tcp.as_bytes()).unwrap(),
+            ("10.0.0.2".to_string(), 15060)
+        );
+        let udp = tcp
+            .replace("TCP/RTP/AVP", "RTP/AVP")
+            .replace("a=setup:active\r\n", "");
+        assert!(StreamMode::Udp.check_answer(udp.as_bytes()).is_ok());
+        assert!(StreamMode::TcpActive.check_answer(udp.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_broadcast_offer() {
+        let sdp = "v=0\r\no=34020000001370000001 0 0 IN IP4 10.0.0.3\r\ns=Play\r\nc=IN IP4 10.0.0.3\r\nt=0 0\r\nm=audio 15062 TCP/RTP/AVP 8 96\r\na=setup:active\r\na=recvonly\r\na=rtpmap:8 PCMA/8000\r\ny=0100000002\r\nf=v/////a/1/8/1\r\n";
+        let mut info = BroadcastInfo::default();
+        info.apply_offer(sdp.as_bytes()).unwrap();
+        assert_eq!(info.device_ip, "10.0.0.3");
+        assert_eq!(info.device_port, 15062);
+        assert_eq!(info.trans_mode, 1);
+        assert_eq!(info.payload, 8);
+        assert_eq!(info.ssrc, "0100000002");
+        let video = sdp.replace("m=audio", "m=video");
+        assert!(BroadcastInfo::default()
+            .apply_offer(video.as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_device_status_response() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>DeviceStatus</CmdType>
+<SN>248</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<Result>OK</Result>
+<Online>ONLINE</Online>
+<Status>OK</Status>
+<Encode>ON</Encode>
+<Record>OFF</Record>
+<DeviceTime>2024-05-01T12:00:00</DeviceTime>
+<Alarmstatus Num="2">
+<Item>
+<DeviceID>34020000001340000001</DeviceID>
+<DutyStatus>ONDUTY</DutyStatus>
+</Item>
+<Item>
+<DeviceID>34020000001340000002</DeviceID>
+<DutyStatus>ALARM</DutyStatus>
+</Item>
+</Alarmstatus>
+</Response>"#;
+        let Body::Response(Response::DeviceStatus(res)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected DeviceStatus");
+        };
+        let info = DeviceStatusInfo::from(res);
+        assert_eq!(info.device_id, "34020000001110000001");
+        assert!(info.online);
+        assert_eq!(info.encode, Some(true));
+        assert_eq!(info.record, Some(false));
+        assert_eq!(info.device_time.as_deref(), Some("2024-05-01T12:00:00"));
+        assert_eq!(info.alarm_status.len(), 2);
+        assert_eq!(info.alarm_status[1].duty_status, "ALARM");
+    }
+
+    #[test]
+    fn test_config_download_response() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>ConfigDownload</CmdType>
+<SN>31</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<Result>OK</Result>
+<BasicParam>
+<Name>gate</Name>
+<Expiration>3600</Expiration>
+<HeartBeatInterval>60</HeartBeatInterval>
+<HeartBeatCount>3</HeartBeatCount>
+</BasicParam>
+<SVACEncodeConfig>
+<ROIParam>
+<ROIFlag>1</ROIFlag>
+</ROIParam>
+</SVACEncodeConfig>
+</Response>"#;
+        let Body::Response(Response::ConfigDownload(res)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected ConfigDownload");
+        };
+        let mut info = ConfigDownloadInfo::from(res);
+        let basic = info.basic_param.as_ref().unwrap();
+        assert_eq!(basic.name.as_deref(), Some("gate"));
+        assert_eq!(basic.expiration, Some(3600));
+        assert_eq!(basic.heart_beat_interval, Some(60));
+        assert_eq!(info.svac_encode_config.as_ref().unwrap().roi_flag, Some(1));
+        assert!(!info.contains("AlarmReport"));
+        info.merge(ConfigDownloadInfo {
+            alarm_report: Some(Default::default()),
+            ..Default::default()
+        });
+        assert!(info.contains("AlarmReport") && info.contains("BasicParam"));
+    }
+
+    #[test]
+    fn test_mobile_position_notify() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Notify>
+<CmdType>MobilePosition</CmdType>
+<SN>8</SN>
+<DeviceID>34020000001320000001</DeviceID>
+<Time>2024-05-01T08:30:00</Time>
+<Longitude>116.397128</Longitude>
+<Latitude>39.916527</Latitude>
+<Speed>36.5</Speed>
+<Direction>90</Direction>
+<Altitude>52</Altitude>
+</Notify>"#;
+        let Body::Notify(Notify::MobilePosition(notify)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected MobilePosition");
+        };
+        let pos = MobilePosition::try_from(notify).unwrap();
+        assert_eq!(pos.channel_id, "34020000001320000001");
+        assert_eq!(
+            pos.time,
+            TimeRange::parse_gb_time("2024-05-01T08:30:00").unwrap()
+        );
+        assert!((pos.longitude - 116.397128).abs() < 1e-9);
+        assert!((pos.latitude - 39.916527).abs() < 1e-9);
+        assert_eq!(pos.speed, Some(36.5));
+        assert_eq!(pos.direction, Some(90.0));
+        assert_eq!(pos.altitude, Some(52.0));
+    }
 }
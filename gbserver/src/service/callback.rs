@@ -1,5 +1,5 @@
-use crate::general::model::AlarmInfo;
-use crate::general::AlarmConf;
+use crate::general::model::{AlarmInfo, CatalogSyncEvent, StreamEvent};
+use crate::general::{AlarmConf, StreamConf};
 use crate::service::{ResMsg, EXPIRES};
 use common::anyhow::anyhow;
 use common::exception::GlobalError::SysErr;
@@ -28,6 +28,7 @@ const STOP_PLAY: &str = "/stop/play";
 const QUERY_STREAM_COUNT: &str = "/query/stream/count";
 #[allow(dead_code)]
 const RTP_MEDIA: &str = "/rtp/media";
+const RTP_CONNECT: &str = "/rtp/connect";
 
 fn build_uri_header(
     gbs_token: &str,
@@ -174,13 +175,66 @@ pub async fn _ident_rtp_media_info(
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "common::serde")]
+struct RtpConnectDto {
+    ssrc: u32,
+    stream_id: String,
+    //设备应答SDP中的监听地址
+    ip: String,
+    port: u16,
+}
+
+//TCP主动模式：通知流媒体节点连接设备取流
+pub async fn call_connect_rtp(
+    stream_id: &str,
+    ssrc: &str,
+    remote: (String, u16),
+    gbs_token: &str,
+    local_ip: &Ipv4Addr,
+    local_port: &u16,
+) -> GlobalResult<bool> {
+    let ssrc = ssrc.parse::<u32>().hand_log(|msg| error!("{msg}"))?;
+    let (ip, port) = remote;
+    let dto = RtpConnectDto {
+        ssrc,
+        stream_id: stream_id.to_string(),
+        ip,
+        port,
+    };
+    let (uri, headers) = build_uri_header(gbs_token, local_ip, local_port)?;
+    let res = reqwest::Client::builder()
+        .timeout(Duration::from_secs(EXPIRES))
+        .default_headers(headers)
+        .build()
+        .hand_log(|msg| error!("{msg}"))?
+        .post(format!("{uri}{RTP_CONNECT}"))
+        .json(&dto)
+        .send()
+        .await
+        .hand_log(|msg| error!("{msg}"))?;
+    if res.status().is_success() {
+        let body = res
+            .json::<ResMsg<bool>>()
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(body.code == 200)
+    } else {
+        Err(SysErr(anyhow!("{}", res.status().to_string()))).hand_log(|msg| error!("{msg}"))?
+    }
+}
+
+//未配置server.alarm.push_url时不推送
 pub async fn call_alarm_info(info: &AlarmInfo) -> GlobalResult<bool> {
     let conf = AlarmConf::get_alarm_conf();
+    let Some(push_url) = conf.push_url.as_ref().filter(|url| !url.is_empty()) else {
+        return Ok(false);
+    };
     let res = reqwest::Client::builder()
         .timeout(Duration::from_secs(EXPIRES))
         .build()
         .hand_log(|msg| error!("{msg}"))?
-        .post(conf.push_url.as_ref().unwrap())
+        .post(push_url)
         .json(info)
         .send()
         .await
@@ -195,3 +249,33 @@ pub async fn call_alarm_info(info: &AlarmInfo) -> GlobalResult<bool> {
         Err(SysErr(anyhow!("{}", res.status().to_string()))).hand_log(|msg| error!("{msg}"))?
     }
 }
+
+pub async fn call_stream_event(event: &StreamEvent) -> GlobalResult<bool> {
+    post_event(event).await
+}
+
+pub async fn call_catalog_sync(event: &CatalogSyncEvent) -> GlobalResult<bool> {
+    post_event(event).await
+}
+
+//未配置server.stream.event_url时不推送
+async fn post_event<T: Serialize>(event: &T) -> GlobalResult<bool> {
+    let conf = StreamConf::get_stream_conf();
+    let Some(event_url) = conf.get_event_url() else {
+        return Ok(false);
+    };
+    let res = reqwest::Client::builder()
+        .timeout(Duration::from_secs(EXPIRES))
+        .build()
+        .hand_log(|msg| error!("{msg}"))?
+        .post(event_url)
+        .json(event)
+        .send()
+        .await
+        .hand_log(|msg| error!("{msg}"))?;
+    if res.status().is_success() {
+        Ok(true)
+    } else {
+        Err(SysErr(anyhow!("{}", res.status().to_string()))).hand_log(|msg| error!("{msg}"))?
+    }
+}
@@ -24,6 +24,7 @@ pub async fn upload(
     session_id: String,
     file_id: Option<String>,
     snap_shot_file_id: Option<String>,
+    alarm_id: Option<i64>,
 ) -> GlobalResult<()> {
     let id = snap_shot_file_id.or(file_id);
     let (device_id, channel_id) = se_token::split_dc(&session_id)?;
@@ -38,6 +39,7 @@ pub async fn upload(
     info.create_time = Some(now);
     info.file_type = Some(0);
     info.is_del = Some(0);
+    info.alarm_id = alarm_id;
 
     let (device_id, channel_id) = se_token::split_dc(&session_id)?;
     info.device_id = device_id;
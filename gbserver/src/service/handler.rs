@@ -1,19 +1,57 @@
-use crate::gb::handler::cmd::{CmdControl, CmdStream};
+use crate::gb::handler::cascade::{Cascade, NodeKind};
+use crate::gb::handler::cmd::{
+    CmdControl, CmdNotify, CmdQuery, CmdStream, CmdSubscribe, KEY_BROADCAST, SUBSCRIBE_KINDS,
+};
+use crate::gb::handler::dialog::Dialog;
 use crate::gb::RWSession;
 use crate::general;
 use crate::general::cache::PlayType;
 use crate::general::model::*;
 use crate::service::*;
+use crate::store::entity::{
+    GbsCascadeChannel, GbsCascadeNode, GbsDeviceAlarm, GbsDeviceChannel, GbsDevicePosition,
+    GbsDevicePreset, GbsFileInfo, GbsOauth,
+};
 use crate::utils::id_builder;
 use common::bytes::Bytes;
+use common::chrono::{Local, NaiveDateTime, TimeZone};
 use common::exception::{GlobalError, GlobalResult, TransError};
 use common::log::error;
+use common::serde::{Deserialize, Serialize};
 use common::serde_json;
 use common::tokio::sync::mpsc;
-use common::tokio::time::{sleep, Instant};
+use common::tokio::time::{sleep, timeout, Instant};
 use std::time::Duration;
 
 const KEY_STREAM_IN: &str = "KEY_STREAM_IN:";
+const KEY_DOWNLOAD: &str = "KEY_DOWNLOAD:";
+//下载任务结束后状态保留时长
+const DOWNLOAD_RETAIN: u64 = 3600;
+//云台定时移动默认时长(毫秒)
+const PTZ_DURATION: u32 = 1000;
+//下发广播通知后等待设备发起音频INVITE的时间
+const BROADCAST_EXPIRES: u64 = 15;
+//目录同步默认等待时长(秒)
+const CATALOG_SYNC_EXPIRES: u8 = 30;
+//位置订阅默认上报间隔(秒)
+const POSITION_INTERVAL: u32 = 5;
+//轨迹查询默认条数上限
+const POSITION_LIMIT: u32 = 1000;
+const ALARM_PAGE_SIZE: u32 = 20;
+//告警录像待入库：KEY_ALARM_CLIP:{stream_id}
+const KEY_ALARM_CLIP: &str = "KEY_ALARM_CLIP:";
+const ALARM_CLIP_SPEED: u8 = 4;
+
+//告警录像下载中,待流媒体录制完成后入库
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "common::serde")]
+struct AlarmClip {
+    alarm_id: i64,
+    device_id: String,
+    channel_id: String,
+    st: u32,
+    node_name: String,
+}
 
 pub fn on_publish(_: PublishRequest) -> OnPublishResponse {
     // true
@@ -89,21 +127,8 @@ pub fn on_rtp_server_timeout(_: RtpServerTimeoutRequest) -> OnRtpServerTimeoutRe
 //无人观看则关闭流
 pub async fn stream_idle(base_stream_info: BaseStreamInfo) -> bool {
     let stream_id = base_stream_info.get_stream_id();
-    let cst_info = general::cache::Cache::stream_map_build_call_id_seq_from_to_tag(stream_id);
-
-    let (device_id, channel_id, ssrc) = id_builder::de_stream_id(stream_id);
-    if let Some((call_id, seq, from_tag, to_tag)) = cst_info {
-        let _ =
-            CmdStream::play_bye(seq, call_id, &device_id, &channel_id, &from_tag, &to_tag).await;
-    }
-    if let Some(play_type) =
-        general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id)
-    {
-        general::cache::Cache::device_map_remove(
-            &device_id,
-            Some((&channel_id, Some((play_type, &ssrc)))),
-        );
-        general::cache::Cache::stream_map_remove(stream_id, None);
+    if let Some(PlayType::Down) = teardown_stream(stream_id).await {
+        download_end(stream_id, DownloadInfo::TERMINATED);
     }
     let ssrc = base_stream_info.rtp_info.ssrc;
     let ssrc_num = (ssrc % 10000) as u16;
@@ -128,7 +153,7 @@ pub async fn stream_in(base_stream_info: BaseStreamInfo) {
 }
 
 //gbs-stream接收流超时:还ssrc_sn,清理stream_map/device_map
-pub fn stream_input_timeout(stream_state: StreamState) {
+pub async fn stream_input_timeout(stream_state: StreamState) {
     let ssrc = stream_state.base_stream_info.rtp_info.ssrc;
     let ssrc_num = (ssrc % 10000) as u16;
     general::cache::Cache::ssrc_sn_set(ssrc_num);
@@ -136,13 +161,70 @@ pub fn stream_input_timeout(stream_state: StreamState) {
     if let Some(play_type) =
         general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id)
     {
-        general::cache::Cache::stream_map_remove(stream_id, None);
         let (device_id, channel_id, ssrc) = id_builder::de_stream_id(stream_id);
+        //挂断设备侧会话,避免其继续向已释放的ssrc推流
+        if let Some(call_id) =
+            general::cache::Cache::stream_map_query_call_id_by_stream_id(stream_id)
+                .filter(|call_id| Dialog::contains(call_id))
+        {
+            let _ = CmdStream::play_bye(call_id, &device_id, &channel_id)
+                .await
+                .hand_log(|msg| error!("{msg}"));
+        }
+        general::cache::Cache::stream_map_remove(stream_id, None);
         general::cache::Cache::device_map_remove(
             &device_id,
             Some((&channel_id, Some((play_type, &ssrc)))),
         );
+        if play_type == PlayType::Down {
+            download_end(stream_id, DownloadInfo::TERMINATED);
+        }
+    }
+}
+
+//设备通知录像文件发送结束(MediaStatus NotifyType=121)：
+//结束回放/下载会话,关闭推流,归还ssrc,并推送流结束事件
+pub async fn media_file_end(stream_id: &String) {
+    let play_type = match general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id)
+    {
+        Some(play_type @ (PlayType::Back | PlayType::Down)) => play_type,
+        _ => return,
+    };
+    if play_type == PlayType::Down {
+        download_end(stream_id, DownloadInfo::FINISHED);
     }
+    stream_end(stream_id, play_type).await;
+}
+
+//设备主动挂断(BYE)：对话已终止,清理流缓存,归还ssrc,并推送流结束事件
+pub async fn device_bye(stream_id: &String) {
+    let Some(play_type) = general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id)
+    else {
+        return;
+    };
+    if play_type == PlayType::Down {
+        download_end(stream_id, DownloadInfo::TERMINATED);
+    }
+    stream_end(stream_id, play_type).await;
+}
+
+async fn stream_end(stream_id: &String, play_type: PlayType) {
+    teardown_stream(stream_id).await;
+    let (device_id, channel_id, ssrc) = id_builder::de_stream_id(stream_id);
+    if let Ok(ssrc) = ssrc.parse::<u32>() {
+        general::cache::Cache::ssrc_sn_set((ssrc % 10000) as u16);
+    }
+    let event = StreamEvent {
+        event: "end".to_string(),
+        stream_id: stream_id.to_string(),
+        device_id,
+        channel_id,
+        play_type: play_type.name().to_string(),
+        ts: Local::now().timestamp(),
+    };
+    let _ = callback::call_stream_event(&event)
+        .await
+        .hand_log(|msg| error!("流结束事件推送失败:{msg}"));
 }
 
 /*
@@ -171,12 +253,15 @@ pub async fn play_live(play_live_model: PlayLiveModel, token: String) -> GlobalR
         general::cache::Cache::stream_map_insert_token(stream_id.clone(), token);
         return Ok(StreamInfo::build(stream_id, node_name));
     }
+    let stream_mode = StreamMode::build(play_live_model.get_trans_mode().unwrap_or(0))?;
     let (stream_id, node_name) = start_invite_stream(
         device_id,
         channel_id,
         &token,
         play_type,
+        stream_mode,
         TimeRange::build(0, 0),
+        0,
     )
     .await?;
     general::cache::Cache::stream_map_insert_token(stream_id.clone(), token);
@@ -205,18 +290,541 @@ pub async fn play_back(play_back_model: PlayBackModel, token: String) -> GlobalR
     }
     let st = play_back_model.get_st();
     let et = play_back_model.get_et();
+    let stream_mode = StreamMode::build(play_back_model.get_trans_mode().unwrap_or(0))?;
     let (stream_id, node_name) = start_invite_stream(
         device_id,
         channel_id,
         &token,
         play_type,
+        stream_mode,
         TimeRange::build(*st, *et),
+        0,
     )
     .await?;
     general::cache::Cache::stream_map_insert_token(stream_id.clone(), token);
     Ok(StreamInfo::build(stream_id, node_name))
 }
 
+/*
+1.检查设备状态：是否在线
+2.同一通道同时只允许一路下载
+3.开启下载流,记录下载任务状态
+*/
+pub async fn download(play_down_model: PlayDownModel, token: String) -> GlobalResult<StreamInfo> {
+    let device_id = play_down_model.get_device_id();
+    let channel_id = if let Some(channel_id) = play_down_model.get_channel_id() {
+        channel_id
+    } else {
+        device_id
+    };
+    let stream_mode = StreamMode::build(play_down_model.get_trans_mode().unwrap_or(0))?;
+    let (stream_id, node_name) = start_download(
+        device_id,
+        channel_id,
+        *play_down_model.get_st(),
+        *play_down_model.get_et(),
+        play_down_model.get_speed().unwrap_or(4),
+        stream_mode,
+        token,
+    )
+    .await?;
+    Ok(StreamInfo::build(stream_id, node_name))
+}
+
+//告警联动录像：下载告警前后片段,录制完成后文件关联告警
+//占用通道的下载会话,通道已有下载任务(含用户下载)时失败,不会抢占
+pub async fn alarm_clip(
+    alarm_id: i64,
+    device_id: &String,
+    channel_id: &String,
+    st: u32,
+    et: u32,
+    clip_mode: u8,
+) -> GlobalResult<()> {
+    let stream_mode = StreamMode::build(clip_mode)?;
+    let (stream_id, node_name) = start_download(
+        device_id,
+        channel_id,
+        st,
+        et,
+        ALARM_CLIP_SPEED,
+        stream_mode,
+        format!("alarm{alarm_id}"),
+    )
+    .await?;
+    let clip = AlarmClip {
+        alarm_id,
+        device_id: device_id.to_string(),
+        channel_id: channel_id.to_string(),
+        st,
+        node_name,
+    };
+    //录制完成回调晚于下载结束,按下载预留时长保留待入库信息
+    let expire =
+        Duration::from_secs(((et - st) / ALARM_CLIP_SPEED as u32) as u64 + DOWNLOAD_RETAIN);
+    general::cache::Cache::state_insert_obj_by_timer(
+        format!("{KEY_ALARM_CLIP}{stream_id}"),
+        &clip,
+        expire,
+        None,
+    );
+    Ok(())
+}
+
+//流媒体录制文件完成：告警录像以实际文件入库并关联告警,非告警录像忽略
+pub async fn end_record(info: StreamRecordInfo) -> GlobalResult<()> {
+    let stream_id = info.get_base_stream_info().get_stream_id();
+    let Some((bytes, _)) =
+        general::cache::Cache::state_remove(&format!("{KEY_ALARM_CLIP}{stream_id}"))
+    else {
+        return Ok(());
+    };
+    let clip: AlarmClip = serde_json::from_slice(&bytes).hand_log(|msg| error!("{msg}"))?;
+    if *info.get_file_size() == 0 {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            &format!("告警录像文件为空: alarm_id = {}", clip.alarm_id),
+            |msg| error!("{msg}"),
+        ));
+    }
+    let file = GbsFileInfo {
+        device_id: clip.device_id,
+        channel_id: clip.channel_id,
+        biz_time: Some(to_time(clip.st)?),
+        biz_id: stream_id.clone(),
+        file_type: Some(1),
+        file_size: Some(*info.get_file_size() as u64 * 1024),
+        file_name: info.get_file_name().clone(),
+        dir_path: info.get_file_path().clone(),
+        note: Some(format!("告警录像,流媒体节点:{}", clip.node_name)),
+        is_del: Some(0),
+        create_time: Some(Local::now().naive_local()),
+        alarm_id: Some(clip.alarm_id),
+        ..Default::default()
+    };
+    GbsFileInfo::insert_gbs_file_info(vec![file]).await
+}
+
+//返回(stream_id,node_name)
+async fn start_download(
+    device_id: &String,
+    channel_id: &String,
+    st: u32,
+    et: u32,
+    speed: u8,
+    stream_mode: StreamMode,
+    token: String,
+) -> GlobalResult<(String, String)> {
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let play_type = PlayType::Down;
+    if general::cache::Cache::device_map_get_invite_info(device_id, channel_id, &play_type)
+        .is_some()
+    {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "该通道已有下载任务",
+            |msg| error!("{msg}"),
+        ));
+    }
+    if st >= et {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "开始时间需小于结束时间",
+            |msg| error!("{msg}"),
+        ));
+    }
+    //GB28181下载倍速仅支持1、2、4
+    if !matches!(speed, 1 | 2 | 4) {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "下载倍速仅支持1、2、4",
+            |msg| error!("{msg}"),
+        ));
+    }
+    let (stream_id, node_name) = start_invite_stream(
+        device_id,
+        channel_id,
+        &token,
+        play_type,
+        stream_mode,
+        TimeRange::build(st, et),
+        speed,
+    )
+    .await?;
+    general::cache::Cache::stream_map_insert_token(stream_id.clone(), token);
+    let info = DownloadInfo {
+        stream_id: stream_id.clone(),
+        device_id: device_id.to_string(),
+        channel_id: channel_id.to_string(),
+        st,
+        et,
+        speed,
+        start_ts: Local::now().timestamp(),
+        end_ts: None,
+        state: DownloadInfo::DOWNLOADING,
+        progress: 0,
+    };
+    //设备未通知结束时,按录像时长与倍速预留过期时间
+    let expire = Duration::from_secs(((et - st) / speed as u32) as u64 + DOWNLOAD_RETAIN);
+    general::cache::Cache::state_insert_obj_by_timer(
+        format!("{KEY_DOWNLOAD}{stream_id}"),
+        &info,
+        expire,
+        None,
+    );
+    Ok((stream_id, node_name))
+}
+
+pub async fn download_info(stream_id: String, _token: String) -> GlobalResult<DownloadInfo> {
+    let (bytes, _) = general::cache::Cache::state_get(&format!("{KEY_DOWNLOAD}{stream_id}"))
+        .ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "下载任务不存在或已过期", |msg| {
+                error!("{msg}")
+            })
+        })?;
+    let mut info: DownloadInfo = serde_json::from_slice(&bytes).hand_log(|msg| error!("{msg}"))?;
+    if info.state == DownloadInfo::DOWNLOADING {
+        info.progress = info.estimate_progress(Local::now().timestamp());
+    }
+    Ok(info)
+}
+
+pub async fn download_stop(stop_model: PlayStreamModel, _token: String) -> GlobalResult<bool> {
+    let stream_id = stop_model.get_stream_id();
+    if general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id)
+        != Some(PlayType::Down)
+    {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "下载任务不存在或已结束",
+            |msg| error!("{msg}"),
+        ));
+    }
+    download_end(stream_id, DownloadInfo::TERMINATED);
+    teardown_stream(stream_id).await;
+    let (_device_id, _channel_id, ssrc) = id_builder::de_stream_id(stream_id);
+    if let Ok(ssrc) = ssrc.parse::<u32>() {
+        general::cache::Cache::ssrc_sn_set((ssrc % 10000) as u16);
+    }
+    Ok(true)
+}
+
+pub async fn record_info(
+    record_query_model: RecordQueryModel,
+    _token: String,
+) -> GlobalResult<Vec<RecordItem>> {
+    let device_id = record_query_model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let st = *record_query_model.get_st();
+    let et = *record_query_model.get_et();
+    if st >= et {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "开始时间需小于结束时间",
+            |msg| error!("{msg}"),
+        ));
+    }
+    let record_type = record_query_model
+        .get_record_type()
+        .as_deref()
+        .unwrap_or("all");
+    let secrecy = record_query_model.get_secrecy().unwrap_or(0);
+    CmdQuery::query_record_info(
+        device_id,
+        record_query_model.get_channel_id().as_ref(),
+        TimeRange::build(st, et),
+        record_type,
+        secrecy,
+    )
+    .await
+}
+
+/*
+1.检查设备状态：是否在线
+2.同一音频通道同时只允许一路广播
+3.选择流媒体节点,下发广播通知,等待设备INVITE协商完成
+*/
+pub async fn broadcast(
+    broadcast_model: BroadcastModel,
+    _token: String,
+) -> GlobalResult<BroadcastInfo> {
+    let device_id = broadcast_model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let channel_id = broadcast_model
+        .get_channel_id()
+        .as_ref()
+        .unwrap_or(device_id);
+    let key = format!("{KEY_BROADCAST}{channel_id}");
+    if general::cache::Cache::state_get(&key).is_some() {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "该通道正在语音广播",
+            |msg| error!("{msg}"),
+        ));
+    }
+    let oauth = GbsOauth::read_gbs_oauth_by_device_id(device_id)
+        .await?
+        .ok_or_else(|| GlobalError::new_biz_error(1100, "未知设备", |msg| error!("{msg}")))?;
+    let conf = general::StreamConf::get_stream_conf();
+    let (_, node_name) = general::cache::Cache::stream_map_order_node()
+        .pop_first()
+        .ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "无可用流媒体服务", |msg| error!("{msg}"))
+        })?;
+    let stream_node = conf.get_node_map().get(&node_name).unwrap();
+    let info = BroadcastInfo {
+        device_id: device_id.to_string(),
+        channel_id: channel_id.to_string(),
+        source_id: oauth.get_domain_id().to_string(),
+        node_name,
+        node_ip: stream_node.get_pub_ip().to_string(),
+        node_port: *stream_node.get_pub_port(),
+        ..Default::default()
+    };
+    let (tx, mut rx) = mpsc::channel(1);
+    general::cache::Cache::state_insert_obj_by_timer(
+        key.clone(),
+        &info,
+        Duration::from_secs(BROADCAST_EXPIRES),
+        Some(tx),
+    );
+    if let Err(err) = CmdNotify::broadcast(device_id, channel_id, &info.source_id).await {
+        general::cache::Cache::state_remove(&key);
+        return Err(err);
+    }
+    match timeout(Duration::from_secs(BROADCAST_EXPIRES), rx.recv()).await {
+        Ok(Some(Some(bytes))) => {
+            Ok(serde_json::from_slice::<BroadcastInfo>(&bytes).hand_log(|msg| error!("{msg}"))?)
+        }
+        _ => {
+            general::cache::Cache::state_remove(&key);
+            Err(GlobalError::new_biz_error(
+                1100,
+                "设备未发起语音广播会话",
+                |msg| error!("{msg}"),
+            ))
+        }
+    }
+}
+
+pub async fn broadcast_stop(broadcast_model: BroadcastModel, _token: String) -> GlobalResult<bool> {
+    let device_id = broadcast_model.get_device_id();
+    let channel_id = broadcast_model
+        .get_channel_id()
+        .as_ref()
+        .unwrap_or(device_id);
+    let key = format!("{KEY_BROADCAST}{channel_id}");
+    let (bytes, _) = general::cache::Cache::state_get(&key).ok_or_else(|| {
+        GlobalError::new_biz_error(1100, "语音广播不存在", |msg| error!("{msg}"))
+    })?;
+    general::cache::Cache::state_remove(&key);
+    let info: BroadcastInfo = serde_json::from_slice(&bytes).hand_log(|msg| error!("{msg}"))?;
+    if Dialog::contains(&info.call_id) {
+        CmdStream::play_bye(info.call_id, device_id, channel_id).await?;
+    }
+    Ok(true)
+}
+
+pub async fn device_status(
+    device_id: &String,
+    timeout: Option<u8>,
+    _token: String,
+) -> GlobalResult<DeviceStatusInfo> {
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let expires = Duration::from_secs(timeout.unwrap_or(EXPIRES as u8) as u64);
+    CmdQuery::query_device_status(device_id, expires).await
+}
+
+pub async fn config_download(
+    config_query_model: ConfigQueryModel,
+    _token: String,
+) -> GlobalResult<ConfigDownloadInfo> {
+    let device_id = config_query_model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let config_types = config_query_model.get_config_types();
+    if config_types.is_empty()
+        || config_types
+            .iter()
+            .any(|tp| !CONFIG_TYPES.contains(&tp.as_str()))
+    {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            &format!("配置类型需为{:?}", CONFIG_TYPES),
+            |msg| error!("{msg}"),
+        ));
+    }
+    let expires =
+        Duration::from_secs(config_query_model.get_timeout().unwrap_or(EXPIRES as u8) as u64);
+    CmdQuery::query_config_download(device_id, config_types, expires).await
+}
+
+pub async fn catalog_sync(
+    model: CatalogSyncModel,
+    _token: String,
+) -> GlobalResult<CatalogSyncResult> {
+    let device_id = model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let expires = Duration::from_secs(model.get_timeout().unwrap_or(CATALOG_SYNC_EXPIRES) as u64);
+    CmdQuery::sync_device_catalog(device_id, expires).await
+}
+
+pub async fn subscribe_alarm(model: AlarmSubscribeModel, _token: String) -> GlobalResult<bool> {
+    if !RWSession::has_session_by_device_id(model.get_device_id()) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    CmdSubscribe::subscribe_alarm(&model).await?;
+    Ok(true)
+}
+
+pub async fn subscribe_mobile_position(
+    model: MobilePositionSubscribeModel,
+    _token: String,
+) -> GlobalResult<bool> {
+    let device_id = model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    CmdSubscribe::subscribe_mobile_position(
+        device_id,
+        model.get_interval().unwrap_or(POSITION_INTERVAL),
+        *model.get_expires(),
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn subscribe_cancel(model: SubscribeCancelModel, _token: String) -> GlobalResult<bool> {
+    if !SUBSCRIBE_KINDS.contains(&model.get_kind().as_str()) {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            &format!("订阅类型需为{:?}", SUBSCRIBE_KINDS),
+            |msg| error!("{msg}"),
+        ));
+    }
+    Ok(CmdSubscribe::unsubscribe(model.get_device_id(), model.get_kind()).await)
+}
+
+pub async fn position_history(
+    model: PositionQueryModel,
+    _token: String,
+) -> GlobalResult<Vec<MobilePosition>> {
+    let (st, et) = (to_time(*model.get_st())?, to_time(*model.get_et())?);
+    if st > et {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "开始时间不能大于结束时间",
+            |msg| error!("{msg}"),
+        ));
+    }
+    let ls = GbsDevicePosition::query_gbs_device_position(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        st,
+        et,
+        model.get_limit().unwrap_or(POSITION_LIMIT),
+    )
+    .await?;
+    Ok(ls.iter().map(|pos| pos.to_model()).collect())
+}
+
+fn to_time(ts: u32) -> GlobalResult<NaiveDateTime> {
+    Local
+        .timestamp_opt(ts as i64, 0)
+        .single()
+        .map(|dt| dt.naive_local())
+        .ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "时间参数无效", |msg| error!("{msg}"))
+        })
+}
+
+pub async fn alarm_list(model: AlarmQueryModel, _token: String) -> GlobalResult<AlarmPage> {
+    let st = model.get_st().map(to_time).transpose()?;
+    let et = model.get_et().map(to_time).transpose()?;
+    if let (Some(st), Some(et)) = (st, et) {
+        if st > et {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "开始时间不能大于结束时间",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    let size = model.get_size().unwrap_or(ALARM_PAGE_SIZE);
+    //page无上限,按u64计算避免溢出
+    let offset =
+        (model.get_page().unwrap_or(1).saturating_sub(1) as u64).saturating_mul(size as u64);
+    let (total, ls) = GbsDeviceAlarm::query_gbs_device_alarms(&model, st, et, offset, size).await?;
+    Ok(AlarmPage {
+        total,
+        list: ls.iter().map(|alarm| alarm.to_model()).collect(),
+    })
+}
+
+//确认告警：需复位时先向设备下发ResetAlarm,成功后再确认
+pub async fn alarm_ack(model: AlarmAckModel, _token: String) -> GlobalResult<bool> {
+    let alarm = GbsDeviceAlarm::query_gbs_device_alarm_by_id(*model.get_alarm_id())
+        .await?
+        .ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "告警不存在", |msg| error!("{msg}"))
+        })?;
+    if model.get_reset().unwrap_or_default() {
+        let device_id = alarm.get_device_id();
+        //告警通道为设备本身时复位设备
+        let channel_id = Some(alarm.get_channel_id()).filter(|id| *id != device_id);
+        let cmd = DeviceControlCmd::ResetAlarm {
+            alarm_method: Some(*alarm.get_method()).filter(|method| *method != 0),
+            alarm_type: Some(*alarm.get_alarm_type()).filter(|alarm_type| *alarm_type != 0),
+        };
+        device_control(device_id, channel_id, &cmd, *model.get_timeout()).await?;
+    }
+    GbsDeviceAlarm::ack_gbs_device_alarm(*alarm.get_id()).await?;
+    Ok(true)
+}
+
+pub async fn device_config(
+    device_config_model: DeviceConfigModel,
+    _token: String,
+) -> GlobalResult<bool> {
+    let device_id = device_config_model.get_device_id();
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let expires =
+        Duration::from_secs(device_config_model.get_timeout().unwrap_or(EXPIRES as u8) as u64);
+    CmdControl::control_device_config(&device_config_model, expires).await?;
+    Ok(true)
+}
+
 pub async fn seek(seek_mode: PlaySeekModel, _token: String) -> GlobalResult<bool> {
     let (device_id, channel_id, _ssrc) = id_builder::de_stream_id(seek_mode.get_stream_id());
     let (call_id, seq, from_tag, to_tag) =
@@ -257,34 +865,391 @@ pub async fn speed(speed_mode: PlaySpeedModel, _token: String) -> GlobalResult<b
     Ok(true)
 }
 
+pub async fn pause(pause_mode: PlayStreamModel, _token: String) -> GlobalResult<bool> {
+    stream_info(pause_mode.get_stream_id(), &CmdStream::pause_body()).await
+}
+
+pub async fn replay(replay_mode: PlayStreamModel, _token: String) -> GlobalResult<bool> {
+    stream_info(replay_mode.get_stream_id(), &CmdStream::replay_body()).await
+}
+
+//按stream_id查找点播对话并下发MANSRTSP INFO
+async fn stream_info(stream_id: &String, body: &str) -> GlobalResult<bool> {
+    let (device_id, channel_id, _ssrc) = id_builder::de_stream_id(stream_id);
+    let (call_id, seq, from_tag, to_tag) =
+        general::cache::Cache::stream_map_build_call_id_seq_from_to_tag(stream_id).ok_or_else(
+            || GlobalError::new_biz_error(1100, "流不存在", |msg| error!("{msg}")),
+        )?;
+    CmdStream::play_info(
+        &device_id,
+        &channel_id,
+        body,
+        &from_tag,
+        &to_tag,
+        seq,
+        call_id,
+    )
+    .await?;
+    Ok(true)
+}
+
+//移动时长为0时持续移动；否则到时后下发停止指令
 pub async fn ptz(ptz_control_model: PtzControlModel, _token: String) -> GlobalResult<bool> {
     CmdControl::control_ptz(&ptz_control_model).await?;
-    let mut model = PtzControlModel::default();
-    model.set_device_id(ptz_control_model.get_device_id());
-    sleep(Duration::from_millis(1000)).await;
-    model.set_channel_id(ptz_control_model.get_channel_id());
-    CmdControl::control_ptz(&model).await?;
+    let duration = ptz_control_model.get_duration().unwrap_or(PTZ_DURATION);
+    if duration > 0 {
+        let mut model = PtzControlModel::default();
+        model.set_device_id(ptz_control_model.get_device_id().to_string());
+        model.set_channel_id(ptz_control_model.get_channel_id().to_string());
+        common::tokio::spawn(async move {
+            sleep(Duration::from_millis(duration as u64)).await;
+            let _ = CmdControl::control_ptz(&model).await;
+        });
+    }
     Ok(true)
 }
 
+pub async fn ptz_fi(ptz_fi_model: PtzFiModel, _token: String) -> GlobalResult<bool> {
+    let device_id = ptz_fi_model.get_device_id().to_string();
+    let channel_id = ptz_fi_model.get_channel_id().to_string();
+    CmdControl::control_ptz_cmd(&device_id, &channel_id, &ptz_fi_model.to_cmd()).await?;
+    let duration = ptz_fi_model.get_duration().unwrap_or(PTZ_DURATION);
+    if duration > 0 {
+        common::tokio::spawn(async move {
+            sleep(Duration::from_millis(duration as u64)).await;
+            let _ = CmdControl::control_ptz_cmd(&device_id, &channel_id, &PtzCmd::Stop).await;
+        });
+    }
+    Ok(true)
+}
+
+pub async fn ptz_preset(ptz_preset_model: PtzPresetModel, _token: String) -> GlobalResult<bool> {
+    let cmd = ptz_preset_model
+        .to_cmd()
+        .map_err(|err| GlobalError::new_biz_error(1100, &err.to_string(), |msg| error!("{msg}")))?;
+    CmdControl::control_ptz_cmd(
+        ptz_preset_model.get_device_id(),
+        ptz_preset_model.get_channel_id(),
+        &cmd,
+    )
+    .await?;
+    //设置/删除后重新查询,刷新预置位列表
+    if !matches!(cmd, PtzCmd::PresetCall(_)) {
+        let _ = CmdQuery::query_preset(
+            ptz_preset_model.get_device_id(),
+            Some(ptz_preset_model.get_channel_id()),
+        )
+        .await;
+    }
+    Ok(true)
+}
+
+pub async fn preset_list(
+    device_id: &String,
+    channel_id: &String,
+    _token: String,
+) -> GlobalResult<Vec<PresetInfo>> {
+    let ls = GbsDevicePreset::query_gbs_device_preset(device_id, channel_id).await?;
+    Ok(ls
+        .into_iter()
+        .map(|preset| PresetInfo {
+            preset_id: *preset.get_preset_id(),
+            preset_name: preset.get_preset_name().clone(),
+        })
+        .collect())
+}
+
+pub async fn ptz_cruise(ptz_cruise_model: PtzCruiseModel, _token: String) -> GlobalResult<bool> {
+    let cmd = ptz_cruise_model
+        .to_cmd()
+        .map_err(|err| GlobalError::new_biz_error(1100, &err.to_string(), |msg| error!("{msg}")))?;
+    CmdControl::control_ptz_cmd(
+        ptz_cruise_model.get_device_id(),
+        ptz_cruise_model.get_channel_id(),
+        &cmd,
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn ptz_scan(ptz_scan_model: PtzScanModel, _token: String) -> GlobalResult<bool> {
+    let cmd = ptz_scan_model
+        .to_cmd()
+        .map_err(|err| GlobalError::new_biz_error(1100, &err.to_string(), |msg| error!("{msg}")))?;
+    CmdControl::control_ptz_cmd(
+        ptz_scan_model.get_device_id(),
+        ptz_scan_model.get_channel_id(),
+        &cmd,
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn ptz_aux(ptz_aux_model: PtzAuxModel, _token: String) -> GlobalResult<bool> {
+    CmdControl::control_ptz_cmd(
+        ptz_aux_model.get_device_id(),
+        ptz_aux_model.get_channel_id(),
+        &ptz_aux_model.to_cmd(),
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn tele_boot(model: DeviceControlModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        &DeviceControlCmd::TeleBoot,
+        *model.get_timeout(),
+    )
+    .await
+}
+
+pub async fn record_control(model: RecordControlModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        &model.to_cmd(),
+        *model.get_timeout(),
+    )
+    .await
+}
+
+pub async fn guard_control(model: GuardControlModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        &model.to_cmd(),
+        *model.get_timeout(),
+    )
+    .await
+}
+
+pub async fn alarm_reset(model: AlarmResetModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        &model.to_cmd(),
+        *model.get_timeout(),
+    )
+    .await
+}
+
+pub async fn iframe(model: DeviceControlModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        model.get_channel_id().as_ref(),
+        &DeviceControlCmd::IFame,
+        *model.get_timeout(),
+    )
+    .await
+}
+
+pub async fn drag_zoom(model: DragZoomModel, _token: String) -> GlobalResult<bool> {
+    device_control(
+        model.get_device_id(),
+        Some(model.get_channel_id()),
+        &model.to_cmd(),
+        None,
+    )
+    .await
+}
+
+pub async fn cascade_share_list(
+    platform_id: &String,
+    _token: String,
+) -> GlobalResult<CascadeShareInfo> {
+    let nodes = GbsCascadeNode::query_gbs_cascade_nodes(platform_id).await?;
+    let channels = GbsCascadeChannel::query_gbs_cascade_channels(platform_id).await?;
+    Ok(CascadeShareInfo {
+        nodes: nodes.iter().map(GbsCascadeNode::to_model).collect(),
+        channels: channels.iter().map(GbsCascadeChannel::to_model).collect(),
+    })
+}
+
+pub async fn cascade_node_save(model: CascadeNodeModel, _token: String) -> GlobalResult<bool> {
+    cascade_platform_check(model.get_platform_id())?;
+    let node = GbsCascadeNode::build(&model);
+    if NodeKind::of(node.get_node_id()).is_none() {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            "节点编码须为行政区划、业务分组(215)或虚拟组织(216)编码",
+            |msg| error!("{msg}"),
+        ));
+    }
+    let nodes = GbsCascadeNode::query_gbs_cascade_nodes(node.get_platform_id()).await?;
+    if let Some(parent_id) = node.get_parent_id() {
+        if parent_id == node.get_node_id()
+            || !nodes.iter().any(|item| item.get_node_id() == parent_id)
+        {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "上级节点不存在",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    if let Some(business_group_id) = node.get_business_group_id() {
+        if NodeKind::of(business_group_id) != Some(NodeKind::BusinessGroup)
+            || !nodes
+                .iter()
+                .any(|item| item.get_node_id() == business_group_id)
+        {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "业务分组不存在",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    node.save_gbs_cascade_node().await?;
+    Ok(true)
+}
+
+pub async fn cascade_node_remove(
+    model: CascadeNodeRemoveModel,
+    _token: String,
+) -> GlobalResult<bool> {
+    //节点下仍有子节点或共享通道时拒绝删除,避免其在目录中悬空
+    let (nodes, channels) =
+        GbsCascadeNode::count_gbs_cascade_node_refs(model.get_platform_id(), model.get_node_id())
+            .await?;
+    if nodes > 0 || channels > 0 {
+        return Err(GlobalError::new_biz_error(
+            1100,
+            &format!("节点下仍有{nodes}个子节点、{channels}个共享通道,请先移除"),
+            |msg| error!("{msg}"),
+        ));
+    }
+    GbsCascadeNode::delete_gbs_cascade_node(model.get_platform_id(), model.get_node_id()).await?;
+    Ok(true)
+}
+
+//共享通道：上报编码在同一上级平台内唯一
+pub async fn cascade_channel_save(
+    model: CascadeChannelModel,
+    _token: String,
+) -> GlobalResult<bool> {
+    cascade_platform_check(model.get_platform_id())?;
+    let share = GbsCascadeChannel::build(&model);
+    GbsDeviceChannel::query_gbs_device_channel(share.get_device_id(), share.get_channel_id())
+        .await?
+        .ok_or_else(|| {
+            GlobalError::new_biz_error(1100, "通道不存在", |msg| error!("{msg}"))
+        })?;
+    if let Some(share_id) = share.get_share_id() {
+        if share_id.len() != 20 || !share_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "上报编码须为20位数字",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    if let Some(other) = GbsCascadeChannel::query_gbs_cascade_channel_by_shared_id(
+        share.get_platform_id(),
+        share.shared_id(),
+    )
+    .await?
+    {
+        if other.get_device_id() != share.get_device_id()
+            || other.get_channel_id() != share.get_channel_id()
+        {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "上报编码已被其他共享通道使用",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    if let Some(parent_id) = share.get_parent_id() {
+        let nodes = GbsCascadeNode::query_gbs_cascade_nodes(share.get_platform_id()).await?;
+        if !nodes.iter().any(|node| node.get_node_id() == parent_id) {
+            return Err(GlobalError::new_biz_error(
+                1100,
+                "所属节点不存在",
+                |msg| error!("{msg}"),
+            ));
+        }
+    }
+    share.save_gbs_cascade_channel().await?;
+    Ok(true)
+}
+
+pub async fn cascade_channel_remove(
+    model: CascadeChannelRemoveModel,
+    _token: String,
+) -> GlobalResult<bool> {
+    GbsCascadeChannel::delete_gbs_cascade_channel(
+        model.get_platform_id(),
+        model.get_device_id(),
+        model.get_channel_id(),
+    )
+    .await?;
+    Ok(true)
+}
+
+fn cascade_platform_check(platform_id: &String) -> GlobalResult<()> {
+    Cascade::platform(platform_id).map(|_| ()).ok_or_else(|| {
+        GlobalError::new_biz_error(1100, "未配置的上级平台", |msg| error!("{msg}"))
+    })
+}
+
+async fn device_control(
+    device_id: &String,
+    channel_id_opt: Option<&String>,
+    cmd: &DeviceControlCmd,
+    timeout: Option<u8>,
+) -> GlobalResult<bool> {
+    if !RWSession::has_session_by_device_id(device_id) {
+        return Err(GlobalError::new_biz_error(1000, "设备已离线", |msg| {
+            error!("{msg}")
+        }));
+    }
+    let expires = Duration::from_secs(timeout.unwrap_or(EXPIRES as u8) as u64);
+    CmdControl::control_device(device_id, channel_id_opt, cmd, expires).await?;
+    Ok(true)
+}
+
+//建流失败时归还ssrc；建流成功后由stream_end归还
+struct SsrcGuard(Option<u16>);
+
+impl SsrcGuard {
+    fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for SsrcGuard {
+    fn drop(&mut self) {
+        if let Some(sn) = self.0.take() {
+            general::cache::Cache::ssrc_sn_set(sn);
+        }
+    }
+}
+
 //选择流媒体节点（可用+负载最小）-> 监听流注册
 //发起实时点播 -> 监听设备响应
 //缓存流信息
 async fn start_invite_stream(
     device_id: &String,
     channel_id: &String,
-    _token: &str,
+    token: &str,
     play_type: PlayType,
+    stream_mode: StreamMode,
     range: TimeRange,
+    download_speed: u8,
 ) -> GlobalResult<(String, String)> {
     let ssrc = general::cache::Cache::ssrc_sn_get().ok_or_else(|| {
         GlobalError::new_biz_error(1100, "ssrc已用完,并发达上限,等待释放", |msg| {
             error!("{msg}")
         })
     })?;
+    let ssrc_guard = SsrcGuard(Some(ssrc));
     let mut node_sets = general::cache::Cache::stream_map_order_node();
     let (ssrc, stream_id) =
-        id_builder::build_ssrc_stream_id(device_id, channel_id, ssrc, true).await?;
+        id_builder::build_ssrc_stream_id(device_id, channel_id, ssrc, play_type == PlayType::Live)
+            .await?;
     let conf = general::StreamConf::get_stream_conf();
     //TODO: 选择负载最小的节点开始尝试：节点是否可用;
     if let Some((_, node_name)) = node_sets.pop_first() {
@@ -299,7 +1264,7 @@ async fn start_invite_stream(
         // )
         // .await
         {
-            let (res, _media_map, from_tag, to_tag) = match play_type {
+            let (res, _media_map) = match play_type {
                 PlayType::Live => {
                     CmdStream::play_live_invite(
                         device_id,
@@ -308,7 +1273,7 @@ async fn start_invite_stream(
                             stream_node.get_pub_ip().to_string(),
                             *stream_node.get_pub_port(),
                         ),
-                        StreamMode::Udp,
+                        stream_mode,
                         &ssrc,
                     )
                     .await?
@@ -321,12 +1286,27 @@ async fn start_invite_stream(
                             stream_node.get_pub_ip().to_string(),
                             *stream_node.get_pub_port(),
                         ),
-                        StreamMode::Udp,
+                        stream_mode,
+                        &ssrc,
+                        range,
+                    )
+                    .await?
+                }
+                PlayType::Down => {
+                    CmdStream::download_invite(
+                        device_id,
+                        channel_id,
+                        MediaAddress::build(
+                            stream_node.get_pub_ip().to_string(),
+                            *stream_node.get_pub_port(),
+                        ),
+                        stream_mode,
                         &ssrc,
                         range,
+                        download_speed,
                     )
                     .await?
-                } // PlayType::Down => {}
+                }
             };
 
             //回调给zlm 使其确认媒体类型
@@ -338,7 +1318,43 @@ async fn start_invite_stream(
             //     stream_node.get_local_port(),
             // )
             // .await;
-            let (call_id, seq) = CmdStream::invite_ack(device_id, &res)?;
+            let call_id = CmdStream::invite_ack(device_id, &res)?;
+            //设备应答的传输方式与请求不一致时,流无法建立,直接挂断
+            if let Err(err) = stream_mode.check_answer(res.body()) {
+                //挂断失败仅记录,返回建流失败的原因
+                let _ = CmdStream::play_bye(call_id, device_id, channel_id)
+                    .await
+                    .hand_log(|msg| error!("{msg}"));
+                return Err(GlobalError::new_biz_error(1100, &err.to_string(), |msg| {
+                    error!("{msg}")
+                }));
+            }
+            //TCP主动：设备作为服务端监听,由流媒体节点发起连接
+            if stream_mode == StreamMode::TcpActive {
+                let connected = match StreamMode::answer_address(res.body()) {
+                    Ok(remote) => callback::call_connect_rtp(
+                        &stream_id,
+                        &ssrc,
+                        remote,
+                        token,
+                        stream_node.get_local_ip(),
+                        stream_node.get_local_port(),
+                    )
+                    .await
+                    .unwrap_or(false),
+                    Err(_) => false,
+                };
+                if !connected {
+                    let _ = CmdStream::play_bye(call_id, device_id, channel_id)
+                        .await
+                        .hand_log(|msg| error!("{msg}"));
+                    return Err(GlobalError::new_biz_error(
+                        1100,
+                        "流媒体节点连接设备失败",
+                        |msg| error!("{msg}"),
+                    ));
+                }
+            }
             return if let Some(_base_stream_info) =
                 listen_stream_by_stream_id(&stream_id, RELOAD_EXPIRES).await
             {
@@ -346,10 +1362,8 @@ async fn start_invite_stream(
                     stream_id.clone(),
                     node_name.clone(),
                     call_id,
-                    seq,
                     play_type,
-                    from_tag,
-                    to_tag,
+                    stream_mode,
                 );
                 general::cache::Cache::device_map_insert(
                     device_id.to_string(),
@@ -358,10 +1372,12 @@ async fn start_invite_stream(
                     stream_id.clone(),
                     play_type,
                 );
+                ssrc_guard.keep();
                 Ok((stream_id, node_name))
             } else {
-                CmdStream::play_bye(seq + 1, call_id, device_id, channel_id, &from_tag, &to_tag)
-                    .await?;
+                let _ = CmdStream::play_bye(call_id, device_id, channel_id)
+                    .await
+                    .hand_log(|msg| error!("{msg}"));
                 Err(GlobalError::new_biz_error(
                     1100,
                     "未接收到监控推流",
@@ -377,6 +1393,51 @@ async fn start_invite_stream(
     ))
 }
 
+//关闭设备推流：发送BYE,清理stream_map/device_map,返回流的点播类型
+async fn teardown_stream(stream_id: &String) -> Option<PlayType> {
+    let (device_id, channel_id, ssrc) = id_builder::de_stream_id(stream_id);
+    //设备已主动挂断时对话不存在,无需再发送BYE
+    if let Some(call_id) = general::cache::Cache::stream_map_query_call_id_by_stream_id(stream_id)
+        .filter(|call_id| Dialog::contains(call_id))
+    {
+        let _ = CmdStream::play_bye(call_id, &device_id, &channel_id).await;
+    }
+    let play_type = general::cache::Cache::stream_map_query_play_type_by_stream_id(stream_id);
+    if let Some(play_type) = play_type {
+        general::cache::Cache::device_map_remove(
+            &device_id,
+            Some((&channel_id, Some((play_type, &ssrc)))),
+        );
+        general::cache::Cache::stream_map_remove(stream_id, None);
+    }
+    play_type
+}
+
+//更新下载任务为结束状态,保留一段时间供查询
+fn download_end(stream_id: &String, state: u8) {
+    let key = format!("{KEY_DOWNLOAD}{stream_id}");
+    if let Some((bytes, _)) = general::cache::Cache::state_remove(&key) {
+        if let Ok(mut info) = serde_json::from_slice::<DownloadInfo>(&bytes) {
+            if info.state == DownloadInfo::DOWNLOADING {
+                let now = Local::now().timestamp();
+                info.progress = if state == DownloadInfo::FINISHED {
+                    100
+                } else {
+                    info.estimate_progress(now)
+                };
+                info.state = state;
+                info.end_ts = Some(now);
+            }
+            general::cache::Cache::state_insert_obj_by_timer(
+                key,
+                &info,
+                Duration::from_secs(DOWNLOAD_RETAIN),
+                None,
+            );
+        }
+    }
+}
+
 //首先查看session缓存中是否有映射关系,然后看stream中是否有相应数据:都为true时返回数据
 //当session有,stream无时：session调用stream->使其重新监听ssrc
 //(避免stream重启后,数据不一致)
@@ -453,7 +1514,7 @@ async fn listen_stream_by_stream_id(stream_id: &String, secs: u64) -> Option<Bas
 
 #[cfg(test)]
 mod test {
-    use common::chrono::Local;
+    use common::chrono::{Local, TimeZone};
     use common::tokio;
     use common::tokio::sync::mpsc;
     use common::tokio::time::{sleep_until, Instant};
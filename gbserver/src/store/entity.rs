@@ -1,5 +1,8 @@
-use crate::gb::handler::parser;
-use common::chrono::{Local, NaiveDateTime};
+use crate::gb::handler::{manscdp, parser};
+use crate::general::model::{
+    AlarmInfo, AlarmQueryModel, CascadeChannelModel, CascadeNodeModel, MobilePosition, TimeRange,
+};
+use common::chrono::{Local, NaiveDateTime, TimeZone};
 use common::constructor::{Get, Set};
 use common::dbx::mysqlx::get_conn_by_pool;
 use common::exception::{GlobalResult, TransError};
@@ -118,8 +121,10 @@ pub struct GbsDeviceExt {
 }
 
 impl GbsDeviceExt {
-    pub async fn update_gbs_device_ext_info(vs: Vec<(String, String)>) -> GlobalResult<()> {
-        let ext = Self::build(vs);
+    pub async fn update_gbs_device_ext_info(
+        info: &manscdp::DeviceInfoResponse,
+    ) -> GlobalResult<()> {
+        let ext = Self::build(info);
         let pool = get_conn_by_pool()?;
         sqlx::query("update gb_device_list set device_type=?,manufacturer=?,model=?,firmware=?,max_camera=? where device_id=?")
             .bind(ext.device_type)
@@ -133,34 +138,15 @@ impl GbsDeviceExt {
         Ok(())
     }
 
-    fn build(vs: Vec<(String, String)>) -> GbsDeviceExt {
-        use crate::gb::handler::parser::xml::*;
-
-        let mut de = GbsDeviceExt::default();
-        for (k, v) in vs {
-            match &k[..] {
-                RESPONSE_DEVICE_ID => {
-                    de.device_id = v.to_string();
-                }
-                RESPONSE_MANUFACTURER => {
-                    de.manufacturer = v.to_string();
-                }
-                RESPONSE_MODEL => {
-                    de.model = v.to_string();
-                }
-                RESPONSE_FIRMWARE => {
-                    de.firmware = v.to_string();
-                }
-                RESPONSE_DEVICE_TYPE => {
-                    de.device_type = Some(v.to_string());
-                }
-                RESPONSE_MAX_CAMERA => {
-                    de.max_camera = v.parse::<u8>().ok();
-                }
-                _ => {}
-            }
+    fn build(info: &manscdp::DeviceInfoResponse) -> GbsDeviceExt {
+        GbsDeviceExt {
+            device_id: info.device_id.clone(),
+            device_type: info.device_type.clone(),
+            manufacturer: info.manufacturer.clone(),
+            model: info.model.clone(),
+            firmware: info.firmware.clone(),
+            max_camera: info.max_camera,
         }
-        de
     }
 }
 
@@ -192,14 +178,142 @@ pub struct GbsDeviceChannel {
 impl GbsDeviceChannel {
     pub async fn insert_gbs_device_channel(
         device_id: &String,
-        vs: Vec<(String, String)>,
+        list: &manscdp::CatalogList,
     ) -> GlobalResult<Vec<GbsDeviceChannel>> {
-        let dc_ls = Self::build(device_id, vs);
+        let dc_ls = Self::build(device_id, list);
+        Self::upsert_gbs_device_channel(&dc_ls).await?;
+        Ok(dc_ls)
+    }
+
+    //目录变更通知：ADD/UPDATE写入,DEL删除,ON/OFF/VLOST/DEFECT仅更新状态
+    pub async fn apply_catalog_notify(
+        device_id: &String,
+        list: &manscdp::CatalogList,
+    ) -> GlobalResult<()> {
+        let mut upsert_ls = Vec::new();
+        for (event, dc) in Self::build_with_event(device_id, list) {
+            match &event.to_ascii_uppercase()[..] {
+                "DEL" => Self::delete_gbs_device_channel(device_id, &dc.channel_id).await?,
+                "ON" => {
+                    Self::update_gbs_device_channel_status(device_id, &dc.channel_id, "ON").await?
+                }
+                "OFF" | "VLOST" | "DEFECT" => {
+                    Self::update_gbs_device_channel_status(device_id, &dc.channel_id, "OFF").await?
+                }
+                _ => upsert_ls.push(dc),
+            }
+        }
+        Self::upsert_gbs_device_channel(&upsert_ls).await
+    }
+
+    pub async fn delete_gbs_device_channel(
+        device_id: &String,
+        channel_id: &String,
+    ) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("delete from gb_device_channel_list where device_id=? and channel_id=?")
+            .bind(device_id)
+            .bind(channel_id)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    pub async fn update_gbs_device_channel_status(
+        device_id: &String,
+        channel_id: &String,
+        status: &str,
+    ) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query(
+            "update gb_device_channel_list set status=? where device_id=? and channel_id=?",
+        )
+        .bind(status)
+        .bind(device_id)
+        .bind(channel_id)
+        .execute(pool)
+        .await
+        .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    pub async fn query_gbs_device_channel_ids(device_id: &String) -> GlobalResult<Vec<String>> {
+        let pool = get_conn_by_pool()?;
+        let res: Vec<(String,)> =
+            sqlx::query_as("select channel_id from gb_device_channel_list where device_id=?")
+                .bind(device_id)
+                .fetch_all(pool)
+                .await
+                .hand_log(|msg| error!("{msg}"))?;
+        Ok(res.into_iter().map(|(channel_id,)| channel_id).collect())
+    }
+
+    //级联：共享给上级平台的通道
+    pub async fn query_shared_gbs_device_channels(
+        platform_id: &String,
+    ) -> GlobalResult<Vec<GbsDeviceChannel>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsDeviceChannel>("select c.device_id,c.channel_id,c.name,c.manufacturer,c.model,c.owner,c.status,c.civil_code,c.address,c.parental,c.block,
+        c.parent_id,c.ip_address,c.port,c.password,c.longitude,c.latitude,c.ptz_type,c.supply_light_type,c.alias_name from gb_device_channel_list c
+        inner join gb_cascade_channel s on s.device_id=c.device_id and s.channel_id=c.channel_id where s.platform_id=? order by c.device_id,c.channel_id")
+            .bind(platform_id)
+            .fetch_all(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    pub async fn query_gbs_device_channel(
+        device_id: &String,
+        channel_id: &String,
+    ) -> GlobalResult<Option<GbsDeviceChannel>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsDeviceChannel>("select device_id,channel_id,name,manufacturer,model,owner,status,civil_code,address,parental,block,parent_id,
+        ip_address,port,password,longitude,latitude,ptz_type,supply_light_type,alias_name from gb_device_channel_list where device_id=? and channel_id=?")
+            .bind(device_id)
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    //设备目录中已不存在的通道置为离线,保留历史记录及共享、告警等关联
+    pub async fn offline_gbs_device_channels(
+        device_id: &String,
+        channel_ids: &[String],
+    ) -> GlobalResult<()> {
+        if channel_ids.is_empty() {
+            return Ok(());
+        }
+        let pool = get_conn_by_pool()?;
+        let mut builder = sqlx::query_builder::QueryBuilder::new(
+            "update gb_device_channel_list set status='OFF' where device_id=",
+        );
+        builder.push_bind(device_id).push(" and channel_id in (");
+        let mut separated = builder.separated(",");
+        for channel_id in channel_ids {
+            separated.push_bind(channel_id);
+        }
+        separated.push_unseparated(")");
+        builder
+            .build()
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    pub async fn upsert_gbs_device_channel(dc_ls: &[GbsDeviceChannel]) -> GlobalResult<()> {
+        if dc_ls.is_empty() {
+            return Ok(());
+        }
         let pool = get_conn_by_pool()?;
         let mut builder = sqlx::query_builder::QueryBuilder::new("INSERT INTO gb_device_channel_list (device_id, channel_id, name, manufacturer,
          model, owner, status, civil_code, address, parental, block, parent_id, ip_address, port,password,
          longitude,latitude,ptz_type,supply_light_type,alias_name) ");
-        builder.push_values(&dc_ls, |mut b, dc| {
+        builder.push_values(dc_ls, |mut b, dc| {
             b.push_bind(&dc.device_id)
                 .push_bind(&dc.channel_id)
                 .push_bind(&dc.name)
@@ -231,81 +345,610 @@ impl GbsDeviceChannel {
             .execute(pool)
             .await
             .hand_log(|msg| error!("{msg}"))?;
-        Ok(dc_ls)
+        Ok(())
     }
-    fn build(device_id: &String, vs: Vec<(String, String)>) -> Vec<GbsDeviceChannel> {
-        use crate::gb::handler::parser::xml::*;
-        let mut dc = GbsDeviceChannel::default();
-        dc.set_device_id(device_id);
-        let mut dcs: Vec<GbsDeviceChannel> = Vec::new();
-        for (k, v) in vs {
-            match &k[..] {
-                RESPONSE_DEVICE_LIST_ITEM_DEVICE_ID => {
-                    dc.channel_id = v.to_string();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_NAME => {
-                    dc.name = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_MANUFACTURER => {
-                    dc.manufacturer = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_MODEL => {
-                    dc.model = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_OWNER => {
-                    dc.owner = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_CIVIL_CODE => {
-                    dc.civil_code = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_BLOCK => {
-                    dc.block = Some(v.to_string());
-                }
-                RESPONSE_DEVICE_LIST_ITEM_ADDRESS => {
-                    dc.address = v.parse::<String>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_PARENTAL => {
-                    dc.parental = v.parse::<u8>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_PARENT_ID => {
-                    dc.parent_id = Some(v.to_string());
-                }
-                RESPONSE_DEVICE_LIST_ITEM_LONGITUDE => {
-                    dc.longitude = v.parse::<f32>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_LATITUDE => {
-                    dc.latitude = v.parse::<f32>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_PTZ_TYPE => {
-                    dc.ptz_type = v.parse::<u8>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_SUPPLY_LIGHT_TYPE => {
-                    dc.supply_light_type = v.parse::<u8>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_IP_ADDRESS => {
-                    dc.ip_address = Some(v.to_string());
-                }
-                RESPONSE_DEVICE_LIST_ITEM_PORT => {
-                    dc.port = v.parse::<u16>().ok();
-                }
-                RESPONSE_DEVICE_LIST_ITEM_PASSWORD => {
-                    dc.password = Some(v.to_string());
-                }
-                RESPONSE_DEVICE_LIST_ITEM_STATUS => {
-                    dc.status = v.to_string();
-                }
-                SPLIT_CLASS if "4".eq(&v) => {
-                    if !dc.channel_id.is_empty() {
-                        dcs.push(dc.clone());
-                        dc = GbsDeviceChannel::default();
-                        dc.device_id = device_id.to_string();
-                    }
+    pub fn build(device_id: &String, list: &manscdp::CatalogList) -> Vec<GbsDeviceChannel> {
+        Self::build_with_event(device_id, list)
+            .into_iter()
+            .map(|(_, dc)| dc)
+            .collect()
+    }
+
+    //目录条目及其变更事件,查询应答中无事件则为空串
+    fn build_with_event(
+        device_id: &String,
+        list: &manscdp::CatalogList,
+    ) -> Vec<(String, GbsDeviceChannel)> {
+        list.device_list
+            .items
+            .iter()
+            .filter(|item| !item.device_id.is_empty())
+            .map(|item| {
+                let info = item.info.as_ref();
+                let dc = GbsDeviceChannel {
+                    device_id: device_id.to_string(),
+                    channel_id: item.device_id.clone(),
+                    name: item.name.clone(),
+                    manufacturer: item.manufacturer.clone(),
+                    model: item.model.clone(),
+                    owner: item.owner.clone(),
+                    status: item.status.clone(),
+                    civil_code: item.civil_code.clone(),
+                    address: item.address.clone(),
+                    parental: item.parental,
+                    block: item.block.clone(),
+                    parent_id: item.parent_id.clone(),
+                    ip_address: item.ip_address.clone(),
+                    port: item.port,
+                    password: item.password.clone(),
+                    longitude: item.longitude,
+                    latitude: item.latitude,
+                    ptz_type: info.and_then(|info| info.ptz_type),
+                    supply_light_type: item
+                        .supply_light_type
+                        .or(info.and_then(|info| info.supply_light_type)),
+                    alias_name: None,
+                };
+                (item.event.clone().unwrap_or_default(), dc)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, Get, FromRow)]
+pub struct GbsDevicePreset {
+    device_id: String,
+    channel_id: String,
+    preset_id: u8,
+    preset_name: Option<String>,
+}
+
+impl GbsDevicePreset {
+    //设备应答为完整列表时(条目数=SumNum),同时删除设备上已不存在的预置位
+    pub async fn save_gbs_device_preset(
+        device_id: &String,
+        res: &manscdp::PresetQueryResponse,
+    ) -> GlobalResult<()> {
+        let (channel_id, sum_num, ls) = Self::build(device_id, res);
+        if channel_id.is_empty() {
+            return Ok(());
+        }
+        let pool = get_conn_by_pool()?;
+        if !ls.is_empty() {
+            let mut builder = sqlx::query_builder::QueryBuilder::new(
+                "INSERT INTO gb_device_preset_list (device_id, channel_id, preset_id, preset_name) ",
+            );
+            builder.push_values(&ls, |mut b, preset| {
+                b.push_bind(&preset.device_id)
+                    .push_bind(&preset.channel_id)
+                    .push_bind(preset.preset_id)
+                    .push_bind(&preset.preset_name);
+            });
+            builder.push(" ON DUPLICATE KEY UPDATE preset_name=VALUES(preset_name)");
+            builder
+                .build()
+                .execute(pool)
+                .await
+                .hand_log(|msg| error!("{msg}"))?;
+        }
+        if sum_num.unwrap_or(ls.len()) == ls.len() {
+            let mut builder = sqlx::query_builder::QueryBuilder::new(
+                "DELETE FROM gb_device_preset_list WHERE device_id=",
+            );
+            builder.push_bind(device_id);
+            builder.push(" AND channel_id=");
+            builder.push_bind(&channel_id);
+            if !ls.is_empty() {
+                builder.push(" AND preset_id NOT IN (");
+                let mut separated = builder.separated(",");
+                for preset in &ls {
+                    separated.push_bind(preset.preset_id);
                 }
-                &_ => {}
+                separated.push_unseparated(")");
+            }
+            builder
+                .build()
+                .execute(pool)
+                .await
+                .hand_log(|msg| error!("{msg}"))?;
+        }
+        Ok(())
+    }
+
+    pub async fn query_gbs_device_preset(
+        device_id: &String,
+        channel_id: &String,
+    ) -> GlobalResult<Vec<GbsDevicePreset>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsDevicePreset>("select device_id,channel_id,preset_id,preset_name from gb_device_preset_list where device_id=? and channel_id=? order by preset_id")
+            .bind(device_id).bind(channel_id).fetch_all(pool).await.hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    fn build(
+        device_id: &String,
+        res: &manscdp::PresetQueryResponse,
+    ) -> (String, Option<usize>, Vec<GbsDevicePreset>) {
+        let channel_id = res.device_id.clone();
+        let ls = res
+            .preset_list
+            .items
+            .iter()
+            .filter_map(|item| {
+                item.preset_id
+                    .filter(|id| *id > 0)
+                    .map(|preset_id| GbsDevicePreset {
+                        device_id: device_id.to_string(),
+                        channel_id: channel_id.clone(),
+                        preset_id,
+                        preset_name: item.preset_name.clone(),
+                    })
+            })
+            .collect();
+        (channel_id, res.sum_num.map(|n| n as usize), ls)
+    }
+}
+
+#[derive(Debug, Clone, Default, Get, FromRow)]
+pub struct GbsDevicePosition {
+    device_id: String,
+    channel_id: String,
+    time: NaiveDateTime,
+    longitude: f64,
+    latitude: f64,
+    speed: Option<f64>,
+    direction: Option<f64>,
+    altitude: Option<f64>,
+}
+
+impl GbsDevicePosition {
+    pub fn build(pos: &MobilePosition) -> Self {
+        Self {
+            device_id: pos.device_id.clone(),
+            channel_id: pos.channel_id.clone(),
+            time: Local
+                .timestamp_opt(pos.time as i64, 0)
+                .single()
+                .map(|dt| dt.naive_local())
+                .unwrap_or_else(|| Local::now().naive_local()),
+            longitude: pos.longitude,
+            latitude: pos.latitude,
+            speed: pos.speed,
+            direction: pos.direction,
+            altitude: pos.altitude,
+        }
+    }
+
+    pub fn to_model(&self) -> MobilePosition {
+        MobilePosition {
+            device_id: self.device_id.clone(),
+            channel_id: self.channel_id.clone(),
+            time: Local
+                .from_local_datetime(&self.time)
+                .single()
+                .map(|dt| dt.timestamp() as u32)
+                .unwrap_or_default(),
+            longitude: self.longitude,
+            latitude: self.latitude,
+            speed: self.speed,
+            direction: self.direction,
+            altitude: self.altitude,
+        }
+    }
+
+    pub async fn insert_gbs_device_position(&self) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("insert into gb_device_position_history (device_id,channel_id,time,longitude,latitude,speed,direction,altitude) values (?,?,?,?,?,?,?,?)")
+            .bind(&self.device_id)
+            .bind(&self.channel_id)
+            .bind(self.time)
+            .bind(self.longitude)
+            .bind(self.latitude)
+            .bind(self.speed)
+            .bind(self.direction)
+            .bind(self.altitude)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    //按时间升序返回轨迹点
+    pub async fn query_gbs_device_position(
+        device_id: &String,
+        channel_id: Option<&String>,
+        start_time: NaiveDateTime,
+        end_time: NaiveDateTime,
+        limit: u32,
+    ) -> GlobalResult<Vec<GbsDevicePosition>> {
+        let pool = get_conn_by_pool()?;
+        let mut builder = sqlx::query_builder::QueryBuilder::new("select device_id,channel_id,time,longitude,latitude,speed,direction,altitude from gb_device_position_history where device_id=");
+        builder.push_bind(device_id);
+        if let Some(channel_id) = channel_id {
+            builder.push(" and channel_id=").push_bind(channel_id);
+        }
+        builder
+            .push(" and time between ")
+            .push_bind(start_time)
+            .push(" and ")
+            .push_bind(end_time)
+            .push(" order by time limit ")
+            .push_bind(limit);
+        let res = builder
+            .build_query_as::<GbsDevicePosition>()
+            .fetch_all(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+}
+
+//设备告警记录
+#[derive(Debug, Clone, Default, Get, FromRow)]
+pub struct GbsDeviceAlarm {
+    id: i64,
+    device_id: String,
+    //告警通道：报警输入通道或设备自身
+    channel_id: String,
+    priority: u8,
+    method: u8,
+    alarm_type: u8,
+    alarm_time: NaiveDateTime,
+    description: Option<String>,
+    create_time: NaiveDateTime,
+    //确认时间,未确认为空
+    ack_time: Option<NaiveDateTime>,
+}
+
+impl GbsDeviceAlarm {
+    //告警时间无法解析时取接收时间
+    pub fn build(info: &AlarmInfo) -> Self {
+        let now = Local::now().naive_local();
+        Self {
+            id: 0,
+            device_id: info.deviceId.clone(),
+            channel_id: info.channelId.clone(),
+            priority: info.priority,
+            method: info.method,
+            alarm_type: info.alarmType,
+            alarm_time: TimeRange::parse_gb_time(&info.timeStr)
+                .and_then(|ts| Local.timestamp_opt(ts as i64, 0).single())
+                .map(|dt| dt.naive_local())
+                .unwrap_or(now),
+            description: info.description.clone(),
+            create_time: now,
+            ack_time: None,
+        }
+    }
+
+    pub fn to_model(&self) -> AlarmInfo {
+        let to_ts = |time: &NaiveDateTime| {
+            Local
+                .from_local_datetime(time)
+                .single()
+                .map(|dt| dt.timestamp())
+                .unwrap_or_default()
+        };
+        AlarmInfo {
+            priority: self.priority,
+            method: self.method,
+            alarmType: self.alarm_type,
+            timeStr: self.alarm_time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            deviceId: self.device_id.clone(),
+            channelId: self.channel_id.clone(),
+            description: self.description.clone(),
+            alarmId: Some(self.id),
+            ackTime: self.ack_time.as_ref().map(to_ts),
+        }
+    }
+
+    //返回告警ID
+    pub async fn insert_gbs_device_alarm(&self) -> GlobalResult<i64> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query("insert into gb_device_alarm (device_id,channel_id,priority,method,alarm_type,alarm_time,description,create_time) values (?,?,?,?,?,?,?,?)")
+            .bind(&self.device_id)
+            .bind(&self.channel_id)
+            .bind(self.priority)
+            .bind(self.method)
+            .bind(self.alarm_type)
+            .bind(self.alarm_time)
+            .bind(&self.description)
+            .bind(self.create_time)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res.last_insert_id() as i64)
+    }
+
+    //按告警时间倒序分页,返回(总数,当前页)
+    pub async fn query_gbs_device_alarms(
+        model: &AlarmQueryModel,
+        start_time: Option<NaiveDateTime>,
+        end_time: Option<NaiveDateTime>,
+        offset: u64,
+        limit: u32,
+    ) -> GlobalResult<(u64, Vec<GbsDeviceAlarm>)> {
+        let pool = get_conn_by_pool()?;
+        let mut builder = sqlx::query_builder::QueryBuilder::new(
+            "select count(*) from gb_device_alarm where 1=1",
+        );
+        Self::push_alarm_filter(&mut builder, model, start_time, end_time);
+        let (total,): (i64,) = builder
+            .build_query_as()
+            .fetch_one(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        if total == 0 {
+            return Ok((0, Vec::new()));
+        }
+        let mut builder = sqlx::query_builder::QueryBuilder::new("select id,device_id,channel_id,priority,method,alarm_type,alarm_time,description,create_time,ack_time from gb_device_alarm where 1=1");
+        Self::push_alarm_filter(&mut builder, model, start_time, end_time);
+        builder
+            .push(" order by alarm_time desc,id desc limit ")
+            .push_bind(limit)
+            .push(" offset ")
+            .push_bind(offset);
+        let res = builder
+            .build_query_as::<GbsDeviceAlarm>()
+            .fetch_all(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok((total as u64, res))
+    }
+
+    fn push_alarm_filter<'a>(
+        builder: &mut sqlx::query_builder::QueryBuilder<'a, sqlx::MySql>,
+        model: &'a AlarmQueryModel,
+        start_time: Option<NaiveDateTime>,
+        end_time: Option<NaiveDateTime>,
+    ) {
+        if let Some(device_id) = model.get_device_id() {
+            builder.push(" and device_id=").push_bind(device_id);
+        }
+        if let Some(channel_id) = model.get_channel_id() {
+            builder.push(" and channel_id=").push_bind(channel_id);
+        }
+        if let Some(start_time) = start_time {
+            builder.push(" and alarm_time>=").push_bind(start_time);
+        }
+        if let Some(end_time) = end_time {
+            builder.push(" and alarm_time<=").push_bind(end_time);
+        }
+        if let Some(priority) = model.get_priority() {
+            builder.push(" and priority=").push_bind(*priority);
+        }
+        if let Some(method) = model.get_method() {
+            builder.push(" and method=").push_bind(*method);
+        }
+        if let Some(alarm_type) = model.get_alarm_type() {
+            builder.push(" and alarm_type=").push_bind(*alarm_type);
+        }
+        match model.get_acked() {
+            Some(true) => {
+                builder.push(" and ack_time is not null");
             }
+            Some(false) => {
+                builder.push(" and ack_time is null");
+            }
+            None => {}
+        }
+    }
+
+    pub async fn query_gbs_device_alarm_by_id(id: i64) -> GlobalResult<Option<GbsDeviceAlarm>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsDeviceAlarm>("select id,device_id,channel_id,priority,method,alarm_type,alarm_time,description,create_time,ack_time from gb_device_alarm where id=?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    //重复确认保留首次确认时间
+    pub async fn ack_gbs_device_alarm(id: i64) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("update gb_device_alarm set ack_time=ifnull(ack_time,?) where id=?")
+            .bind(Local::now().naive_local())
+            .bind(id)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+}
+
+//级联共享目录节点：行政区划、业务分组、虚拟组织,类型由编码区分
+#[derive(Debug, Clone, Default, Get, FromRow)]
+pub struct GbsCascadeNode {
+    platform_id: String,
+    node_id: String,
+    name: String,
+    parent_id: Option<String>,
+    //虚拟组织所属业务分组
+    business_group_id: Option<String>,
+}
+
+impl GbsCascadeNode {
+    pub fn build(model: &CascadeNodeModel) -> Self {
+        GbsCascadeNode {
+            platform_id: model.get_platform_id().clone(),
+            node_id: model.get_node_id().clone(),
+            name: model.get_name().clone(),
+            parent_id: model.get_parent_id().clone().filter(|id| !id.is_empty()),
+            business_group_id: model
+                .get_business_group_id()
+                .clone()
+                .filter(|id| !id.is_empty()),
+        }
+    }
+
+    pub fn to_model(&self) -> CascadeNodeModel {
+        CascadeNodeModel::new(
+            self.platform_id.clone(),
+            self.node_id.clone(),
+            self.name.clone(),
+            self.parent_id.clone(),
+            self.business_group_id.clone(),
+        )
+    }
+
+    pub async fn query_gbs_cascade_nodes(
+        platform_id: &String,
+    ) -> GlobalResult<Vec<GbsCascadeNode>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsCascadeNode>("select platform_id,node_id,name,parent_id,business_group_id from gb_cascade_node where platform_id=? order by node_id")
+            .bind(platform_id)
+            .fetch_all(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    pub async fn save_gbs_cascade_node(&self) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("INSERT INTO gb_cascade_node (platform_id, node_id, name, parent_id, business_group_id) VALUES (?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE name=VALUES(name),parent_id=VALUES(parent_id),business_group_id=VALUES(business_group_id)")
+            .bind(&self.platform_id)
+            .bind(&self.node_id)
+            .bind(&self.name)
+            .bind(&self.parent_id)
+            .bind(&self.business_group_id)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    //删除节点,其下共享通道归属到平台根节点
+    //节点下的子节点数与共享通道数
+    pub async fn count_gbs_cascade_node_refs(
+        platform_id: &String,
+        node_id: &String,
+    ) -> GlobalResult<(i64, i64)> {
+        let pool = get_conn_by_pool()?;
+        let res: (i64, i64) = sqlx::query_as(
+            "select (select count(*) from gb_cascade_node where platform_id=? and parent_id=?),
+            (select count(*) from gb_cascade_channel where platform_id=? and parent_id=?)",
+        )
+        .bind(platform_id)
+        .bind(node_id)
+        .bind(platform_id)
+        .bind(node_id)
+        .fetch_one(pool)
+        .await
+        .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    pub async fn delete_gbs_cascade_node(
+        platform_id: &String,
+        node_id: &String,
+    ) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("delete from gb_cascade_node where platform_id=? and node_id=?")
+            .bind(platform_id)
+            .bind(node_id)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+}
+
+//级联共享通道：share_id为上报上级的通道编码,为空时沿用原编码;parent_id为所属共享节点
+#[derive(Debug, Clone, Default, Get, FromRow)]
+pub struct GbsCascadeChannel {
+    platform_id: String,
+    device_id: String,
+    channel_id: String,
+    share_id: Option<String>,
+    parent_id: Option<String>,
+}
+
+impl GbsCascadeChannel {
+    pub fn build(model: &CascadeChannelModel) -> Self {
+        GbsCascadeChannel {
+            platform_id: model.get_platform_id().clone(),
+            device_id: model.get_device_id().clone(),
+            channel_id: model.get_channel_id().clone(),
+            share_id: model.get_share_id().clone().filter(|id| !id.is_empty()),
+            parent_id: model.get_parent_id().clone().filter(|id| !id.is_empty()),
         }
-        dcs.push(dc);
-        dcs
+    }
+
+    pub fn to_model(&self) -> CascadeChannelModel {
+        CascadeChannelModel::new(
+            self.platform_id.clone(),
+            self.device_id.clone(),
+            self.channel_id.clone(),
+            self.share_id.clone(),
+            self.parent_id.clone(),
+        )
+    }
+
+    //上报上级的通道编码
+    pub fn shared_id(&self) -> &String {
+        self.share_id.as_ref().unwrap_or(&self.channel_id)
+    }
+
+    pub async fn query_gbs_cascade_channels(
+        platform_id: &String,
+    ) -> GlobalResult<Vec<GbsCascadeChannel>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsCascadeChannel>("select platform_id,device_id,channel_id,share_id,parent_id from gb_cascade_channel where platform_id=? order by device_id,channel_id")
+            .bind(platform_id)
+            .fetch_all(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    //按上报编码查找共享通道
+    pub async fn query_gbs_cascade_channel_by_shared_id(
+        platform_id: &String,
+        shared_id: &String,
+    ) -> GlobalResult<Option<GbsCascadeChannel>> {
+        let pool = get_conn_by_pool()?;
+        let res = sqlx::query_as::<_, GbsCascadeChannel>("select platform_id,device_id,channel_id,share_id,parent_id from gb_cascade_channel where platform_id=? and IFNULL(share_id,channel_id)=? limit 1")
+            .bind(platform_id)
+            .bind(shared_id)
+            .fetch_optional(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(res)
+    }
+
+    pub async fn save_gbs_cascade_channel(&self) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query("INSERT INTO gb_cascade_channel (platform_id, device_id, channel_id, share_id, parent_id) VALUES (?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE share_id=VALUES(share_id),parent_id=VALUES(parent_id)")
+            .bind(&self.platform_id)
+            .bind(&self.device_id)
+            .bind(&self.channel_id)
+            .bind(&self.share_id)
+            .bind(&self.parent_id)
+            .execute(pool)
+            .await
+            .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
+    }
+
+    pub async fn delete_gbs_cascade_channel(
+        platform_id: &String,
+        device_id: &String,
+        channel_id: &String,
+    ) -> GlobalResult<()> {
+        let pool = get_conn_by_pool()?;
+        sqlx::query(
+            "delete from gb_cascade_channel where platform_id=? and device_id=? and channel_id=?",
+        )
+        .bind(platform_id)
+        .bind(device_id)
+        .bind(channel_id)
+        .execute(pool)
+        .await
+        .hand_log(|msg| error!("{msg}"))?;
+        Ok(())
     }
 }
 
@@ -324,6 +967,8 @@ pub struct GbsFileInfo {
     pub note: Option<String>,
     pub is_del: Option<i32>,
     pub create_time: Option<NaiveDateTime>,
+    //告警联动产生的文件关联告警ID
+    pub alarm_id: Option<i64>,
 }
 
 impl GbsFileInfo {
@@ -335,7 +980,7 @@ impl GbsFileInfo {
         let mut builder = sqlx::query_builder::QueryBuilder::new(
             "INSERT INTO GBS_FILE_INFO
                  (DEVICE_ID, CHANNEL_ID, BIZ_TIME, BIZ_ID, FILE_TYPE, FILE_SIZE,
-                  FILE_NAME, FILE_FORMAT, DIR_PATH, NOTE, IS_DEL, CREATE_TIME) ",
+                  FILE_NAME, FILE_FORMAT, DIR_PATH, NOTE, IS_DEL, CREATE_TIME, ALARM_ID) ",
         );
         builder.push_values(arr.iter(), |mut b, info| {
             b.push_bind(&info.device_id)
@@ -349,7 +994,8 @@ impl GbsFileInfo {
                 .push_bind(&info.dir_path)
                 .push_bind(&info.note)
                 .push_bind(info.is_del)
-                .push_bind(info.create_time);
+                .push_bind(info.create_time)
+                .push_bind(info.alarm_id);
         });
         builder
             .build()
@@ -364,6 +1010,7 @@ impl GbsFileInfo {
 #[allow(dead_code, unused_imports)]
 mod tests {
     use super::*;
+    use crate::gb::handler::manscdp::{Body, Manscdp, Notify, Response};
     use common::confgen::conf::init_confgen;
     use common::dbx::mysqlx;
     use common::tokio;
@@ -385,6 +1032,7 @@ mod tests {
                 note: Some("test1".into()),
                 is_del: Some(0),
                 create_time: Some(Local::now().naive_local()),
+                alarm_id: None,
             },
             GbsFileInfo {
                 id: None,
@@ -400,6 +1048,7 @@ mod tests {
                 note: Some("test2".into()),
                 is_del: Some(0),
                 create_time: Some(Local::now().naive_local()),
+                alarm_id: None,
             },
             GbsFileInfo {
                 id: None,
@@ -415,6 +1064,7 @@ mod tests {
                 note: Some("test3".into()),
                 is_del: Some(0),
                 create_time: Some(Local::now().naive_local()),
+                alarm_id: None,
             },
             GbsFileInfo {
                 id: None,
@@ -430,6 +1080,7 @@ mod tests {
                 note: Some("test4".into()),
                 is_del: Some(0),
                 create_time: Some(Local::now().naive_local()),
+                alarm_id: None,
             },
         ];
         init();
@@ -547,6 +1198,115 @@ mod tests {
         println!("{:?}", res);
     }
 
+    #[test]
+    fn test_build_gbs_device_preset() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Response>
+<CmdType>PresetQuery</CmdType>
+<SN>17430</SN>
+<DeviceID>34020000001320000001</DeviceID>
+<SumNum>2</SumNum>
+<PresetList Num="2">
+<Item>
+<PresetID>1</PresetID>
+<PresetName>gate</PresetName>
+</Item>
+<Item>
+<PresetID>2</PresetID>
+<PresetName>yard</PresetName>
+</Item>
+</PresetList>
+</Response>"#;
+        let Body::Response(Response::PresetQuery(res)) =
+            Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected PresetQuery");
+        };
+        let (channel_id, sum_num, ls) =
+            GbsDevicePreset::build(&"34020000001110000001".to_string(), &res);
+        assert_eq!(channel_id, "34020000001320000001");
+        assert_eq!(sum_num, Some(2));
+        assert_eq!(ls.len(), 2);
+        assert_eq!(ls[1].preset_id, 2);
+        assert_eq!(ls[1].preset_name.as_deref(), Some("yard"));
+        assert_eq!(ls[0].device_id, "34020000001110000001");
+    }
+
+    #[test]
+    fn test_build_catalog_notify_event() {
+        let xml = r#"<?xml version="1.0" encoding="GB2312"?>
+<Notify>
+<CmdType>Catalog</CmdType>
+<SN>26</SN>
+<DeviceID>34020000001110000001</DeviceID>
+<SumNum>2</SumNum>
+<DeviceList Num="2">
+<Item>
+<DeviceID>34020000001320000001</DeviceID>
+<Event>OFF</Event>
+</Item>
+<Item>
+<DeviceID>34020000001320000002</DeviceID>
+<Name>yard</Name>
+<Status>ON</Status>
+<Event>ADD</Event>
+</Item>
+</DeviceList>
+</Notify>"#;
+        let Body::Notify(Notify::Catalog(list)) = Manscdp::parse(xml.as_bytes()).unwrap().body
+        else {
+            panic!("unexpected Catalog");
+        };
+        let ls = GbsDeviceChannel::build_with_event(&"34020000001110000001".to_string(), &list);
+        assert_eq!(ls.len(), 2);
+        assert_eq!(ls[0].0, "OFF");
+        assert_eq!(ls[0].1.channel_id, "34020000001320000001");
+        assert_eq!(ls[1].0, "ADD");
+        assert_eq!(ls[1].1.name.as_deref(), Some("yard"));
+        assert_eq!(ls[1].1.status, "ON");
+    }
+
+    #[test]
+    fn test_device_position_round_trip() {
+        let pos = MobilePosition {
+            device_id: "34020000001110000001".into(),
+            channel_id: "34020000001320000001".into(),
+            time: 1714523400,
+            longitude: 116.397128,
+            latitude: 39.916527,
+            speed: Some(36.5),
+            direction: None,
+            altitude: None,
+        };
+        let row = GbsDevicePosition::build(&pos);
+        let back = row.to_model();
+        assert_eq!(back.time, pos.time);
+        assert_eq!(back.channel_id, pos.channel_id);
+        assert_eq!(back.speed, Some(36.5));
+    }
+
+    #[test]
+    fn test_device_alarm_round_trip() {
+        let info = AlarmInfo {
+            priority: 1,
+            method: 5,
+            alarmType: 2,
+            timeStr: "2024-05-01 08:30:00".to_string(),
+            deviceId: "34020000001110000001".into(),
+            channelId: "34020000001320000001".into(),
+            description: Some("区域入侵".to_string()),
+            ..Default::default()
+        };
+        let mut row = GbsDeviceAlarm::build(&info);
+        row.id = 7;
+        let back = row.to_model();
+        assert_eq!(back.timeStr, "2024-05-01T08:30:00");
+        assert_eq!(back.alarmId, Some(7));
+        assert_eq!(back.ackTime, None);
+        assert_eq!((back.priority, back.method, back.alarmType), (1, 5, 2));
+        assert_eq!(back.description.as_deref(), Some("区域入侵"));
+    }
+
     fn init() {
         init_confgen("config.yml".to_string());
         let _ = mysqlx::init_conn_pool();
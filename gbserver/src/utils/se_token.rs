@@ -30,9 +30,24 @@ pub fn split_dc(session_id: &str) -> GlobalResult<(String, String)> {
     Ok((dcs[0..20].to_string(), dcs[20..40].to_string()))
 }
 
+//告警联动抓拍：令牌同时签名告警ID,防止上传时篡改关联
+pub fn build_alarm_token(session_id: &str, alarm_id: i64) -> String {
+    let input = format!("{}@{}@{}", KEY, session_id, alarm_id);
+    crypto::generate_token(&input)
+}
+
 pub fn check_token(session_id: &str, token: &str) -> GlobalResult<()> {
     let input = format!("{}@{}", KEY, session_id);
-    let r_token = crypto::generate_token(&input);
+    verify(&input, token)
+}
+
+pub fn check_alarm_token(session_id: &str, alarm_id: i64, token: &str) -> GlobalResult<()> {
+    let input = format!("{}@{}@{}", KEY, session_id, alarm_id);
+    verify(&input, token)
+}
+
+fn verify(input: &str, token: &str) -> GlobalResult<()> {
+    let r_token = crypto::generate_token(input);
     if r_token.eq(token) {
         return Ok(());
     }
@@ -54,5 +69,9 @@ mod test {
         println!("dc_device_id: {}", dc_device_id);
         println!("dc_channel_id: {}", dc_channel_id);
         super::check_token(&session_id, &token).unwrap();
+        let alarm_token = super::build_alarm_token(&session_id, 7);
+        super::check_alarm_token(&session_id, 7, &alarm_token).unwrap();
+        assert!(super::check_alarm_token(&session_id, 8, &alarm_token).is_err());
+        assert!(super::check_token(&session_id, &alarm_token).is_err());
     }
 }
@@ -133,9 +133,567 @@ impl RestApi {
         }
     }
 
+    #[allow(non_snake_case)]
+    #[oai(path = "/play/back/pause", method = "post")]
+    /// 暂停播放历史视频
+    async fn playback_pause(
+        &self,
+        pause: Json<PlayStreamModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let pause_model = pause.0;
+        info!(
+            "back-pause:header = {:?},body = {:?}",
+            &header, &pause_model
+        );
+        match handler::pause(pause_model, header).await {
+            Err(err) => {
+                let err_msg = format!("暂停播放失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/play/back/replay", method = "post")]
+    /// 恢复播放历史视频
+    async fn playback_replay(
+        &self,
+        replay: Json<PlayStreamModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let replay_model = replay.0;
+        info!(
+            "back-replay:header = {:?},body = {:?}",
+            &header, &replay_model
+        );
+        match handler::replay(replay_model, header).await {
+            Err(err) => {
+                let err_msg = format!("恢复播放失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/play/back/save", method = "post")]
+    /// 下载设备录像 transMode 默认0 udp 模式, 1 tcp 被动模式,2 tcp 主动模式; speed 下载倍速[1,2,4] 默认4; 同一通道同时只能下载一路
+    async fn download(
+        &self,
+        down: Json<PlayDownModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<StreamInfo>> {
+        let header = token.0;
+        let down_model = down.0;
+        info!("download:header = {:?},body = {:?}", &header, &down_model);
+        match handler::download(down_model, header).await {
+            Ok(data) => Json(ResultMessageData::build_success(data)),
+            Err(err) => {
+                let err_msg = format!("下载失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/play/save/info/:stream_id", method = "get")]
+    /// 查看录像下载任务状态 state 0 下载中 1 已完成 2 已终止
+    async fn download_info(
+        &self,
+        #[oai(name = "gbs-token")] token: Header<String>,
+        #[oai(name = "stream_id", validator(min_length = "24", max_length = "32"))] stream_id: Path<
+            String,
+        >,
+    ) -> Json<ResultMessageData<DownloadInfo>> {
+        let header = token.0;
+        match handler::download_info(stream_id.0, header).await {
+            Ok(data) => Json(ResultMessageData::build_success(data)),
+            Err(err) => {
+                let err_msg = format!("查看下载任务失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/play/save/break", method = "post")]
+    /// 提前终止录像下载任务
+    async fn save_break(
+        &self,
+        stop: Json<PlayStreamModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let stop_model = stop.0;
+        info!("save_break:header = {:?},body = {:?}", &header, &stop_model);
+        match handler::download_stop(stop_model, header).await {
+            Err(err) => {
+                let err_msg = format!("终止下载失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/record/info", method = "post")]
+    /// 查询设备录像列表 recordType 默认all; secrecy 默认0
+    async fn record_info(
+        &self,
+        record: Json<RecordQueryModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<Vec<RecordItem>>> {
+        let header = token.0;
+        let record_model = record.0;
+        info!(
+            "record_info:header = {:?},body = {:?}",
+            &header, &record_model
+        );
+        match handler::record_info(record_model, header).await {
+            Err(err) => {
+                let err_msg = format!("查询录像失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(items) => Json(ResultMessageData::build_success(items)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/broadcast/start", method = "post")]
+    /// 语音广播：返回协商后的音频发送信息,由流媒体节点向设备推送音频
+    async fn broadcast_start(
+        &self,
+        broadcast: Json<BroadcastModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<BroadcastInfo>> {
+        let header = token.0;
+        let broadcast_model = broadcast.0;
+        info!(
+            "broadcast_start:header = {:?},body = {:?}",
+            &header, &broadcast_model
+        );
+        match handler::broadcast(broadcast_model, header).await {
+            Err(err) => {
+                let err_msg = format!("语音广播失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(info) => Json(ResultMessageData::build_success(info)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/broadcast/stop", method = "post")]
+    /// 停止语音广播
+    async fn broadcast_stop(
+        &self,
+        broadcast: Json<BroadcastModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let broadcast_model = broadcast.0;
+        info!(
+            "broadcast_stop:header = {:?},body = {:?}",
+            &header, &broadcast_model
+        );
+        match handler::broadcast_stop(broadcast_model, header).await {
+            Err(err) => {
+                let err_msg = format!("停止语音广播失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/device/status/:device_id", method = "get")]
+    /// 查询设备状态 timeout 等待设备应答秒数,默认8
+    async fn device_status(
+        &self,
+        #[oai(name = "gbs-token")] token: Header<String>,
+        #[oai(name = "device_id", validator(min_length = "20", max_length = "20"))] device_id: Path<
+            String,
+        >,
+        #[oai(
+            name = "timeout",
+            validator(maximum(value = "30"), minimum(value = "1"))
+        )]
+        timeout: Query<Option<u8>>,
+    ) -> Json<ResultMessageData<DeviceStatusInfo>> {
+        let header = token.0;
+        info!(
+            "device_status:header = {:?},device_id = {}",
+            &header, &device_id.0
+        );
+        match handler::device_status(&device_id.0, timeout.0, header).await {
+            Err(err) => {
+                let err_msg = format!("查询设备状态失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(info) => Json(ResultMessageData::build_success(info)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/device/config/query", method = "post")]
+    /// 查询设备配置 configTypes: BasicParam、VideoParamOpt、SVACEncodeConfig、SVACDecodeConfig、AlarmReport
+    async fn config_download(
+        &self,
+        config: Json<ConfigQueryModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<ConfigDownloadInfo>> {
+        let header = token.0;
+        let config_model = config.0;
+        info!(
+            "config_download:header = {:?},body = {:?}",
+            &header, &config_model
+        );
+        match handler::config_download(config_model, header).await {
+            Err(err) => {
+                let err_msg = format!("查询设备配置失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(info) => Json(ResultMessageData::build_success(info)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/device/config", method = "post")]
+    /// 设备配置：名称、注册过期时间、心跳间隔/次数、告警上报开关
+    async fn device_config(
+        &self,
+        config: Json<DeviceConfigModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let config_model = config.0;
+        info!(
+            "device_config:header = {:?},body = {:?}",
+            &header, &config_model
+        );
+        match handler::device_config(config_model, header).await {
+            Err(err) => {
+                let err_msg = format!("设备配置失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/teleboot", method = "post")]
+    /// 远程启动设备
+    async fn tele_boot(
+        &self,
+        control: Json<DeviceControlModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!(
+            "tele_boot:header = {:?},body = {:?}",
+            &header, &control_model
+        );
+        match handler::tele_boot(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("远程启动失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/record", method = "post")]
+    /// 设备录像控制：开始/停止录像
+    async fn record_control(
+        &self,
+        control: Json<RecordControlModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!(
+            "record_control:header = {:?},body = {:?}",
+            &header, &control_model
+        );
+        match handler::record_control(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("录像控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/guard", method = "post")]
+    /// 布防/撤防
+    async fn guard_control(
+        &self,
+        control: Json<GuardControlModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!(
+            "guard_control:header = {:?},body = {:?}",
+            &header, &control_model
+        );
+        match handler::guard_control(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("布撤防失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/alarm/reset", method = "post")]
+    /// 报警复位
+    async fn alarm_reset(
+        &self,
+        control: Json<AlarmResetModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!(
+            "alarm_reset:header = {:?},body = {:?}",
+            &header, &control_model
+        );
+        match handler::alarm_reset(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("报警复位失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/iframe", method = "post")]
+    /// 强制关键帧
+    async fn iframe(
+        &self,
+        control: Json<DeviceControlModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!("iframe:header = {:?},body = {:?}", &header, &control_model);
+        match handler::iframe(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("强制关键帧失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/drag_zoom", method = "post")]
+    /// 拉框放大/缩小
+    async fn drag_zoom(
+        &self,
+        control: Json<DragZoomModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let control_model = control.0;
+        info!(
+            "drag_zoom:header = {:?},body = {:?}",
+            &header, &control_model
+        );
+        match handler::drag_zoom(control_model, header).await {
+            Err(err) => {
+                let err_msg = format!("拉框缩放失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/device/catalog/sync", method = "post")]
+    /// 同步设备目录：汇总分包后返回新增/更新/下线通道数
+    async fn catalog_sync(
+        &self,
+        body: Json<CatalogSyncModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<CatalogSyncResult>> {
+        let header = token.0;
+        let model = body.0;
+        info!("catalog_sync:header = {:?},body = {:?}", &header, &model);
+        match handler::catalog_sync(model, header).await {
+            Err(err) => {
+                let err_msg = format!("同步设备目录失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/subscribe/alarm", method = "post")]
+    /// 订阅设备报警：可按报警级别、方式、时间过滤
+    async fn subscribe_alarm(
+        &self,
+        body: Json<AlarmSubscribeModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!("subscribe_alarm:header = {:?},body = {:?}", &header, &model);
+        match handler::subscribe_alarm(model, header).await {
+            Err(err) => {
+                let err_msg = format!("订阅报警失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/subscribe/mobile_position", method = "post")]
+    /// 订阅移动设备位置
+    async fn subscribe_mobile_position(
+        &self,
+        body: Json<MobilePositionSubscribeModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "subscribe_mobile_position:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::subscribe_mobile_position(model, header).await {
+            Err(err) => {
+                let err_msg = format!("订阅移动位置失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/subscribe/cancel", method = "post")]
+    /// 取消订阅 kind: Catalog、Alarm、MobilePosition
+    async fn subscribe_cancel(
+        &self,
+        body: Json<SubscribeCancelModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "subscribe_cancel:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::subscribe_cancel(model, header).await {
+            Err(err) => {
+                let err_msg = format!("取消订阅失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/device/position/history", method = "post")]
+    /// 查询移动设备位置轨迹
+    async fn position_history(
+        &self,
+        body: Json<PositionQueryModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<Vec<MobilePosition>>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "position_history:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::position_history(model, header).await {
+            Err(err) => {
+                let err_msg = format!("查询位置轨迹失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/alarm/list", method = "post")]
+    /// 分页查询告警记录,按告警时间倒序
+    async fn alarm_list(
+        &self,
+        body: Json<AlarmQueryModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<AlarmPage>> {
+        let header = token.0;
+        let model = body.0;
+        info!("alarm_list:header = {:?},body = {:?}", &header, &model);
+        match handler::alarm_list(model, header).await {
+            Err(err) => {
+                let err_msg = format!("查询告警失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/alarm/ack", method = "post")]
+    /// 确认告警 reset 是否同时向设备下发报警复位
+    async fn alarm_ack(
+        &self,
+        body: Json<AlarmAckModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!("alarm_ack:header = {:?},body = {:?}", &header, &model);
+        match handler::alarm_ack(model, header).await {
+            Err(err) => {
+                let err_msg = format!("确认告警失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
     #[allow(non_snake_case)]
     #[oai(path = "/control/ptz", method = "post")]
-    /// 云台控制
+    /// 云台控制 duration 移动时长(毫秒),0 持续移动,默认1000
     async fn control_ptz(
         &self,
         ptz: Json<PtzControlModel>,
@@ -153,7 +711,274 @@ impl RestApi {
             Ok(_) => Json(ResultMessageData::build_success(true)),
         }
     }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/fi", method = "post")]
+    /// 光圈/聚焦控制 iris、focus: 0停止 1缩小/近 2放大/远
+    async fn control_ptz_fi(
+        &self,
+        ptz: Json<PtzFiModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let ptz_model = ptz.0;
+        info!(
+            "control_ptz_fi:header = {:?},body = {:?}",
+            &header, &ptz_model
+        );
+        match handler::ptz_fi(ptz_model, header).await {
+            Err(err) => {
+                let err_msg = format!("光圈聚焦控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/preset", method = "post")]
+    /// 预置位 action: 1设置 2调用 3删除
+    async fn control_ptz_preset(
+        &self,
+        ptz: Json<PtzPresetModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let ptz_model = ptz.0;
+        info!(
+            "control_ptz_preset:header = {:?},body = {:?}",
+            &header, &ptz_model
+        );
+        match handler::ptz_preset(ptz_model, header).await {
+            Err(err) => {
+                let err_msg = format!("预置位控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/preset/:device_id/:channel_id", method = "get")]
+    /// 查询通道预置位列表
+    async fn preset_list(
+        &self,
+        #[oai(name = "gbs-token")] token: Header<String>,
+        #[oai(name = "device_id", validator(min_length = "20", max_length = "20"))] device_id: Path<
+            String,
+        >,
+        #[oai(name = "channel_id", validator(min_length = "20", max_length = "20"))]
+        channel_id: Path<String>,
+    ) -> Json<ResultMessageData<Vec<PresetInfo>>> {
+        let header = token.0;
+        info!(
+            "preset_list:header = {:?},device_id = {},channel_id = {}",
+            &header, &device_id.0, &channel_id.0
+        );
+        match handler::preset_list(&device_id.0, &channel_id.0, header).await {
+            Err(err) => {
+                let err_msg = format!("查询预置位失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(ls) => Json(ResultMessageData::build_success(ls)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/cruise", method = "post")]
+    /// 巡航 action: 1加入巡航点 2删除巡航点 3设置速度 4设置停留时间 5开始巡航
+    async fn control_ptz_cruise(
+        &self,
+        ptz: Json<PtzCruiseModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let ptz_model = ptz.0;
+        info!(
+            "control_ptz_cruise:header = {:?},body = {:?}",
+            &header, &ptz_model
+        );
+        match handler::ptz_cruise(ptz_model, header).await {
+            Err(err) => {
+                let err_msg = format!("巡航控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/scan", method = "post")]
+    /// 自动扫描 action: 1开始 2设置左边界 3设置右边界 4设置速度
+    async fn control_ptz_scan(
+        &self,
+        ptz: Json<PtzScanModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let ptz_model = ptz.0;
+        info!(
+            "control_ptz_scan:header = {:?},body = {:?}",
+            &header, &ptz_model
+        );
+        match handler::ptz_scan(ptz_model, header).await {
+            Err(err) => {
+                let err_msg = format!("扫描控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/control/ptz/aux", method = "post")]
+    /// 辅助开关
+    async fn control_ptz_aux(
+        &self,
+        ptz: Json<PtzAuxModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let ptz_model = ptz.0;
+        info!(
+            "control_ptz_aux:header = {:?},body = {:?}",
+            &header, &ptz_model
+        );
+        match handler::ptz_aux(ptz_model, header).await {
+            Err(err) => {
+                let err_msg = format!("辅助开关控制失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(_) => Json(ResultMessageData::build_success(true)),
+        }
+    }
     // #[allow(non_snake_case)]
+    #[allow(non_snake_case)]
+    #[oai(path = "/cascade/share/:platform_id", method = "get")]
+    /// 查询共享给上级平台的节点与通道
+    async fn cascade_share_list(
+        &self,
+        #[oai(name = "gbs-token")] token: Header<String>,
+        #[oai(name = "platform_id", validator(min_length = "20", max_length = "20"))]
+        platform_id: Path<String>,
+    ) -> Json<ResultMessageData<CascadeShareInfo>> {
+        let header = token.0;
+        info!(
+            "cascade_share_list:header = {:?},platform_id = {}",
+            &header, &platform_id.0
+        );
+        match handler::cascade_share_list(&platform_id.0, header).await {
+            Err(err) => {
+                let err_msg = format!("查询级联共享失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/cascade/share/node", method = "post")]
+    /// 新增或修改共享节点：行政区划、业务分组、虚拟组织
+    async fn cascade_node_save(
+        &self,
+        body: Json<CascadeNodeModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "cascade_node_save:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::cascade_node_save(model, header).await {
+            Err(err) => {
+                let err_msg = format!("保存共享节点失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/cascade/share/node/remove", method = "post")]
+    /// 删除共享节点,其下仍有子节点或共享通道时拒绝
+    async fn cascade_node_remove(
+        &self,
+        body: Json<CascadeNodeRemoveModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "cascade_node_remove:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::cascade_node_remove(model, header).await {
+            Err(err) => {
+                let err_msg = format!("删除共享节点失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/cascade/share/channel", method = "post")]
+    /// 新增或修改共享通道 share_id 上报编码,为空时使用原通道编码
+    async fn cascade_channel_save(
+        &self,
+        body: Json<CascadeChannelModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "cascade_channel_save:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::cascade_channel_save(model, header).await {
+            Err(err) => {
+                let err_msg = format!("保存共享通道失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[oai(path = "/cascade/share/channel/remove", method = "post")]
+    /// 取消共享通道
+    async fn cascade_channel_remove(
+        &self,
+        body: Json<CascadeChannelRemoveModel>,
+        #[oai(name = "gbs-token")] token: Header<String>,
+    ) -> Json<ResultMessageData<bool>> {
+        let header = token.0;
+        let model = body.0;
+        info!(
+            "cascade_channel_remove:header = {:?},body = {:?}",
+            &header, &model
+        );
+        match handler::cascade_channel_remove(model, header).await {
+            Err(err) => {
+                let err_msg = format!("取消共享通道失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(res) => Json(ResultMessageData::build_success(res)),
+        }
+    }
+
     // #[oai(path = "/play/back/speed", method = "get")]
     // /// 倍速播放历史视频 speed [1,2,4]
     // async fn playback_speed(&self,
@@ -168,57 +993,6 @@ impl RestApi {
     //     }
     // }
 
-    //
-    // #[allow(non_snake_case)]
-    // #[oai(path = "/play/save/info", method = "get")]
-    // /// 查看录像信息
-    // async fn down_info(&self,
-    //                    #[oai(name = "deviceId", validator(min_length = "20", max_length = "20"))] deviceId: Query<String>,
-    //                    #[oai(name = "channelId", validator(min_length = "20", max_length = "20"))] channelId: Query<String>) -> Json<ResultMessageData<Option<Vec<RecordInfo>>>> {
-    //     match handler::query_down_info(&deviceId.0, &channelId.0).await {
-    //         Err(err) => {
-    //             error!("查看录像信息失败；{}",err);
-    //             Json(ResultMessageData::build_failure())
-    //         }
-    //         Ok(info) => { Json(ResultMessageData::build_success(Some(info))) }
-    //     }
-    // }
-    //
-    // #[allow(non_snake_case)]
-    // #[oai(path = "/play/back/save", method = "get")]
-    // /// 开启监控历史画面云端录制 transMode 默认0 udp 模式, 1 tcp 被动模式,2 tcp 主动模式， 目前只支持 0 下载速度124   同人同录像机同摄像头只能同时下载一路监控
-    // async fn download(&self,
-    //                   #[oai(name = "deviceId", validator(min_length = "20", max_length = "20"))] deviceId: Query<String>,
-    //                   #[oai(name = "channelId", validator(min_length = "20", max_length = "20"))] channelId: Query<String>,
-    //                   #[oai(name = "identity", validator(min_length = "4", max_length = "32"))] _identity: Query<String>,
-    //                   #[oai(name = "fileName")] _fileName: Query<String>,
-    //                   #[oai(name = "st", validator(minimum(value = "1577808000")))] st: Query<u32>,
-    //                   #[oai(name = "et", validator(minimum(value = "1577808001")))] et: Query<u32>,
-    //                   #[oai(name = "speed", validator(maximum(value = "4"), minimum(value = "1")))] _speed: Query<u8>,
-    //                   #[oai(name = "transMode", validator(maximum(value = "2"), minimum(value = "0")))] _transMode: Query<u8>) -> Json<ResultMessageData<Option<bool>>> {
-    //     let dt = Local::now();
-    //     match handler::down(&deviceId.0, &channelId.0, 0, st.0, et.0, 4, "twoLevel", dt.timestamp().to_string()).await {
-    //         Err(err) => {
-    //             error!("下载失败；{}",err);
-    //             Json(ResultMessageData::build_failure())
-    //         }
-    //         Ok(b) => { Json(ResultMessageData::build_success(Some(b))) }
-    //     }
-    // }
-    //
-    // #[allow(non_snake_case)]
-    // #[oai(path = "/play/save/break", method = "get")]
-    // /// 提前终止云端录像任务
-    // async fn save_break(&self,
-    //                     #[oai(name = "id", validator(min_length = "32", max_length = "32"))] id: Query<String>) -> Json<ResultMessageData<Option<bool>>> {
-    //     match handler::teardown_save(&id.0).await {
-    //         Err(err) => {
-    //             error!("终止失败；{}",err);
-    //             Json(ResultMessageData::build_failure())
-    //         }
-    //         Ok(_info) => { Json(ResultMessageData::build_success_none()) }
-    //     }
-    // }
     //
     // #[allow(non_snake_case)]
     // #[oai(path = "/play/back/stream", method = "get")]
@@ -239,31 +1013,4 @@ impl RestApi {
     //         Ok(info) => { Json(ResultMessageData::build_success(Some(info))) }
     //     }
     // }
-
-    // #[allow(non_snake_case)]
-    // #[oai(path = "/play/back/pause", method = "get")]
-    // /// 暂停播放历史视频
-    // async fn playback_pause(&self,
-    //                         #[oai(name = "streamId", validator(min_length = "32", max_length = "32"))] streamId: Query<String>) -> Json<ResultMessageData<Option<ResMsg>>> {
-    //     match handler::pause(&streamId.0).await {
-    //         Err(err) => {
-    //             error!("暂停播放失败；{}",err);
-    //             Json(ResultMessageData::build_failure())
-    //         }
-    //         Ok(msg) => { Json(ResultMessageData::build_success(Some(msg))) }
-    //     }
-    // }
-    // #[allow(non_snake_case)]
-    // #[oai(path = "/play/back/replay", method = "get")]
-    // /// 恢复播放历史视频
-    // async fn playback_replay(&self,
-    //                          #[oai(name = "streamId", validator(min_length = "32", max_length = "32"))] streamId: Query<String>) -> Json<ResultMessageData<Option<ResMsg>>> {
-    //     match handler::replay(&streamId.0).await {
-    //         Err(err) => {
-    //             error!("恢复播放失败；{}",err);
-    //             Json(ResultMessageData::build_failure())
-    //         }
-    //         Ok(msg) => { Json(ResultMessageData::build_success(Some(msg))) }
-    //     }
-    // }
 }
@@ -4,9 +4,9 @@ use crate::service::{
     OnRtpServerTimeoutResponse, OnStreamChangedResponse, OnStreamNoneReaderResponse,
     OnStreamNotFoundResponse, PlayRequest, PlayerCountChangeRequest, PublishRequest,
     RtpServerTimeoutRequest, StreamChangedRequest, StreamNoneReaderRequest, StreamNotFoundRequest,
-    StreamPlayInfo, StreamState,
+    StreamPlayInfo, StreamRecordInfo, StreamState,
 };
-use common::log::info;
+use common::log::{error, info};
 use poem_openapi::payload::Json;
 use poem_openapi::OpenApi;
 pub struct HookApi;
@@ -106,7 +106,7 @@ impl HookApi {
     ) -> Json<ResultMessageData<bool>> {
         let info = stream_state.0;
         info!("stream_input_timeout = {:?}", &info);
-        handler::stream_input_timeout(info);
+        handler::stream_input_timeout(info).await;
         Json(ResultMessageData::build_success_none())
     }
     ///流媒体监测到用户断开点播流：发送一次用户关闭流事件：
@@ -133,4 +133,21 @@ impl HookApi {
             handler::stream_idle(info).await,
         ))
     }
+    ///流媒体录制文件完成：告警联动下载的录像以实际文件入库并关联告警
+    #[oai(path = "/end/record", method = "post")]
+    async fn end_record(
+        &self,
+        stream_record_info: Json<StreamRecordInfo>,
+    ) -> Json<ResultMessageData<bool>> {
+        let info = stream_record_info.0;
+        info!("end_record = {:?}", &info);
+        match handler::end_record(info).await {
+            Err(err) => {
+                let err_msg = format!("录像文件登记失败；{}", err);
+                error!("{}", &err_msg);
+                Json(ResultMessageData::build_failure_msg(err_msg))
+            }
+            Ok(()) => Json(ResultMessageData::build_success(true)),
+        }
+    }
 }
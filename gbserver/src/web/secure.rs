@@ -18,14 +18,20 @@ impl SecureApi {
         #[oai(name = "SessionID")] SessionID: Query<String>,
         #[oai(name = "FileID")] FileID: Query<Option<String>>,
         #[oai(name = "SnapShotFileID")] SnapShotFileID: Query<Option<String>>,
+        #[oai(name = "alarm_id")] alarm_id: Query<Option<i64>>,
         data: Binary<Body>,
     ) {
-        if se_token::check_token(SessionID.0.as_str(), token.0.as_str()).is_ok() {
+        let checked = match alarm_id.0 {
+            None => se_token::check_token(SessionID.0.as_str(), token.0.as_str()),
+            Some(id) => se_token::check_alarm_token(SessionID.0.as_str(), id, token.0.as_str()),
+        };
+        if checked.is_ok() {
             let _ = service::control::upload(
                 data,
                 SessionID.0.clone(),
                 FileID.0.clone(),
                 SnapShotFileID.0.clone(),
+                alarm_id.0,
             )
             .await;
         }
@@ -129,7 +129,7 @@ pub async fn read(
             Err(err) => {
                 info!(
                     "【TCP read failure】 【Local_addr = {}】 【err = {:?}】",
-                    local_addr.to_string(),
+                    local_addr,
                     err,
                 );
                 break;
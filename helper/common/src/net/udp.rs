@@ -54,8 +54,8 @@ pub async fn read(local_addr: SocketAddr, udp_socket: &UdpSocket, tx: Sender<Zip
             Ok((len, remote_addr)) => {
                 if len != 0 {
                     debug!("【UDP read success】 【Local_addr = {}】 【Remote_addr = {}】 【len = {}】",
-                            local_addr.to_string(),
-                            remote_addr.to_string(),
+                            local_addr,
+                            remote_addr,
                             len
                             );
                     let association = Association::new(local_addr, remote_addr, Protocol::UDP);
@@ -73,7 +73,7 @@ pub async fn read(local_addr: SocketAddr, udp_socket: &UdpSocket, tx: Sender<Zip
             Err(err) => {
                 warn!(
                     "【UDP read failure】 【Local_addr = {}】 【err = {:?}】",
-                    local_addr.to_string(),
+                    local_addr,
                     err,
                 );
                 break;